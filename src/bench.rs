@@ -0,0 +1,197 @@
+//! Throughput benchmarks for an initialised external memory
+//!
+//! These benchmarks measure the read and write bandwidth of the region
+//! returned by an `init` method, to help choose settings such as
+//! [`read_burst`](crate::SdramConfiguration::read_burst),
+//! [`read_pipe_delay_cycles`](crate::SdramConfiguration::read_pipe_delay_cycles)
+//! and the MPU attributes of the region. All accesses are volatile and 32
+//! bits wide. The previous contents of the memory are destroyed.
+//!
+//! Time is measured with a [`CycleCounter`], usually the DWT cycle counter
+//! of the core.
+//!
+//! ```ignore
+//! use stm32_fmc::bench;
+//!
+//! let report = bench::run_all(memory, || cortex_m::peripheral::DWT::cycle_count());
+//! let read = report.sequential_read.bytes_per_second(480_000_000);
+//! ```
+
+use core::ptr;
+
+/// A free-running 32-bit cycle counter
+///
+/// Implemented for closures returning the current count. The counter wraps
+/// after 2^32 cycles, about 9 seconds at 480 MHz, so each benchmark must take
+/// less time than that.
+pub trait CycleCounter {
+    /// Current value of the counter
+    fn cycles(&mut self) -> u32;
+}
+
+impl<F: FnMut() -> u32> CycleCounter for F {
+    fn cycles(&mut self) -> u32 {
+        self()
+    }
+}
+
+/// Number of bytes transferred in a number of cycles
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Throughput {
+    /// Bytes transferred
+    pub bytes: usize,
+    /// Cycles taken. Only correct if the transfer took less than 2^32 cycles
+    pub cycles: u32,
+}
+
+impl Throughput {
+    /// Bytes per second, for a counter running at `counter_hz`
+    pub fn bytes_per_second(&self, counter_hz: u32) -> u64 {
+        let cycles = u64::from(self.cycles.max(1));
+        self.bytes as u64 * u64::from(counter_hz) / cycles
+    }
+}
+
+/// Results of all the benchmarks
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BenchReport {
+    /// See [`sequential_read`]
+    pub sequential_read: Throughput,
+    /// See [`sequential_write`]
+    pub sequential_write: Throughput,
+    /// See [`random_read`]
+    pub random_read: Throughput,
+    /// See [`random_write`]
+    pub random_write: Throughput,
+}
+
+/// Index of the `n`th word in a pseudo-random order
+///
+/// A linear congruential generator, so that the order is the same on every
+/// run.
+fn random_index(state: &mut u32, len: usize) -> usize {
+    *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    *state as usize % len
+}
+
+/// Time `f`, which transfers all of `len` words
+fn measure<C: CycleCounter>(
+    counter: &mut C,
+    len: usize,
+    f: impl FnOnce(),
+) -> Throughput {
+    let start = counter.cycles();
+    f();
+    let end = counter.cycles();
+
+    Throughput {
+        bytes: len * 4,
+        cycles: end.wrapping_sub(start),
+    }
+}
+
+/// Cycles taken to generate `len` pseudo-random indices, without accessing
+/// the memory
+///
+/// Subtracted from the random benchmarks, so that they measure the memory
+/// rather than the generator.
+fn random_overhead<C: CycleCounter>(counter: &mut C, len: usize) -> u32 {
+    let mut state = 1;
+    let mut index = 0;
+    measure(counter, len, || {
+        for _ in 0..len {
+            unsafe {
+                ptr::write_volatile(&mut index, random_index(&mut state, len))
+            };
+        }
+    })
+    .cycles
+}
+
+/// Read every word of `memory` in order
+pub fn sequential_read<C: CycleCounter>(
+    memory: &[u32],
+    counter: &mut C,
+) -> Throughput {
+    measure(counter, memory.len(), || {
+        for word in memory {
+            let _ = unsafe { ptr::read_volatile(word) };
+        }
+    })
+}
+
+/// Write every word of `memory` in order
+pub fn sequential_write<C: CycleCounter>(
+    memory: &mut [u32],
+    counter: &mut C,
+) -> Throughput {
+    let len = memory.len();
+    measure(counter, len, || {
+        for word in memory.iter_mut() {
+            unsafe { ptr::write_volatile(word, 0) };
+        }
+    })
+}
+
+/// Read as many words as `memory` holds, at pseudo-random addresses
+///
+/// The time taken to generate the addresses is measured separately and
+/// subtracted.
+pub fn random_read<C: CycleCounter>(
+    memory: &[u32],
+    counter: &mut C,
+) -> Throughput {
+    let len = memory.len();
+    let overhead = random_overhead(counter, len);
+    let mut state = 1;
+    let throughput = measure(counter, len, || {
+        for _ in 0..len {
+            let word = &memory[random_index(&mut state, len)];
+            let _ = unsafe { ptr::read_volatile(word) };
+        }
+    });
+
+    Throughput {
+        cycles: throughput.cycles.saturating_sub(overhead),
+        ..throughput
+    }
+}
+
+/// Write as many words as `memory` holds, at pseudo-random addresses
+///
+/// The time taken to generate the addresses is measured separately and
+/// subtracted.
+pub fn random_write<C: CycleCounter>(
+    memory: &mut [u32],
+    counter: &mut C,
+) -> Throughput {
+    let len = memory.len();
+    let overhead = random_overhead(counter, len);
+    let mut state = 1;
+    let throughput = measure(counter, len, || {
+        for _ in 0..len {
+            let word = &mut memory[random_index(&mut state, len)];
+            unsafe { ptr::write_volatile(word, 0) };
+        }
+    });
+
+    Throughput {
+        cycles: throughput.cycles.saturating_sub(overhead),
+        ..throughput
+    }
+}
+
+/// Run all the benchmarks
+pub fn run_all<C: CycleCounter>(
+    memory: &mut [u32],
+    mut counter: C,
+) -> BenchReport {
+    BenchReport {
+        sequential_write: sequential_write(memory, &mut counter),
+        sequential_read: sequential_read(memory, &mut counter),
+        random_write: random_write(memory, &mut counter),
+        random_read: random_read(memory, &mut counter),
+    }
+}
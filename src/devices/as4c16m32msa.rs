@@ -1,6 +1,5 @@
 /// Alliance Memory AS4C16M32MSA SDRAM
 #[allow(unused)]
-
 pub mod as4c16m32msa_6 {
     use crate::sdram::{SdramChip, SdramConfiguration, SdramTiming};
 
@@ -42,6 +41,7 @@ pub mod as4c16m32msa_6 {
             row_cycle: 10,                // tRC = 60ns
             row_precharge: 3,             // tRP = 18ns
             row_to_column: 3,             // tRCD = 18ns
+            auto_refresh_commands: 8,     // 8 auto-refresh commands
         };
 
         /// SDRAM controller configuration
@@ -56,4 +56,6 @@ pub mod as4c16m32msa_6 {
             read_pipe_delay_cycles: 0,
         };
     }
+
+    const _: () = crate::sdram::check_sdram_chip::<As4c16m32msa>();
 }
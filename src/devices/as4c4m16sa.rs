@@ -1,7 +1,6 @@
 /// Alliance Memory AS4C4M16SA SDRAM
 /// <https://www.alliancememory.com/wp-content/uploads/pdf/dram/Alliance_Memory_64M-AS4C4M16SA-CI_v5.0_October_2018.pdf>
 #[allow(unused)]
-
 pub mod as4c4m16sa_6 {
     use crate::sdram::{SdramChip, SdramConfiguration, SdramTiming};
 
@@ -56,6 +55,7 @@ pub mod as4c4m16sa_6 {
             row_cycle: 10, // tRC = 60ns cycles = ceil(166000000*(60*10^(-9)))
             row_precharge: 3, // tRP = 18ns cycles = ceil(166000000*(18*10^(-9)))
             row_to_column: 3, // tRCD = 18ns cycles = ceil(166000000*(18*10^(-9)))
+            auto_refresh_commands: 8, // 8 auto-refresh commands
         };
 
         /// SDRAM controller configuration
@@ -70,4 +70,6 @@ pub mod as4c4m16sa_6 {
             read_pipe_delay_cycles: 0,
         };
     }
+
+    const _: () = crate::sdram::check_sdram_chip::<As4c4m16sa>();
 }
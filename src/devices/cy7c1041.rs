@@ -0,0 +1,37 @@
+/// Cypress CY7C1041 4 Mbit (256K x 16) asynchronous SRAM
+#[allow(unused)]
+/// Speed Grade 10
+pub mod cy7c1041_10 {
+    use crate::sram::SramConfiguration;
+
+    /// CY7C1041 with 10 ns access time
+    ///
+    /// Timings are given for an FMC kernel clock of up to 200 MHz (5 ns)
+    pub const CONFIG: SramConfiguration = SramConfiguration {
+        memory_data_width: 16,    // 16-bit
+        address_setup_cycles: 1,  // tSA = 0ns, tHZWE = 5ns
+        address_hold_cycles: 1,   // unused in mode 1
+        data_setup_cycles: 3,     // tAA = 10ns, tPWE = 7ns, tSD = 5ns
+        bus_turnaround_cycles: 1, // tHZOE = 5ns
+        write_enable: true,
+    };
+}
+
+/// Cypress CY7C1041 4 Mbit (256K x 16) asynchronous SRAM
+#[allow(unused)]
+/// Speed Grade 12
+pub mod cy7c1041_12 {
+    use crate::sram::SramConfiguration;
+
+    /// CY7C1041 with 12 ns access time
+    ///
+    /// Timings are given for an FMC kernel clock of up to 200 MHz (5 ns)
+    pub const CONFIG: SramConfiguration = SramConfiguration {
+        memory_data_width: 16,    // 16-bit
+        address_setup_cycles: 1,  // tSA = 0ns, tHZWE = 6ns
+        address_hold_cycles: 1,   // unused in mode 1
+        data_setup_cycles: 4,     // tAA = 12ns, tPWE = 8ns, tSD = 6ns
+        bus_turnaround_cycles: 2, // tHZOE = 6ns
+        write_enable: true,
+    };
+}
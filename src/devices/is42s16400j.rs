@@ -1,6 +1,5 @@
 /// ISI IS42S16400J SDRAM
 #[allow(unused)]
-
 /// Speed Grade 7
 pub mod is42s16400j_7 {
     use crate::sdram::{SdramChip, SdramConfiguration, SdramTiming};
@@ -42,6 +41,7 @@ pub mod is42s16400j_7 {
             row_cycle: 7,                 // tRC = 63ns
             row_precharge: 2,             // tRP = 15ns
             row_to_column: 2,             // tRCD = 15ns
+            auto_refresh_commands: 8,     // 8 auto-refresh commands
         };
 
         /// SDRAM controller configuration
@@ -56,4 +56,6 @@ pub mod is42s16400j_7 {
             read_pipe_delay_cycles: 0,
         };
     }
+
+    const _: () = crate::sdram::check_sdram_chip::<Is42s16400j>();
 }
@@ -10,6 +10,21 @@ mod as4c16m32msa;
 #[cfg(feature = "sdram")]
 pub use as4c16m32msa::*;
 
+#[cfg(feature = "sdram")]
+mod as4c8m16sa;
+#[cfg(feature = "sdram")]
+pub use as4c8m16sa::*;
+
+#[cfg(feature = "sdram")]
+mod is42s16160j;
+#[cfg(feature = "sdram")]
+pub use is42s16160j::*;
+
+#[cfg(feature = "sdram")]
+mod is42s16320f;
+#[cfg(feature = "sdram")]
+pub use is42s16320f::*;
+
 #[cfg(feature = "sdram")]
 mod is42s16400j;
 #[cfg(feature = "sdram")]
@@ -25,12 +40,42 @@ mod is42s32800g;
 #[cfg(feature = "sdram")]
 pub use is42s32800g::*;
 
+#[cfg(feature = "sdram")]
+mod mt48lc16m16a2;
+#[cfg(feature = "sdram")]
+pub use mt48lc16m16a2::*;
+
 #[cfg(feature = "sdram")]
 mod mt48lc4m32b2;
 #[cfg(feature = "sdram")]
 pub use mt48lc4m32b2::*;
 
+#[cfg(feature = "sdram")]
+mod mt48lc8m16a2;
+#[cfg(feature = "sdram")]
+pub use mt48lc8m16a2::*;
+
+#[cfg(feature = "sdram")]
+mod w9825g6kh;
+#[cfg(feature = "sdram")]
+pub use w9825g6kh::*;
+
+#[cfg(feature = "sdram")]
+mod w9864g6jt;
+#[cfg(feature = "sdram")]
+pub use w9864g6jt::*;
+
 #[cfg(feature = "nand")]
 mod s34ml08g3;
 #[cfg(feature = "nand")]
 pub use s34ml08g3::*;
+
+#[cfg(feature = "sram")]
+mod cy7c1041;
+#[cfg(feature = "sram")]
+pub use cy7c1041::*;
+
+#[cfg(feature = "sram")]
+mod mt45w8mw16;
+#[cfg(feature = "sram")]
+pub use mt45w8mw16::*;
@@ -0,0 +1,22 @@
+/// Micron MT45W8MW16 128 Mbit (8M x 16) CellularRAM PSRAM
+#[allow(unused)]
+/// Speed Grade 70, asynchronous mode
+///
+/// The device powers on in asynchronous mode, where it behaves like an SRAM.
+/// Burst mode, including the CPSIZE and NWAIT settings of the FMC, is not
+/// supported by [`Sram`](crate::Sram).
+pub mod mt45w8mw16_70 {
+    use crate::sram::SramConfiguration;
+
+    /// MT45W8MW16 with 70 ns access time, in asynchronous mode
+    ///
+    /// Timings are given for an FMC kernel clock of up to 200 MHz (5 ns)
+    pub const CONFIG: SramConfiguration = SramConfiguration {
+        memory_data_width: 16,    // 16-bit
+        address_setup_cycles: 1,  // tAS = 0ns
+        address_hold_cycles: 1,   // unused in mode 1
+        data_setup_cycles: 14,    // tAA = 70ns, tWP = 46ns, tDW = 23ns
+        bus_turnaround_cycles: 2, // tOHZ = 8ns
+        write_enable: true,
+    };
+}
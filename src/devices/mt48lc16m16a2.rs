@@ -0,0 +1,102 @@
+/// Micron MT48LC16M16A2 SDRAM
+#[allow(unused)]
+/// Speed Grade 6A
+pub mod mt48lc16m16a2_6a {
+    use crate::sdram::{
+        Cas, ModeRegister, SdramChip, SdramConfiguration, SdramTiming,
+    };
+
+    /// MT48LC16M16A2 with Speed Grade 6A
+    ///
+    /// Configured with CAS latency 3, limited 100MHz
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Mt48lc16m16a2 {}
+
+    impl SdramChip for Mt48lc16m16a2 {
+        /// Value of the mode register
+        const MODE_REGISTER: u16 = ModeRegister::new()
+            .burst_length(1)
+            .cas_latency(Cas::Three)
+            .single_write()
+            .checked(&Self::CONFIG);
+
+        /// Timing Parameters
+        const TIMING: SdramTiming = SdramTiming {
+            startup_delay_ns: 100_000,    // 100 µs
+            max_sd_clock_hz: 100_000_000, // 100 MHz
+            refresh_period_ns: 7_812,     // 64ms / (8192 rows) = 7812ns
+            mode_register_to_active: 2,   // tMRD = 2 cycles
+            exit_self_refresh: 7,         // tXSR = 67ns
+            active_to_precharge: 5,       // tRAS = 42ns
+            row_cycle: 6,                 // tRC = 60ns
+            row_precharge: 2,             // tRP = 18ns
+            row_to_column: 2,             // tRCD = 18ns
+            auto_refresh_commands: 8,     // 8 auto-refresh commands
+        };
+
+        /// SDRAM controller configuration
+        const CONFIG: SdramConfiguration = SdramConfiguration {
+            column_bits: 9,
+            row_bits: 13,
+            memory_data_width: 16, // 16-bit
+            internal_banks: 4,     // 4 internal banks
+            cas_latency: 3,        // CAS latency = 3
+            write_protection: false,
+            read_burst: true,
+            read_pipe_delay_cycles: 0,
+        };
+    }
+
+    const _: () = crate::sdram::check_sdram_chip::<Mt48lc16m16a2>();
+}
+
+#[allow(unused)]
+/// Speed Grade 75
+pub mod mt48lc16m16a2_75 {
+    use crate::sdram::{
+        Cas, ModeRegister, SdramChip, SdramConfiguration, SdramTiming,
+    };
+
+    /// MT48LC16M16A2 with Speed Grade 75
+    ///
+    /// Configured with CAS latency 3, limited 100MHz
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Mt48lc16m16a2 {}
+
+    impl SdramChip for Mt48lc16m16a2 {
+        /// Value of the mode register
+        const MODE_REGISTER: u16 = ModeRegister::new()
+            .burst_length(1)
+            .cas_latency(Cas::Three)
+            .single_write()
+            .checked(&Self::CONFIG);
+
+        /// Timing Parameters
+        const TIMING: SdramTiming = SdramTiming {
+            startup_delay_ns: 100_000,    // 100 µs
+            max_sd_clock_hz: 100_000_000, // 100 MHz
+            refresh_period_ns: 7_812,     // 64ms / (8192 rows) = 7812ns
+            mode_register_to_active: 2,   // tMRD = 2 cycles
+            exit_self_refresh: 8,         // tXSR = 75ns
+            active_to_precharge: 5,       // tRAS = 44ns
+            row_cycle: 7,                 // tRC = 66ns
+            row_precharge: 2,             // tRP = 20ns
+            row_to_column: 2,             // tRCD = 20ns
+            auto_refresh_commands: 8,     // 8 auto-refresh commands
+        };
+
+        /// SDRAM controller configuration
+        const CONFIG: SdramConfiguration = SdramConfiguration {
+            column_bits: 9,
+            row_bits: 13,
+            memory_data_width: 16, // 16-bit
+            internal_banks: 4,     // 4 internal banks
+            cas_latency: 3,        // CAS latency = 3
+            write_protection: false,
+            read_burst: true,
+            read_pipe_delay_cycles: 0,
+        };
+    }
+
+    const _: () = crate::sdram::check_sdram_chip::<Mt48lc16m16a2>();
+}
@@ -1,6 +1,5 @@
 /// Micron MT48LC4M32B2 SDRAM
 #[allow(unused)]
-
 /// Speed Grade 6
 pub mod mt48lc4m32b2_6 {
     use crate::sdram::{SdramChip, SdramConfiguration, SdramTiming};
@@ -40,6 +39,7 @@ pub mod mt48lc4m32b2_6 {
             row_cycle: 7,                 // tRC = 70ns
             row_precharge: 2,             // tRP = 18ns
             row_to_column: 2,             // tRCD = 18ns
+            auto_refresh_commands: 8,     // 8 auto-refresh commands
         };
 
         /// SDRAM controller configuration
@@ -54,4 +54,6 @@ pub mod mt48lc4m32b2_6 {
             read_pipe_delay_cycles: 0,
         };
     }
+
+    const _: () = crate::sdram::check_sdram_chip::<Mt48lc4m32b2>();
 }
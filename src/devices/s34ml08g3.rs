@@ -1,6 +1,5 @@
 /// SkyHigh S34ML08G3 SLC NAND Flash
 #[allow(unused)]
-
 /// SkyHigh S34ML08G3 SLC NAND Flash with 4kB pages
 pub mod s34ml08g3_4kb {
     use crate::nand::{NandChip, NandConfiguration, NandTiming};
@@ -29,6 +28,7 @@ pub mod s34ml08g3_4kb {
         const CONFIG: NandConfiguration = NandConfiguration {
             data_width: 8,   // 8-bit
             column_bits: 12, // 4096 byte pages
+            ecc_sector_bytes: 512,
         };
     }
 }
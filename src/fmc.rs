@@ -13,7 +13,7 @@ pub enum FmcBank {
     Bank2,
     /// Bank3: NAND Flash
     Bank3,
-    /// Bank4:
+    /// Bank4: PC Card (FSMC only)
     Bank4,
     /// Bank5: SDRAM 1
     Bank5,
@@ -66,6 +66,39 @@ address_pin_markers!(
 #[cfg(feature = "sdram")]
 use crate::sdram::{PinsSdram, SdramBank1, SdramBank2};
 
+#[cfg(feature = "sdram")]
+macro_rules! impl_8bit_sdram {
+    ($($pins:tt: [$ckeN:tt, $neN:tt,
+                  $nInternalB:expr
+                  $(, $pba1:ident: $ba1:tt)* ; // BA1 pins
+                  $addressPins:ident
+                  [ $($pa:ident: $a:ident),* ] // Address pins
+    ]),+) => {
+        $(
+            #[rustfmt::skip]
+            /// 8-bit SDRAM
+            impl<PA0, PA1, PA2, PA3, PA4, PA5, PA6, PA7, PA8, PA9, PA10, $($pa,)*
+            PBA0, $($pba1,)* PD0, PD1, PD2, PD3, PD4, PD5, PD6, PD7, PNBL0,
+            PSDCKEn, PSDCLK, PSDNCAS, PSDNEn, PSDNRAS, PSDNWE>
+                PinsSdram<$pins, $addressPins>
+                for (PA0, PA1, PA2, PA3, PA4, PA5, PA6, PA7, PA8, PA9, PA10, $($pa,)*
+                     PBA0, $($pba1,)* PD0, PD1, PD2, PD3, PD4, PD5, PD6, PD7,
+                     PNBL0, PSDCKEn, PSDCLK, PSDNCAS, PSDNEn, PSDNRAS, PSDNWE)
+            where PA0: A0, PA1: A1, PA2: A2, PA3: A3, PA4: A4, PA5: A5, PA6: A6,
+                  PA7: A7, PA8: A8, PA9: A9, PA10: A10, $($pa:$a,)*
+                  PBA0: BA0, $($pba1:$ba1,)*
+                  PD0: D0, PD1: D1, PD2: D2, PD3: D3, PD4: D4, PD5: D5, PD6: D6,
+                  PD7: D7,
+                  PNBL0: NBL0, PSDCKEn: $ckeN, PSDCLK: SDCLK,
+                  PSDNCAS: SDNCAS, PSDNEn: $neN, PSDNRAS: SDNRAS, PSDNWE: SDNWE {
+
+                const NUMBER_INTERNAL_BANKS: u8 = $nInternalB;
+                const N_BYTE_LANES: u8 = 1;
+            }
+        )+
+    }
+}
+
 #[cfg(feature = "sdram")]
 macro_rules! impl_16bit_sdram {
     ($($pins:tt: [$ckeN:tt, $neN:tt,
@@ -96,6 +129,7 @@ macro_rules! impl_16bit_sdram {
                   PSDNCAS: SDNCAS, PSDNEn: $neN, PSDNRAS: SDNRAS, PSDNWE: SDNWE {
 
                 const NUMBER_INTERNAL_BANKS: u8 = $nInternalB;
+                const N_BYTE_LANES: u8 = 2;
             }
         )+
     }
@@ -139,11 +173,34 @@ macro_rules! impl_32bit_sdram {
                   PSDNCAS: SDNCAS, PSDNEn: $neN, PSDNRAS: SDNRAS, PSDNWE: SDNWE {
 
                 const NUMBER_INTERNAL_BANKS: u8 = $nInternalB;
+                const N_BYTE_LANES: u8 = 4;
             }
         )+
     }
 }
 
+#[cfg(feature = "sdram")]
+impl_8bit_sdram! {
+    // 8-bit SDRAM with 11 address lines, BA0 only
+    SdramBank1: [SDCKE0, SDNE0, 2; AddressPins11 []],
+    SdramBank2: [SDCKE1, SDNE1, 2; AddressPins11 []],
+    // 8-bit SDRAM with 11 address lines, BA0 and BA1
+    SdramBank1: [SDCKE0, SDNE0, 4, PBA1: BA1; AddressPins11 []],
+    SdramBank2: [SDCKE1, SDNE1, 4, PBA1: BA1; AddressPins11 []],
+    // 8-bit SDRAM with 12 address lines, BA0 only
+    SdramBank1: [SDCKE0, SDNE0, 2; AddressPins12 [PA11: A11]],
+    SdramBank2: [SDCKE1, SDNE1, 2; AddressPins12 [PA11: A11]],
+    // 8-bit SDRAM with 12 address lines, BA0 and BA1
+    SdramBank1: [SDCKE0, SDNE0, 4, PBA1: BA1; AddressPins12 [PA11: A11]],
+    SdramBank2: [SDCKE1, SDNE1, 4, PBA1: BA1; AddressPins12 [PA11: A11]],
+    // 8-bit SDRAM with 13 address lines, BA0 only
+    SdramBank1: [SDCKE0, SDNE0, 2; AddressPins13 [PA11: A11, PA12: A12]],
+    SdramBank2: [SDCKE1, SDNE1, 2; AddressPins13 [PA11: A11, PA12: A12]],
+    // 8-bit SDRAM with 13 address lines, BA0 and BA1
+    SdramBank1: [SDCKE0, SDNE0, 4, PBA1: BA1; AddressPins13 [PA11: A11, PA12: A12]],
+    SdramBank2: [SDCKE1, SDNE1, 4, PBA1: BA1; AddressPins13 [PA11: A11, PA12: A12]]
+}
+
 #[cfg(feature = "sdram")]
 impl_16bit_sdram! {
     // 16-bit SDRAM with 11 address lines, BA0 only
@@ -205,6 +262,169 @@ where ALE: A17, CLE: A16,
     const N_DATA: usize = 8;
 }
 
+#[cfg(feature = "nand")]
+#[rustfmt::skip]
+/// 16-bit NAND
+impl<ALE, CLE, PD0, PD1, PD2, PD3, PD4, PD5, PD6, PD7, PD8, PD9, PD10, PD11,
+     PD12, PD13, PD14, PD15, PNCE, PNOE, PNWE, PNWAIT>
+    PinsNand
+    for (ALE, CLE, PD0, PD1, PD2, PD3, PD4, PD5, PD6, PD7, PD8, PD9, PD10, PD11,
+         PD12, PD13, PD14, PD15, PNCE, PNOE, PNWE, PNWAIT)
+where ALE: A17, CLE: A16,
+      PD0: D0, PD1: D1, PD2: D2, PD3: D3, PD4: D4, PD5: D5, PD6: D6, PD7: D7,
+      PD8: D8, PD9: D9, PD10: D10, PD11: D11, PD12: D12, PD13: D13, PD14: D14,
+      PD15: D15,
+      PNCE: NCE, PNOE: NOE, PNWE: NWE, PNWAIT: NWAIT {
+    const N_DATA: usize = 16;
+}
+
+// ---- SRAM ----
+
+#[cfg(feature = "sram")]
+use crate::sram::{PinsSram, SramBank1, SramBank2, SramBank3, SramBank4};
+
+#[cfg(feature = "sram")]
+macro_rules! impl_sram {
+    ($($pins:ident: $neN:ident),+) => {
+        $(
+            impl_sram!(@width $pins, $neN, 8;
+                [PD0: D0, PD1: D1, PD2: D2, PD3: D3, PD4: D4, PD5: D5, PD6: D6,
+                 PD7: D7]
+                []);
+            impl_sram!(@width $pins, $neN, 16;
+                [PD0: D0, PD1: D1, PD2: D2, PD3: D3, PD4: D4, PD5: D5, PD6: D6,
+                 PD7: D7, PD8: D8, PD9: D9, PD10: D10, PD11: D11, PD12: D12,
+                 PD13: D13, PD14: D14, PD15: D15]
+                [PNBL0: NBL0, PNBL1: NBL1]);
+            impl_sram!(@width $pins, $neN, 32;
+                [PD0: D0, PD1: D1, PD2: D2, PD3: D3, PD4: D4, PD5: D5, PD6: D6,
+                 PD7: D7, PD8: D8, PD9: D9, PD10: D10, PD11: D11, PD12: D12,
+                 PD13: D13, PD14: D14, PD15: D15, PD16: D16, PD17: D17,
+                 PD18: D18, PD19: D19, PD20: D20, PD21: D21, PD22: D22,
+                 PD23: D23, PD24: D24, PD25: D25, PD26: D26, PD27: D27,
+                 PD28: D28, PD29: D29, PD30: D30, PD31: D31]
+                [PNBL0: NBL0, PNBL1: NBL1, PNBL2: NBL2, PNBL3: NBL3]);
+        )+
+    };
+    (@width $pins:ident, $neN:ident, $width:expr;
+     [$($PD:ident: $D:ident),+] [$($PBL:ident: $BL:ident),*]) => {
+        #[rustfmt::skip]
+        impl<$($PD,)+ $($PBL,)* PNE, PNOE, PNWE>
+            PinsSram<$pins>
+            for ($($PD,)+ $($PBL,)* PNE, PNOE, PNWE)
+        where $($PD: $D,)+ $($PBL: $BL,)*
+              PNE: $neN, PNOE: NOE, PNWE: NWE {
+            const DATA_WIDTH: u8 = $width;
+        }
+    };
+}
+
+#[cfg(feature = "sram")]
+impl_sram! {
+    SramBank1: NE1,
+    SramBank2: NE2,
+    SramBank3: NE3,
+    SramBank4: NE4
+}
+
+// ---- Custom pin sets ----
+
+/// Define a struct of pins that can be passed to [`Sdram::new`](crate::Sdram::new)
+///
+/// Grouping pins in a named struct may be cleaner than a long tuple. The
+/// fields must be listed in the same order as the equivalent tuple (see
+/// [`PinsSdram`](crate::PinsSdram)), and the same constraints apply: the
+/// struct only implements `PinsSdram` for the given SDRAM bank and number of
+/// address pins if the equivalent tuple does. Otherwise compilation fails at
+/// the point where the struct is defined.
+///
+/// ```ignore
+/// stm32_fmc::define_sdram_pinset! {
+///     /// SDRAM pins on my board
+///     pub struct BoardSdramPins: SdramBank2, AddressPins12 {
+///         pub a0: PF0<Alternate<12>>,
+///         // ... A1 - A11, BA0 - BA1, D0 - D15, NBL0 - NBL1
+///         pub sdcke1: PH7<Alternate<12>>,
+///         pub sdclk: PG8<Alternate<12>>,
+///         pub sdncas: PG15<Alternate<12>>,
+///         pub sdne1: PH6<Alternate<12>>,
+///         pub sdnras: PF11<Alternate<12>>,
+///         pub sdnwe: PH5<Alternate<12>>,
+///     }
+/// }
+/// ```
+#[cfg(feature = "sdram")]
+#[macro_export]
+macro_rules! define_sdram_pinset {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $Name:ident: $Bank:ty, $Address:ty {
+            $( $(#[$fmeta:meta])* $fvis:vis $field:ident: $Pin:ty ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $Name {
+            $( $(#[$fmeta])* $fvis $field: $Pin ),+
+        }
+
+        impl $crate::PinsSdram<$Bank, $Address> for $Name
+        where
+            ( $($Pin,)+ ): $crate::PinsSdram<$Bank, $Address>,
+        {
+            const NUMBER_INTERNAL_BANKS: u8 = <( $($Pin,)+ ) as
+                $crate::PinsSdram<$Bank, $Address>>::NUMBER_INTERNAL_BANKS;
+            const N_BYTE_LANES: u8 = <( $($Pin,)+ ) as
+                $crate::PinsSdram<$Bank, $Address>>::N_BYTE_LANES;
+        }
+    };
+}
+
+/// Define a struct of pins that can be passed to [`Nand::new`](crate::Nand::new)
+///
+/// Grouping pins in a named struct may be cleaner than a long tuple. The
+/// fields must be listed in the same order as the equivalent tuple (see
+/// [`PinsNand`](crate::PinsNand)), and the same constraints apply: the struct
+/// only implements `PinsNand` if the equivalent tuple does. Otherwise
+/// compilation fails at the point where the struct is defined.
+///
+/// ```ignore
+/// stm32_fmc::define_nand_pinset! {
+///     /// NAND pins on my board
+///     pub struct BoardNandPins {
+///         pub ale: PD12<Alternate<12>>,
+///         pub cle: PD11<Alternate<12>>,
+///         // ... D0 - D7, or D0 - D15 for a 16-bit device
+///         pub nce: PG9<Alternate<12>>,
+///         pub noe: PD4<Alternate<12>>,
+///         pub nwe: PD5<Alternate<12>>,
+///         pub nwait: PD6<Alternate<12>>,
+///     }
+/// }
+/// ```
+#[cfg(feature = "nand")]
+#[macro_export]
+macro_rules! define_nand_pinset {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $Name:ident {
+            $( $(#[$fmeta:meta])* $fvis:vis $field:ident: $Pin:ty ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $Name {
+            $( $(#[$fmeta])* $fvis $field: $Pin ),+
+        }
+
+        impl $crate::PinsNand for $Name
+        where
+            ( $($Pin,)+ ): $crate::PinsNand,
+        {
+            const N_DATA: usize =
+                <( $($Pin,)+ ) as $crate::PinsNand>::N_DATA;
+        }
+    };
+}
+
 /// Marks a type as an A0 pin
 pub trait A0 {}
 /// Marks a type as an A1 pin
@@ -404,10 +624,10 @@ pub trait SDNRAS {}
 /// Marks a type as a SDNWE pin
 pub trait SDNWE {}
 
-use crate::ral::fmc;
+use crate::ral::{fmc, read_reg};
 use crate::FmcPeripheral;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub(crate) struct FmcRegisters(usize);
 
 impl FmcRegisters {
@@ -420,4 +640,21 @@ impl FmcRegisters {
     pub fn global(&self) -> &'static fmc::RegisterBlock {
         unsafe { &*(self.0 as *const _) }
     }
+
+    /// Return `true` if a memory controller is already running on the FMC.
+    /// In that case the FMC must not be enabled again, as
+    /// [`FmcPeripheral::enable`] may reset it
+    ///
+    /// NOR/PSRAM/SRAM sub-bank 1 is enabled at reset, so it is only detected
+    /// on parts where the FMCEN bit has been set.
+    pub fn in_use(&self) -> bool {
+        let regs = self.global();
+        read_reg!(fmc, regs, BCR1, FMCEN) != 0
+            || read_reg!(fmc, regs, SDCR1, SDCLK) != 0
+            || read_reg!(fmc, regs, BCR2, MBKEN) != 0
+            || read_reg!(fmc, regs, BCR3, MBKEN) != 0
+            || read_reg!(fmc, regs, BCR4, MBKEN) != 0
+            || read_reg!(fmc, regs, PCR, PBKEN) != 0
+            || read_reg!(fmc, regs, PCR4, PBKEN) != 0
+    }
 }
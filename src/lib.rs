@@ -83,7 +83,7 @@
 //!         pins: PINS,
 //!         chip: CHIP,
 //!         clocks: &CoreClocks,
-//!     ) -> Sdram<FMC, CHIP> {
+//!     ) -> Sdram<FMC, CHIP, PINS> {
 //!         let fmc = Self::new(fmc, clocks);
 //!         Sdram::new(fmc, pins, chip)
 //!     }
@@ -139,18 +139,64 @@ pub use fmc::*;
 
 #[cfg(feature = "sdram")]
 mod sdram;
+#[cfg(all(feature = "sdram", feature = "embedded-dma"))]
+pub use sdram::DmaBuffer;
+#[cfg(all(feature = "sdram", feature = "alloc"))]
+pub use sdram::ExternalHeap;
 #[cfg(feature = "sdram")]
 pub use sdram::{
-    PinsSdram, Sdram, SdramChip, SdramConfiguration, SdramPinSet,
-    SdramTargetBank, SdramTiming,
+    check_sdram_chip, CalibrationSource, Cas, DriveStrength, DynPinsSdram,
+    ExtendedModeRegister, ExternalRam, ExternalRamWord, Initialized,
+    MobileSdramChip, ModeRegister, Partition, Pasr, PinsSdram, Sdram,
+    SdramBank1, SdramBank2, SdramCalibration, SdramChip, SdramCommand,
+    SdramConfiguration, SdramInit, SdramInitError, SdramMode, SdramPinSet,
+    SdramRegisterDiff, SdramStatus, SdramTargetBank, SdramTiming,
+    SdramTimingNs, SdramTimingReport, SealError, Tcsr, Uninitialized,
+    SEAL_HEADER_LEN,
 };
+#[cfg(all(feature = "sdram", feature = "graphics"))]
+pub use sdram::{DoubleBuffer, Framebuffer};
 
 #[cfg(feature = "nand")]
 mod nand;
 #[cfg(feature = "nand")]
 pub use nand::device as nand_device;
 #[cfg(feature = "nand")]
-pub use nand::{Nand, NandChip, NandConfiguration, NandTiming, PinsNand};
+pub use nand::ecc as nand_ecc;
+#[cfg(feature = "ftl")]
+pub use nand::ftl as nand_ftl;
+#[cfg(feature = "nand")]
+pub use nand::{
+    BadBlockTable, Nand, NandChip, NandConfiguration, NandTiming, PinsNand,
+};
+
+#[cfg(all(feature = "nand", feature = "embedded-storage"))]
+pub use nand::NandStorage;
+
+#[cfg(feature = "sram")]
+mod sram;
+#[cfg(feature = "sram")]
+pub use sram::{
+    Lcd, LcdChip, LcdConfiguration, LcdDevice, LcdTiming, PinsSram, Sram,
+    SramBank1, SramBank2, SramBank3, SramBank4, SramConfiguration, SramPinSet,
+    SramTargetBank,
+};
+
+#[cfg(feature = "memtest")]
+pub mod memtest;
+
+#[cfg(feature = "bench")]
+pub mod bench;
+
+#[cfg(feature = "pccard")]
+mod pccard;
+#[cfg(feature = "pccard")]
+pub use pccard::ata as pccard_ata;
+#[cfg(feature = "pccard")]
+pub use pccard::{
+    Pccard, PccardChip, PccardConfiguration, PccardDevice, PccardSpaceTiming,
+    PccardTiming,
+};
 
 /// Memory device definitions
 pub mod devices;
@@ -160,6 +206,12 @@ mod ral;
 /// A trait for device-specific FMC peripherals. Implement this to add support
 /// for a new hardware platform. Peripherals that have this trait must have the
 /// same register block as STM32 FMC peripherals.
+///
+/// # Safety
+///
+/// `REGISTERS` must point to the register block of an FMC/FSMC peripheral,
+/// and the implementing type must have exclusive ownership of that
+/// peripheral.
 pub unsafe trait FmcPeripheral: Send {
     /// Pointer to the register block
     const REGISTERS: *const ();
@@ -0,0 +1,146 @@
+//! Memory tests for an initialised external memory
+//!
+//! These tests are intended for board bring-up, and operate on the region
+//! returned by an `init` method, for example
+//! [`Sdram::as_mut_slice`](crate::Sdram::as_mut_slice). All accesses are
+//! volatile and 32 bits wide. The previous contents of the memory are
+//! destroyed.
+//!
+//! ```no_run
+//! # fn test(memory: &mut [u32]) {
+//! use stm32_fmc::memtest;
+//!
+//! if let Err(e) = memtest::run_all(memory) {
+//!     panic!("Failed at {:#x}, bits {:#x}", e.address, e.failing_bits());
+//! }
+//! # }
+//! ```
+
+use core::ptr;
+
+/// The first location where the memory did not read back as expected
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemtestError {
+    /// Address of the failing word
+    pub address: usize,
+    /// Value that was expected
+    pub expected: u32,
+    /// Value that was read
+    pub actual: u32,
+}
+
+impl MemtestError {
+    /// Bits that differ from the expected value
+    pub fn failing_bits(&self) -> u32 {
+        self.expected ^ self.actual
+    }
+}
+
+/// Read the word at `index`, and check that it is `expected`
+fn check(
+    memory: &[u32],
+    index: usize,
+    expected: u32,
+) -> Result<(), MemtestError> {
+    let address: *const u32 = &memory[index];
+    let actual = unsafe { ptr::read_volatile(address) };
+
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(MemtestError {
+            address: address as usize,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Write `value` to the word at `index`
+fn write(memory: &mut [u32], index: usize, value: u32) {
+    unsafe { ptr::write_volatile(&mut memory[index], value) }
+}
+
+/// Walking ones test of the data bus
+///
+/// Each data bit is set in turn in the first word of `memory`, and read
+/// back. This detects data lines that are open, or shorted to each other
+/// or to a supply.
+pub fn walking_ones(memory: &mut [u32]) -> Result<(), MemtestError> {
+    if memory.is_empty() {
+        return Ok(());
+    }
+
+    for bit in 0..32 {
+        write(memory, 0, 1 << bit);
+        check(memory, 0, 1 << bit)?;
+    }
+    Ok(())
+}
+
+/// Address-in-address test
+///
+/// Every word of `memory` is written with its own address, then all words
+/// are read back. This detects address lines that are open or shorted, and
+/// incorrect row, column or bank wiring, since these cause locations to
+/// alias.
+pub fn address_in_address(memory: &mut [u32]) -> Result<(), MemtestError> {
+    let base = memory.as_ptr() as usize;
+    let address = |index: usize| (base + index * 4) as u32;
+
+    for index in 0..memory.len() {
+        write(memory, index, address(index));
+    }
+    for index in 0..memory.len() {
+        check(memory, index, address(index))?;
+    }
+    Ok(())
+}
+
+/// March C- test
+///
+/// Runs the sequence ⇕(w0); ⇑(r0, w1); ⇑(r1, w0); ⇓(r0, w1); ⇓(r1, w0);
+/// ⇕(r0) over every word of `memory`, with all bits of a word written
+/// together. This detects stuck-at, transition and most coupling faults in
+/// the memory array.
+pub fn march_c_minus(memory: &mut [u32]) -> Result<(), MemtestError> {
+    const ZERO: u32 = 0;
+    const ONE: u32 = !0;
+
+    let len = memory.len();
+
+    for index in 0..len {
+        write(memory, index, ZERO);
+    }
+    for index in 0..len {
+        check(memory, index, ZERO)?;
+        write(memory, index, ONE);
+    }
+    for index in 0..len {
+        check(memory, index, ONE)?;
+        write(memory, index, ZERO);
+    }
+    for index in (0..len).rev() {
+        check(memory, index, ZERO)?;
+        write(memory, index, ONE);
+    }
+    for index in (0..len).rev() {
+        check(memory, index, ONE)?;
+        write(memory, index, ZERO);
+    }
+    for index in 0..len {
+        check(memory, index, ZERO)?;
+    }
+    Ok(())
+}
+
+/// Run all the tests in this module, stopping at the first failure
+///
+/// The data bus is tested first, then the address bus and finally the
+/// memory array.
+pub fn run_all(memory: &mut [u32]) -> Result<(), MemtestError> {
+    walking_ones(memory)?;
+    address_in_address(memory)?;
+    march_c_minus(memory)
+}
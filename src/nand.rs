@@ -11,17 +11,33 @@ use crate::FmcPeripheral;
 
 use crate::ral::{fmc, modify_reg};
 
+mod bad_block;
 pub mod device;
+pub mod ecc;
+#[cfg(feature = "ftl")]
+pub mod ftl;
+#[cfg(feature = "embedded-storage")]
+mod storage;
+
+pub use bad_block::BadBlockTable;
+#[cfg(feature = "embedded-storage")]
+pub use storage::NandStorage;
 
 /// FMC NAND Physical Interface Configuration
 ///
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct NandConfiguration {
-    /// Data path width in bits
+    /// Data path width in bits, 8 or 16
     pub data_width: u8,
-    /// Number of address bits used for the column address
+    /// Number of address bits used for the column address, such that there
+    /// are 2^`column_bits` data bytes in each page. This is in bytes for
+    /// devices with a 16-bit data bus too, although their column address is
+    /// in words
     pub column_bits: u8,
+    /// Number of bytes covered by each ECC computed by the FMC. A power of
+    /// two from 256 to 8192, and no larger than the page
+    pub ecc_sector_bytes: u16,
 }
 
 /// FMC NAND Timing parameters
@@ -87,7 +103,7 @@ impl<IC: NandChip, FMC: FmcPeripheral> Nand<FMC, IC> {
     /// # Panics
     ///
     /// * Panics if there is a mismatch between the data lines in `PINS` and the
-    /// NAND device
+    ///   NAND device
     pub fn new<PINS>(fmc: FMC, _pins: PINS, _chip: IC) -> Self
     where
         PINS: PinsNand,
@@ -132,21 +148,22 @@ impl<IC: NandChip, FMC: FmcPeripheral> Nand<FMC, IC> {
     /// # Panics
     ///
     /// * Panics if any setting in `IC::CONFIG` cannot be achieved
+    /// * Panics if the ECC sector size in `IC::CONFIG` is not supported or is
+    ///   larger than the page
     /// * Panics if the FMC Kernel Clock is too fast to achieve the timing
-    /// required by the NAND device
+    ///   required by the NAND device
     pub fn init<D>(&mut self, delay: &mut D) -> device::NandDevice
     where
         D: DelayNs,
     {
-        // calculate clock period, round down
-        let fmc_source_ck_hz = self.fmc.source_clock_hz();
-        let ker_clk_period_ns = 1_000_000_000u32 / fmc_source_ck_hz;
-
-        // enable memory controller AHB register access
-        self.fmc.enable();
+        // enable memory controller AHB register access. Skipped if another
+        // memory controller is running, as this may reset the FMC
+        if !self.regs.in_use() {
+            self.fmc.enable();
+        }
 
         // device features and timing
-        self.set_features_timings(IC::CONFIG, IC::TIMING, ker_clk_period_ns);
+        self.set_features_timings(IC::CONFIG, IC::TIMING);
 
         // enable memory controller
         self.fmc.memory_controller_enable();
@@ -157,10 +174,30 @@ impl<IC: NandChip, FMC: FmcPeripheral> Nand<FMC, IC> {
         unsafe {
             // Create device. NAND Flash is always on Bank 3
             let ptr = FmcBank::Bank3.ptr() as *mut u8;
-            device::NandDevice::init(ptr, IC::CONFIG.column_bits as usize)
+            device::NandDevice::init(
+                ptr,
+                IC::CONFIG.column_bits as usize,
+                IC::CONFIG.data_width,
+                IC::CONFIG.ecc_sector_bytes as usize,
+                self.regs,
+            )
         }
     }
 
+    /// Change the FMC timing to `timing`
+    ///
+    /// This is used after the NAND device has been switched to a faster
+    /// timing mode with
+    /// [`NandDevice::set_timing_mode`](device::NandDevice::set_timing_mode),
+    /// as devices start in the slowest mode after power on.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the FMC Kernel Clock is too fast to achieve `timing`
+    pub fn set_timing(&mut self, timing: NandTiming) {
+        self.set_features_timings(IC::CONFIG, timing);
+    }
+
     /// Program memory device features and timings
     ///
     /// Timing calculations from AN4761 Section 4.2
@@ -169,9 +206,10 @@ impl<IC: NandChip, FMC: FmcPeripheral> Nand<FMC, IC> {
         &mut self,
         config: NandConfiguration,
         timing: NandTiming,
-        period_ns: u32,
     ) {
-        let period_ns = period_ns as i32;
+        // calculate clock period, round down
+        let fmc_source_ck_hz = self.fmc.source_clock_hz();
+        let period_ns = (1_000_000_000u32 / fmc_source_ck_hz) as i32;
         let n_clock_periods = |time_ns: i32| {
             (time_ns + period_ns - 1) / period_ns // round up
         };
@@ -230,13 +268,29 @@ impl<IC: NandChip, FMC: FmcPeripheral> Nand<FMC, IC> {
             _ => panic!("not possible"),
         };
 
+        let ecc_page_size = match config.ecc_sector_bytes {
+            256 => 0b000,
+            512 => 0b001,
+            1024 => 0b010,
+            2048 => 0b011,
+            4096 => 0b100,
+            8192 => 0b101,
+            _ => panic!(
+                "ECC sector size must be a power of two from 256 to 8192"
+            ),
+        };
+        assert!(
+            u32::from(config.ecc_sector_bytes) <= 1 << config.column_bits,
+            "ECC sector size larger than the NAND page"
+        );
+
         // PCR
         #[rustfmt::skip]
         modify_reg!(fmc, self.regs.global(), PCR,
                     TAR: tar as u32,
                     TCLR: tclr as u32,
-                    ECCPS: 1,   // 0b1: 512 bytes
-                    ECCEN: 0,   // 0b0: ECC computation disabled
+                    ECCPS: ecc_page_size,
+                    ECCEN: 0,   // 0b0: Disabled, enabled per sector by ecc_start
                     PWID: data_width,
                     PTYP: 1,    // 0b1: NAND Flash
                     PWAITEN: 1  // 0b1: Wait feature enabled
@@ -0,0 +1,116 @@
+//! Table of bad blocks in a NAND Flash device
+//!
+//! Blocks that are bad when the device leaves the factory are marked by a
+//! non-`0xFF` value in the first byte of the spare area of their first or
+//! last page: ONFI Section 3.2.2. The marker is erased with the block, so it
+//! must be read before any block is erased and the result kept, for example
+//! in a reserved block.
+
+/// Bitmap of the bad blocks in a NAND Flash device
+///
+/// One bit is stored for each block, in storage provided by the
+/// application. See
+/// [`NandDevice::scan_bad_blocks`](crate::nand_device::NandDevice::scan_bad_blocks).
+///
+/// ```ignore
+/// let mut storage = [0u32; BadBlockTable::storage_words(4096)];
+/// let mut table = BadBlockTable::new(&mut storage, 4096, 64);
+/// nand_device.scan_bad_blocks(&mut table);
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct BadBlockTable<'a> {
+    bits: &'a mut [u32],
+    blocks: usize,
+    pages_per_block: usize,
+}
+
+impl<'a> BadBlockTable<'a> {
+    /// Number of `u32` words of storage needed for `blocks` blocks
+    pub const fn storage_words(blocks: usize) -> usize {
+        (blocks + 31) / 32
+    }
+
+    /// Create a table of `blocks` blocks, each of `pages_per_block` pages,
+    /// with no blocks marked bad
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `storage` is shorter than
+    ///   [`storage_words`](BadBlockTable::storage_words)
+    /// * Panics if `pages_per_block` is zero
+    pub fn new(
+        storage: &'a mut [u32],
+        blocks: usize,
+        pages_per_block: usize,
+    ) -> Self {
+        assert!(
+            storage.len() >= Self::storage_words(blocks),
+            "Storage too short for bad block table"
+        );
+        assert!(pages_per_block > 0, "Blocks must have at least one page");
+
+        let bits = &mut storage[..Self::storage_words(blocks)];
+        for word in bits.iter_mut() {
+            *word = 0;
+        }
+        BadBlockTable {
+            bits,
+            blocks,
+            pages_per_block,
+        }
+    }
+
+    /// Number of blocks in the device
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// Number of pages in each block
+    pub fn pages_per_block(&self) -> usize {
+        self.pages_per_block
+    }
+
+    fn check_block(&self, block: usize) {
+        assert!(block < self.blocks, "Block out of range");
+    }
+
+    /// Is `block` bad?
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `block` is out of range
+    pub fn is_bad(&self, block: usize) -> bool {
+        self.check_block(block);
+        self.bits[block / 32] & (1 << (block % 32)) != 0
+    }
+
+    /// Mark `block` as bad in the table
+    ///
+    /// This does not change the device. See
+    /// [`NandDevice::mark_bad_block`](crate::nand_device::NandDevice::mark_bad_block).
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `block` is out of range
+    pub fn mark_bad(&mut self, block: usize) {
+        self.check_block(block);
+        self.bits[block / 32] |= 1 << (block % 32);
+    }
+
+    /// Number of bad blocks
+    pub fn bad_count(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Iterate over the good blocks, in order
+    pub fn good_blocks(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.blocks).filter(move |&block| !self.is_bad(block))
+    }
+
+    /// The words of the bitmap, for storing the table
+    ///
+    /// Bit `n` of word `w` is set if block `32 * w + n` is bad.
+    pub fn as_words(&self) -> &[u32] {
+        self.bits
+    }
+}
@@ -9,6 +9,20 @@ use core::convert::TryInto;
 use core::sync::atomic::{fence, Ordering};
 use core::{fmt, ptr, str};
 
+#[cfg(feature = "bch")]
+use super::ecc::{Bch, EccError};
+use super::BadBlockTable;
+use crate::fmc::FmcRegisters;
+use crate::ral::{fmc, modify_reg, read_reg};
+
+/// Longest BCH code for a sector, with t = 8
+#[cfg(feature = "bch")]
+const MAX_BCH_ECC_BYTES: usize = 13;
+
+/// Number of times the rising edge flag is checked when waiting for NWAIT,
+/// before falling back to polling Read Status
+const READY_WAIT_POLLS: u32 = 1_000_000;
+
 /// NAND Commands defined in ONFI Specification 5.1
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -26,8 +40,15 @@ enum Command {
     BlockErase = 0x60,
     /// 0x70 Read Status: ONFI Section 5.10
     ReadStatus = 0x70,
+    /// 0xEE Get Features: ONFI Section 5.27
+    GetFeatures = 0xEE,
+    /// 0xEF Set Features: ONFI Section 5.26
+    SetFeatures = 0xEF,
 }
 
+/// Feature address of the timing mode: ONFI Section 5.30.1
+pub const FEATURE_TIMING_MODE: u8 = 0x01;
+
 /// Status returned from 0x70 Read Status: ONFI Section 5.10
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -46,6 +67,22 @@ impl Status {
     }
 }
 
+/// Interrupt sources of the FMC NAND controller
+///
+/// The FMC detects edges and levels of the ready/busy signal on NWAIT. The
+/// device is busy while NWAIT is low, so a rising edge indicates the end of
+/// an array operation such as tR, tPROG or tBERS.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NandInterrupt {
+    /// Rising edge on NWAIT: the device has become ready
+    RisingEdge,
+    /// High level on NWAIT: the device is ready
+    HighLevel,
+    /// Falling edge on NWAIT: the device has become busy
+    FallingEdge,
+}
+
 /// Identifier returned from 0x90 Read ID: ONFI Section 5.6
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -71,6 +108,7 @@ pub struct ParameterPage {
     blocks_per_lun: u32,
     lun_count: u8,
     ecc_bits: u8,
+    sdr_timing_modes: u16,
 }
 impl ParameterPage {
     /// Manufacturer of the device
@@ -81,6 +119,31 @@ impl ParameterPage {
     pub fn model(&self) -> &str {
         str::from_utf8(&self.model).unwrap_or("<ERR>")
     }
+    /// Number of data bytes in each page
+    pub fn data_bytes_per_page(&self) -> u32 {
+        self.data_bytes_per_page
+    }
+    /// Number of spare bytes in each page
+    pub fn spare_bytes_per_page(&self) -> u16 {
+        self.spare_bytes_per_page
+    }
+    /// Number of pages in each block
+    pub fn pages_per_block(&self) -> u32 {
+        self.pages_per_block
+    }
+    /// Number of blocks in each logical unit (LUN)
+    pub fn blocks_per_lun(&self) -> u32 {
+        self.blocks_per_lun
+    }
+    /// Number of logical units (LUNs)
+    pub fn lun_count(&self) -> u8 {
+        self.lun_count
+    }
+    /// SDR timing modes supported by the device. Bit `n` is set if timing
+    /// mode `n` is supported
+    pub fn sdr_timing_modes(&self) -> u16 {
+        self.sdr_timing_modes
+    }
 }
 impl fmt::Debug for ParameterPage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -95,6 +158,7 @@ impl fmt::Debug for ParameterPage {
             .field("Blocks per LUN", &self.blocks_per_lun)
             .field("LUN Count", &self.lun_count)
             .field("ECC Bits Correctability", &self.ecc_bits)
+            .field("SDR Timing Modes", &self.sdr_timing_modes)
             .finish()
     }
 }
@@ -111,6 +175,18 @@ pub struct NandDevice {
     /// Number of address bits C that are used for the column address. The
     /// number of data bytes per page is typically 2^C
     column_bits: Option<usize>,
+
+    /// Data bus width in bits, 8 or 16
+    data_width: u8,
+
+    /// Number of bytes covered by each ECC computed by the FMC
+    ecc_sector_bytes: usize,
+
+    /// Register access, for the ECC and interrupts
+    regs: FmcRegisters,
+
+    /// Wait for a rising edge on NWAIT rather than polling Read Status
+    ready_wait: bool,
 }
 
 unsafe fn write_volatile_sync<T>(dest: *mut T, src: T) {
@@ -130,13 +206,23 @@ impl NandDevice {
     /// The FMC controller must have been initialized as NAND controller and
     /// enabled for this bank, with the correct pin settings. The bank pointer
     /// must be a singleton.
-    pub(crate) unsafe fn init(ptr: *mut u8, column_bits: usize) -> NandDevice {
+    pub(crate) unsafe fn init(
+        ptr: *mut u8,
+        column_bits: usize,
+        data_width: u8,
+        ecc_sector_bytes: usize,
+        regs: FmcRegisters,
+    ) -> NandDevice {
         let mut nand = NandDevice {
             common_command: ptr.add(0x1_0000),
             common_address: ptr.add(0x2_0000),
             attribute_command: ptr.add(0x801_0000),
             common_data: ptr,
             column_bits: Some(column_bits),
+            data_width,
+            ecc_sector_bytes,
+            regs,
+            ready_wait: false,
         };
 
         // Reset Command. May be specifically required by some devices and there
@@ -164,12 +250,18 @@ impl NandDevice {
     /// Generic Address
     ///
     /// column_bits must be set first!
+    ///
+    /// `address` is in bytes. Devices with a 16-bit data bus are addressed in
+    /// words within the page, so the column is halved: ONFI Section 3.1.2.
     fn address(&mut self, address: usize, spare: bool) {
         let column_bits = self
             .column_bits
             .expect("Number of column bits must be configured first");
-        let column = (address & ((1 << column_bits) - 1))
+        let mut column = (address & ((1 << column_bits) - 1))
             + if spare { 1 << column_bits } else { 0 };
+        if self.data_width == 16 {
+            column >>= 1;
+        }
         let row = address >> column_bits;
 
         let mut addr_cycles = [0u8; 5];
@@ -188,6 +280,73 @@ impl NandDevice {
         }
     }
 
+    /// Number of bytes in each data bus cycle
+    pub(crate) fn bus_bytes(&self) -> usize {
+        self.data_width as usize / 8
+    }
+    /// Check that `len` bytes are a whole number of data bus cycles
+    fn check_bus_length(&self, len: usize) {
+        assert!(
+            len % self.bus_bytes() == 0,
+            "16-bit NAND transfers must be an even number of bytes"
+        );
+    }
+    /// Data phase read into `buffer`. On a 16-bit bus each word is split into
+    /// two bytes, the lower byte first
+    pub(crate) fn read_data(&mut self, buffer: &mut [u8]) {
+        self.check_bus_length(buffer.len());
+        if self.data_width == 16 {
+            for pair in buffer.chunks_exact_mut(2) {
+                let word =
+                    unsafe { ptr::read_volatile(self.common_data as *mut u16) };
+                pair.copy_from_slice(&word.to_le_bytes());
+            }
+        } else {
+            for x in buffer {
+                *x = unsafe { ptr::read_volatile(self.common_data) };
+            }
+        }
+    }
+    /// Data phase write from `buffer`. On a 16-bit bus each pair of bytes is
+    /// one word, the lower byte first
+    pub(crate) fn write_data(&mut self, buffer: &[u8]) {
+        self.check_bus_length(buffer.len());
+        if self.data_width == 16 {
+            for pair in buffer.chunks_exact(2) {
+                let word = u16::from_le_bytes([pair[0], pair[1]]);
+                unsafe {
+                    write_volatile_sync(self.common_data as *mut u16, word);
+                }
+            }
+        } else {
+            for x in buffer {
+                unsafe {
+                    write_volatile_sync(self.common_data, *x);
+                }
+            }
+        }
+    }
+    /// Data phase read of `len` bytes that are discarded
+    #[cfg(feature = "bch")]
+    fn skip_data(&mut self, len: usize) {
+        let mut discard = [0u8; 2];
+        let discard = &mut discard[..self.bus_bytes()];
+        self.check_bus_length(len);
+        for _ in 0..len / discard.len() {
+            self.read_data(discard);
+        }
+    }
+    /// Data phase write of `len` bytes of `0xFF`, which leaves them erased
+    #[cfg(feature = "bch")]
+    fn fill_data(&mut self, len: usize) {
+        let erased = [0xFFu8; 2];
+        let erased = &erased[..self.bus_bytes()];
+        self.check_bus_length(len);
+        for _ in 0..len / erased.len() {
+            self.write_data(erased);
+        }
+    }
+
     /// 0x90 Read ID: ONFI Section 5.6
     pub fn read_id(&mut self) -> ID {
         let mut id = [0u8; 5];
@@ -213,7 +372,7 @@ impl NandDevice {
     }
     /// 0xEC Read Parameter Page: ONFI Section 5.7
     pub fn read_parameter_page(&mut self) -> ParameterPage {
-        let mut page = [0u8; 115];
+        let mut page = [0u8; 131];
         self.command(Command::ReadParameterPage, 0, &mut page);
 
         ParameterPage {
@@ -236,6 +395,9 @@ impl NandDevice {
             ),
             lun_count: page[100],
             ecc_bits: page[112],
+            sdr_timing_modes: u16::from_le_bytes(
+                page[129..131].try_into().unwrap(),
+            ),
         }
     }
     /// 0xED Read Unique ID: ONFI Section 5.8
@@ -244,8 +406,64 @@ impl NandDevice {
         self.command(Command::ReadUniqueID, 0, &mut unique);
         u128::from_le_bytes(unique)
     }
+    /// 0xEE Get Features: ONFI Section 5.27
+    ///
+    /// Returns the parameters P1 - P4 of the feature at address `feature`
+    pub fn get_features(&mut self, feature: u8) -> [u8; 4] {
+        let mut parameters = [0u8; 4];
+        self.command(Command::GetFeatures, feature, &mut parameters);
+        parameters
+    }
+    /// 0xEF Set Features: ONFI Section 5.26
+    ///
+    /// Sets the parameters P1 - P4 of the feature at address `feature`, and
+    /// waits for the device to be ready
+    pub fn set_features(&mut self, feature: u8, parameters: [u8; 4]) {
+        self.clear_interrupt(NandInterrupt::RisingEdge);
+        unsafe {
+            write_volatile_sync(
+                self.common_command,
+                Command::SetFeatures as u8,
+            );
+            write_volatile_sync(self.common_address, feature);
+            for p in parameters.iter() {
+                write_volatile_sync(self.common_data, *p);
+            }
+        }
+        let _ = self.wait_status();
+    }
+    /// Current SDR timing mode, read with Get Features
+    pub fn timing_mode(&mut self) -> u8 {
+        self.get_features(FEATURE_TIMING_MODE)[0] & 0xF
+    }
+    /// Switch the device to SDR timing `mode` with Set Features
+    ///
+    /// Devices start in timing mode 0 after power on. Check that `mode` is
+    /// supported with
+    /// [`ParameterPage::sdr_timing_modes`](ParameterPage::sdr_timing_modes).
+    /// Afterwards the FMC timing can be changed to match with
+    /// [`Nand::set_timing`](crate::Nand::set_timing).
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `mode` is greater than 5
+    pub fn set_timing_mode(&mut self, mode: u8) {
+        assert!(mode <= 5, "ONFI SDR timing mode must be 0 - 5");
+        self.set_features(FEATURE_TIMING_MODE, [mode, 0, 0, 0]);
+    }
     /// 0x60 Block Erase: ONFI Section 5.9
+    ///
+    /// Erases the block containing `address` and waits for it to complete.
     pub fn block_erase(&mut self, address: usize) -> Status {
+        self.start_block_erase(address);
+        self.wait_status()
+    }
+    /// 0x60 Block Erase: ONFI Section 5.9
+    ///
+    /// This method starts a Block Erase operation but does not wait for it
+    /// to complete. See [`wait_ready`](Self::wait_ready) and
+    /// [`read_status`](Self::read_status).
+    pub fn start_block_erase(&mut self, address: usize) {
         unsafe {
             write_volatile_sync(self.common_command, 0x60); // auto block erase setup
         }
@@ -262,19 +480,15 @@ impl NandDevice {
                 self.common_address,
                 ((row >> 16) & 0xFF) as u8,
             );
-
-            // erase command
-            write_volatile_sync(self.attribute_command, 0xD0); // t_WB
-            write_volatile_sync(self.common_command, Command::ReadStatus as u8);
-            let status_register = ptr::read_volatile(self.common_data);
-            Status::from_register(status_register)
         }
+        self.busy_command(0xD0); // erase command
     }
 
     /// Page Read: ONFI Section 5.14
     ///
     /// This method starts a Page Read operation but does not include the data
-    /// phase. This method is useful when DMA is used for the data phase.
+    /// phase. This method is useful when DMA is used for the data phase,
+    /// reading from [`data_ptr`](Self::data_ptr).
     ///
     /// For a method that completes the entire transaction see
     /// [`page_read`](Self::page_read).
@@ -282,8 +496,8 @@ impl NandDevice {
         unsafe {
             write_volatile_sync(self.common_command, 0x00);
             self.address(address, spare);
-            write_volatile_sync(self.attribute_command, 0x30); // t_WB
         }
+        self.busy_command(0x30);
     }
     /// Page Read: ONFI Section 5.14
     ///
@@ -296,13 +510,14 @@ impl NandDevice {
     /// If `spare` is true, then the read occours from the spare area. The
     /// address offset from the start of the page plus the slice length should
     /// not exceed the spare area size.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the device has a 16-bit data bus and the length of `page`
+    ///   is odd
     pub fn page_read(&mut self, address: usize, spare: bool, page: &mut [u8]) {
         self.start_page_read(address, spare);
-        for x in page {
-            unsafe {
-                *x = ptr::read_volatile(self.common_data);
-            }
-        }
+        self.read_data(page);
     }
 
     /// Page Program: ONFI Section 5.16
@@ -312,19 +527,505 @@ impl NandDevice {
     /// length should not exceed the number of bytes between the specified
     /// address and the end of the page. Writing beyond this length is
     /// undefined.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the device has a 16-bit data bus and the length of `page`
+    ///   is odd
     pub fn page_program(
         &mut self,
         address: usize,
         spare: bool,
         page: &[u8],
     ) -> Status {
+        self.start_page_program(address, spare);
+        self.write_data(page); // write page
+        self.finish_page_program()
+    }
+
+    /// Page Program: ONFI Section 5.16
+    ///
+    /// This method starts a Page Program operation but does not include the
+    /// data phase. This method is useful when DMA is used for the data phase.
+    /// Complete the operation with
+    /// [`finish_page_program`](Self::finish_page_program).
+    ///
+    /// For a method that completes the entire transaction see
+    /// [`page_program`](Self::page_program).
+    pub fn start_page_program(&mut self, address: usize, spare: bool) {
         unsafe {
             write_volatile_sync(self.common_command, 0x80); // data input
             self.address(address, spare);
-            for x in page {
-                write_volatile_sync(self.common_data, *x); // write page
+        }
+    }
+    /// Page Program: ONFI Section 5.16
+    ///
+    /// Completes a Page Program operation started by
+    /// [`start_page_program`](Self::start_page_program) once the data phase
+    /// is done, and waits for it to complete.
+    pub fn finish_page_program(&mut self) -> Status {
+        self.confirm_page_program();
+        self.wait_status()
+    }
+    /// Page Program: ONFI Section 5.16
+    ///
+    /// Like [`finish_page_program`](Self::finish_page_program), but does not
+    /// wait for the program operation to complete. See
+    /// [`wait_ready`](Self::wait_ready) and
+    /// [`read_status`](Self::read_status).
+    pub fn confirm_page_program(&mut self) {
+        self.busy_command(0x10); // program command
+    }
+
+    /// Page Read of 16-bit words: ONFI Section 5.14
+    ///
+    /// Like [`page_read`](Self::page_read), for a device with a 16-bit data
+    /// bus. `address` is in bytes.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the device has an 8-bit data bus
+    pub fn page_read_u16(
+        &mut self,
+        address: usize,
+        spare: bool,
+        page: &mut [u16],
+    ) {
+        assert!(self.data_width == 16, "NAND device has an 8-bit data bus");
+        self.start_page_read(address, spare);
+        for x in page {
+            unsafe {
+                *x = ptr::read_volatile(self.common_data as *mut u16);
+            }
+        }
+    }
+
+    /// Page Program of 16-bit words: ONFI Section 5.16
+    ///
+    /// Like [`page_program`](Self::page_program), for a device with a 16-bit
+    /// data bus. `address` is in bytes.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the device has an 8-bit data bus
+    pub fn page_program_u16(
+        &mut self,
+        address: usize,
+        spare: bool,
+        page: &[u16],
+    ) -> Status {
+        assert!(self.data_width == 16, "NAND device has an 8-bit data bus");
+        self.start_page_program(address, spare);
+        for x in page {
+            unsafe {
+                write_volatile_sync(self.common_data as *mut u16, *x);
+            }
+        }
+        self.finish_page_program()
+    }
+
+    /// Reset and enable the ECC logic, starting a new sector
+    ///
+    /// When DMA is used for the data phase, call this before transferring
+    /// each sector of [`ecc_sector_bytes`](Self::ecc_sector_bytes) and
+    /// [`ecc_finish`](Self::ecc_finish) after it.
+    pub fn ecc_start(&mut self) {
+        modify_reg!(fmc, self.regs.global(), PCR, ECCEN: 0);
+        modify_reg!(fmc, self.regs.global(), PCR, ECCEN: 1);
+    }
+    /// Wait for the write FIFO to drain, then read the ECC and disable the ECC
+    /// logic
+    ///
+    /// Returns the Hamming code of the sector transferred since
+    /// [`ecc_start`](Self::ecc_start).
+    pub fn ecc_finish(&mut self) -> u32 {
+        while read_reg!(fmc, self.regs.global(), SR, FEMPT == 0) {}
+        let ecc = read_reg!(fmc, self.regs.global(), ECCR);
+        modify_reg!(fmc, self.regs.global(), PCR, ECCEN: 0);
+        ecc
+    }
+    /// Number of bytes covered by each ECC computed by the FMC, from
+    /// [`NandConfiguration`](crate::NandConfiguration)
+    pub fn ecc_sector_bytes(&self) -> usize {
+        self.ecc_sector_bytes
+    }
+    /// Check that `page` and `ecc` are the right length for ECC sectors
+    fn ecc_check_lengths(&self, page: &[u8], ecc: &[u32]) {
+        assert!(
+            page.len() % self.ecc_sector_bytes == 0,
+            "Page length must be a multiple of the ECC sector size"
+        );
+        assert!(
+            ecc.len() == page.len() / self.ecc_sector_bytes,
+            "One ECC value is needed for each ECC sector"
+        );
+    }
+
+    /// Page Read with hardware ECC: ONFI Section 5.14
+    ///
+    /// Executes a Page Read operation from the data area starting at
+    /// `address`, like [`page_read`](Self::page_read). The FMC computes a
+    /// Hamming code over each [`ecc_sector_bytes`](Self::ecc_sector_bytes) of
+    /// `page`, which is written to the corresponding element of `ecc`.
+    ///
+    /// Compare each code with the one stored when the page was programmed
+    /// using [`correct_hamming`](super::ecc::correct_hamming).
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the length of `page` is not a multiple of
+    ///   [`ecc_sector_bytes`](Self::ecc_sector_bytes), or `ecc` does not have
+    ///   one element for each sector
+    pub fn page_read_ecc(
+        &mut self,
+        address: usize,
+        page: &mut [u8],
+        ecc: &mut [u32],
+    ) {
+        self.ecc_check_lengths(page, ecc);
+
+        self.start_page_read(address, false);
+        for (sector, ecc) in page
+            .chunks_exact_mut(self.ecc_sector_bytes)
+            .zip(ecc.iter_mut())
+        {
+            self.ecc_start();
+            self.read_data(sector);
+            *ecc = self.ecc_finish();
+        }
+    }
+
+    /// Page Program with hardware ECC: ONFI Section 5.16
+    ///
+    /// Executes a Page Program operation to the data area starting at
+    /// `address`, like [`page_program`](Self::page_program). The FMC computes
+    /// a Hamming code over each [`ecc_sector_bytes`](Self::ecc_sector_bytes)
+    /// of `page`, which is written to the corresponding element of `ecc`.
+    ///
+    /// The codes should be stored, for example by a partial page program of
+    /// the spare area, so that they can be checked when the page is read.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the length of `page` is not a multiple of
+    ///   [`ecc_sector_bytes`](Self::ecc_sector_bytes), or `ecc` does not have
+    ///   one element for each sector
+    pub fn page_program_ecc(
+        &mut self,
+        address: usize,
+        page: &[u8],
+        ecc: &mut [u32],
+    ) -> Status {
+        self.ecc_check_lengths(page, ecc);
+
+        self.start_page_program(address, false);
+        for (sector, ecc) in
+            page.chunks_exact(self.ecc_sector_bytes).zip(ecc.iter_mut())
+        {
+            self.ecc_start();
+            self.write_data(sector); // write page
+            *ecc = self.ecc_finish();
+        }
+        self.finish_page_program()
+    }
+
+    /// Check that `page` and the spare area layout can be used with `bch`
+    #[cfg(feature = "bch")]
+    fn bch_check_page(&self, bch: &Bch, page: &[u8], spare_offset: usize) {
+        let column_bits = self
+            .column_bits
+            .expect("Number of column bits must be configured first");
+        assert!(
+            page.len() == 1 << column_bits,
+            "Page length must be the data area of a page"
+        );
+        assert!(
+            page.len() % bch.sector_bytes() == 0,
+            "Page length must be a multiple of the BCH sector size"
+        );
+        self.check_bus_length(bch.ecc_bytes());
+        self.check_bus_length(spare_offset);
+    }
+
+    /// Page Read with software BCH correction: ONFI Section 5.14
+    ///
+    /// Reads the whole data area of the page at `address` into `page`,
+    /// followed by the BCH codes from the spare area. The code for each
+    /// sector is stored contiguously, starting `spare_offset` bytes into the
+    /// spare area, as written by [`page_program_bch`](Self::page_program_bch).
+    ///
+    /// Returns the largest number of bits corrected in any sector, which can
+    /// be compared with [`Bch::t`] to decide when a block should be
+    /// refreshed.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `page` is not the length of the data area, or is not a
+    ///   multiple of the BCH sector size
+    /// * Panics if the device has a 16-bit data bus and the BCH code length or
+    ///   `spare_offset` is odd
+    #[cfg(feature = "bch")]
+    pub fn page_read_bch(
+        &mut self,
+        address: usize,
+        bch: &Bch,
+        spare_offset: usize,
+        page: &mut [u8],
+    ) -> Result<usize, EccError> {
+        self.bch_check_page(bch, page, spare_offset);
+
+        self.page_read(address, false, page);
+        // The data phase continues into the spare area
+        self.skip_data(spare_offset);
+
+        let mut ecc = [0u8; MAX_BCH_ECC_BYTES];
+        let ecc = &mut ecc[..bch.ecc_bytes()];
+        let mut result = Ok(0);
+        for sector in page.chunks_exact_mut(bch.sector_bytes()) {
+            self.read_data(ecc);
+            // Correct the remaining sectors even if one is uncorrectable
+            match (bch.correct(sector, ecc), result) {
+                (Ok(bits), Ok(max)) => result = Ok(bits.max(max)),
+                (Err(e), _) => result = Err(e),
+                (Ok(_), Err(_)) => {}
+            }
+        }
+        result
+    }
+
+    /// Page Program with software BCH code: ONFI Section 5.16
+    ///
+    /// Programs the whole data area of the page at `address` from `page`,
+    /// followed by the BCH code for each sector, stored contiguously
+    /// starting `spare_offset` bytes into the spare area. The spare area
+    /// before `spare_offset` is left erased, which preserves a factory bad
+    /// block marker.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `page` is not the length of the data area, or is not a
+    ///   multiple of the BCH sector size
+    /// * Panics if the device has a 16-bit data bus and the BCH code length or
+    ///   `spare_offset` is odd
+    #[cfg(feature = "bch")]
+    pub fn page_program_bch(
+        &mut self,
+        address: usize,
+        bch: &Bch,
+        spare_offset: usize,
+        page: &[u8],
+    ) -> Status {
+        self.bch_check_page(bch, page, spare_offset);
+
+        let mut ecc = [0u8; MAX_BCH_ECC_BYTES];
+        let ecc = &mut ecc[..bch.ecc_bytes()];
+        self.start_page_program(address, false);
+        self.write_data(page); // write page
+        self.fill_data(spare_offset);
+        for sector in page.chunks_exact(bch.sector_bytes()) {
+            bch.encode(sector, ecc);
+            self.write_data(ecc);
+        }
+        self.finish_page_program()
+    }
+
+    /// Address of the first byte of `page`
+    pub(crate) fn page_address(&self, page: usize) -> usize {
+        let column_bits = self
+            .column_bits
+            .expect("Number of column bits must be configured first");
+        page << column_bits
+    }
+
+    /// Read the factory bad block markers of every block into `table`
+    ///
+    /// A block is bad if the first byte of the spare area of its first or
+    /// last page is not `0xFF`, or the first word on a device with a 16-bit
+    /// data bus: ONFI Section 3.2.2. Returns the number of bad blocks.
+    ///
+    /// The markers are erased with their block, so this must be done before
+    /// any block is erased.
+    pub fn scan_bad_blocks(&mut self, table: &mut BadBlockTable) -> usize {
+        let pages_per_block = table.pages_per_block();
+        for block in 0..table.blocks() {
+            let first = block * pages_per_block;
+            let last = first + pages_per_block - 1;
+            for &page in [first, last].iter() {
+                let mut marker = [0xFFu8; 2];
+                let len = self.bus_bytes();
+                let address = self.page_address(page);
+                self.page_read(address, true, &mut marker[..len]);
+                if marker != [0xFF; 2] {
+                    table.mark_bad(block);
+                    break;
+                }
             }
-            write_volatile_sync(self.attribute_command, 0x10); // program command, t_WB
+        }
+        table.bad_count()
+    }
+
+    /// Mark `block` as bad in `table` and on the device
+    ///
+    /// The first byte or word of the spare area of the first page of the
+    /// block is programmed to zero, so that the block is found by
+    /// [`scan_bad_blocks`](Self::scan_bad_blocks). Returns the status of the
+    /// program operation. The block is marked bad in `table` even if the
+    /// program fails.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `block` is out of range of `table`
+    pub fn mark_bad_block(
+        &mut self,
+        table: &mut BadBlockTable,
+        block: usize,
+    ) -> Status {
+        table.mark_bad(block);
+        let address = self.page_address(block * table.pages_per_block());
+        let len = self.bus_bytes();
+        self.page_program(address, true, &[0x00; 2][..len])
+    }
+
+    /// Enable the FMC interrupt for `interrupt`
+    ///
+    /// The FMC interrupt is raised while the flag for `interrupt` is set, so
+    /// it must be cleared with [`clear_interrupt`](Self::clear_interrupt) or
+    /// the interrupt disabled. For the rising edge interrupt,
+    /// [`wait_ready`](Self::wait_ready) does both.
+    pub fn enable_interrupt(&mut self, interrupt: NandInterrupt) {
+        self.set_interrupt(interrupt, true);
+    }
+    /// Disable the FMC interrupt for `interrupt`
+    pub fn disable_interrupt(&mut self, interrupt: NandInterrupt) {
+        self.set_interrupt(interrupt, false);
+    }
+    fn set_interrupt(&mut self, interrupt: NandInterrupt, enable: bool) {
+        let enable = enable as u32;
+        match interrupt {
+            NandInterrupt::RisingEdge => {
+                modify_reg!(fmc, self.regs.global(), SR, IREN: enable)
+            }
+            NandInterrupt::HighLevel => {
+                modify_reg!(fmc, self.regs.global(), SR, ILEN: enable)
+            }
+            NandInterrupt::FallingEdge => {
+                modify_reg!(fmc, self.regs.global(), SR, IFEN: enable)
+            }
+        }
+    }
+    /// Return `true` if `interrupt` has occurred since its flag was last
+    /// cleared
+    ///
+    /// The flags are set whether or not the interrupt is enabled.
+    pub fn is_interrupt_pending(&self, interrupt: NandInterrupt) -> bool {
+        match interrupt {
+            NandInterrupt::RisingEdge => {
+                read_reg!(fmc, self.regs.global(), SR, IRS == 1)
+            }
+            NandInterrupt::HighLevel => {
+                read_reg!(fmc, self.regs.global(), SR, ILS == 1)
+            }
+            NandInterrupt::FallingEdge => {
+                read_reg!(fmc, self.regs.global(), SR, IFS == 1)
+            }
+        }
+    }
+    /// Clear the flag for `interrupt`
+    pub fn clear_interrupt(&mut self, interrupt: NandInterrupt) {
+        match interrupt {
+            NandInterrupt::RisingEdge => {
+                modify_reg!(fmc, self.regs.global(), SR, IRS: 0)
+            }
+            NandInterrupt::HighLevel => {
+                modify_reg!(fmc, self.regs.global(), SR, ILS: 0)
+            }
+            NandInterrupt::FallingEdge => {
+                modify_reg!(fmc, self.regs.global(), SR, IFS: 0)
+            }
+        }
+    }
+
+    /// Wait for the device to become ready after an operation started by
+    /// [`start_page_read`](Self::start_page_read),
+    /// [`confirm_page_program`](Self::confirm_page_program) or
+    /// [`start_block_erase`](Self::start_block_erase)
+    ///
+    /// `idle` is called until a rising edge on NWAIT is detected. The rising
+    /// edge interrupt is then disabled and its flag cleared, so the interrupt
+    /// must be enabled again before the next wait. The flag must not be
+    /// cleared elsewhere while waiting. This waits forever if the ready/busy
+    /// output of the device is not connected to NWAIT.
+    ///
+    /// To sleep while waiting, keep the FMC interrupt disabled in the NVIC
+    /// and set SEVONPEND, so that the interrupt becoming pending wakes the
+    /// core from WFE without running a handler. The pending state is cleared
+    /// before each sleep, as it is not cleared with the flag:
+    ///
+    /// ```ignore
+    /// core.SCB.set_sevonpend();
+    ///
+    /// nand_device.enable_interrupt(NandInterrupt::RisingEdge);
+    /// nand_device.start_block_erase(address);
+    /// nand_device.wait_ready(|| {
+    ///     NVIC::unpend(Interrupt::FMC);
+    ///     cortex_m::asm::wfe();
+    /// });
+    /// let status = nand_device.read_status();
+    /// ```
+    pub fn wait_ready(&mut self, mut idle: impl FnMut()) {
+        while !self.is_interrupt_pending(NandInterrupt::RisingEdge) {
+            idle();
+        }
+        self.disable_interrupt(NandInterrupt::RisingEdge);
+        self.clear_interrupt(NandInterrupt::RisingEdge);
+    }
+
+    /// Wait for a rising edge on NWAIT, rather than polling Read Status, in
+    /// methods that wait for an operation to complete
+    ///
+    /// This avoids continuous NAND bus cycles during long operations, which
+    /// otherwise delay accesses to other memories on the FMC. The ready/busy
+    /// output of the device should be connected to NWAIT. If no rising edge
+    /// is detected after a large number of checks, Read Status is polled
+    /// instead, so a missing connection only slows the wait.
+    pub fn set_ready_wait(&mut self, enable: bool) {
+        self.ready_wait = enable;
+    }
+
+    /// 0x70 Read Status: ONFI Section 5.10
+    ///
+    /// Reads the status register once, without waiting for the device to be
+    /// ready.
+    pub fn read_status(&mut self) -> Status {
+        unsafe {
+            write_volatile_sync(self.common_command, Command::ReadStatus as u8);
+            Status::from_register(ptr::read_volatile(self.common_data))
+        }
+    }
+
+    /// Write a command that makes the device busy
+    ///
+    /// The rising edge flag is cleared first, so that
+    /// [`wait_ready`](Self::wait_ready) detects the end of this operation.
+    fn busy_command(&mut self, command: u8) {
+        self.clear_interrupt(NandInterrupt::RisingEdge);
+        unsafe {
+            write_volatile_sync(self.attribute_command, command); // t_WB
+        }
+    }
+
+    /// Wait until the array is ready, then read the status
+    fn wait_status(&mut self) -> Status {
+        if self.ready_wait {
+            for _ in 0..READY_WAIT_POLLS {
+                if self.is_interrupt_pending(NandInterrupt::RisingEdge) {
+                    self.clear_interrupt(NandInterrupt::RisingEdge);
+                    return self.read_status();
+                }
+            }
+            fmc_trace!("No rising edge on NWAIT, polling Read Status");
+        }
+        unsafe {
             let mut status_register;
             while {
                 write_volatile_sync(
@@ -377,6 +1078,16 @@ impl NandDevice {
     pub fn common_address(&mut self) -> *mut u8 {
         self.common_address
     }
+    /// Address of the data phase, for use as the peripheral address of a DMA
+    /// transfer
+    ///
+    /// Each access is one cycle on the data bus, so the DMA should use the
+    /// width of the data bus and must not increment this address. Start the
+    /// operation with [`start_page_read`](Self::start_page_read) or
+    /// [`start_page_program`](Self::start_page_program).
+    pub fn data_ptr(&self) -> *mut u8 {
+        self.common_data
+    }
     /// Return a Raw Pointer to the common data space. This memory-mapped
     /// address is used to write or read the data phase of NAND device
     /// transactions.
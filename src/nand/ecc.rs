@@ -0,0 +1,72 @@
+//! Error correction for NAND Flash pages
+//!
+//! The FMC computes a Hamming code over each ECC sector as it is read or
+//! written. The code computed when a sector is programmed is stored by the
+//! application, typically in the spare area. When the sector is read back,
+//! [`correct_hamming`] compares the stored code against the code computed
+//! during the read, and corrects a single bit error in the data.
+//!
+//! With the `bch` feature, [`Bch`] provides a software BCH code for devices
+//! that require more than one bit of correction per sector.
+
+#[cfg(feature = "bch")]
+mod bch;
+#[cfg(feature = "bch")]
+pub use bch::Bch;
+
+/// Errors that cannot be corrected by the ECC
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EccError {
+    /// More bits are in error than the code can correct
+    Uncorrectable,
+}
+
+/// Correct a single bit error in `sector` using the Hamming codes `stored`
+/// when the sector was programmed and `computed` when it was read
+///
+/// The codes are the values of the FMC ECC result register. Returns the
+/// number of bits corrected in `sector`, which is 0 if the error was in the
+/// stored code itself.
+///
+/// # Panics
+///
+/// * Panics if the length of `sector` is not a power of two between 256 and
+///   8192 bytes
+pub fn correct_hamming(
+    sector: &mut [u8],
+    stored: u32,
+    computed: u32,
+) -> Result<usize, EccError> {
+    let len = sector.len();
+    assert!(
+        len.is_power_of_two() && (256..=8192).contains(&len),
+        "ECC sector must be a power of two between 256 and 8192 bytes"
+    );
+
+    // Each bit of the bit offset within the sector is covered by a pair of
+    // parity bits, the upper one for 1 and the lower one for 0
+    let address_bits = 3 + len.trailing_zeros();
+    let mask = (!0u64 >> (64 - 2 * address_bits)) as u32;
+    let syndrome = (stored ^ computed) & mask;
+
+    if syndrome == 0 {
+        return Ok(0);
+    }
+    if syndrome.is_power_of_two() {
+        // A single bit error in the stored code
+        return Ok(0);
+    }
+
+    let mut position = 0;
+    for i in 0..address_bits {
+        match (syndrome >> (2 * i)) & 0b11 {
+            0b10 => position |= 1 << i,
+            0b01 => {}
+            _ => return Err(EccError::Uncorrectable),
+        }
+    }
+    sector[position / 8] ^= 1 << (position % 8);
+
+    Ok(1)
+}
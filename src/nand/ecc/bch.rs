@@ -0,0 +1,305 @@
+//! Binary BCH code over GF(2^13)
+//!
+//! The FMC Hamming code corrects a single bit per sector, but most SLC NAND
+//! requires 4 to 8 bits of correction per 512 bytes. [`Bch`] computes the
+//! code in software, and needs no lookup tables.
+
+use super::EccError;
+
+/// Degree of the Galois field
+const M: u32 = 13;
+/// Number of non-zero elements in the field
+const N: u32 = (1 << M) - 1;
+/// Primitive polynomial x^13 + x^4 + x^3 + x + 1
+const PRIMITIVE: u32 = 0x201B;
+/// Largest number of correctable bits supported
+const MAX_T: usize = 8;
+
+/// Multiply `a` and `b` in GF(2^13)
+fn gf_mul(a: u16, b: u16) -> u16 {
+    let (mut a, mut b, mut product) = (u32::from(a), b, 0);
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        b >>= 1;
+        a <<= 1;
+        if a & (1 << M) != 0 {
+            a ^= PRIMITIVE;
+        }
+    }
+    product as u16
+}
+
+/// Raise `a` to the power `exp` in GF(2^13)
+fn gf_pow(a: u16, exp: u32) -> u16 {
+    let (mut base, mut exp, mut result) = (a, exp % N, 1);
+    while exp != 0 {
+        if exp & 1 != 0 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Multiplicative inverse of a non-zero `a` in GF(2^13)
+fn gf_inv(a: u16) -> u16 {
+    gf_pow(a, N - 1)
+}
+
+/// The primitive element alpha raised to the power `exp`
+fn alpha(exp: u32) -> u16 {
+    gf_pow(2, exp)
+}
+
+/// BCH encoder and decoder for sectors of NAND Flash
+///
+/// Corrects up to `t` bit errors in each sector, using
+/// [`ecc_bytes`](Bch::ecc_bytes) bytes of code that are usually stored in
+/// the spare area.
+///
+/// ```ignore
+/// let bch = Bch::new(512, 8);
+/// let mut ecc = [0; 13];
+/// bch.encode(&sector, &mut ecc);
+/// // ... later
+/// let corrected = bch.correct(&mut sector, &ecc)?;
+/// ```
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bch {
+    sector_bytes: usize,
+    t: usize,
+    /// Generator polynomial, without the x^(13t) term
+    generator: u128,
+}
+
+impl Bch {
+    /// Create a code for sectors of `sector_bytes` that corrects up to `t`
+    /// bit errors in each sector
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `t` is not between 1 and 8
+    /// * Panics if `sector_bytes` is zero or too long for the code
+    pub fn new(sector_bytes: usize, t: usize) -> Self {
+        assert!((1..=MAX_T).contains(&t), "BCH t must be between 1 and 8");
+        assert!(
+            sector_bytes > 0 && sector_bytes * 8 + M as usize * t <= N as usize,
+            "BCH sector length too long for GF(2^13)"
+        );
+
+        // The generator has the roots alpha^1 .. alpha^2t and their
+        // conjugates. The cyclotomic cosets of the odd powers are distinct and
+        // each has 13 elements
+        let degree = M as usize * t;
+        let mut g = [0u16; M as usize * MAX_T + 1];
+        g[0] = 1;
+        let mut len = 1;
+        for i in (1..2 * t as u32).step_by(2) {
+            let mut root = alpha(i);
+            for _ in 0..M {
+                // g(x) = g(x) * (x + root)
+                for k in (0..=len).rev() {
+                    let shifted = if k > 0 { g[k - 1] } else { 0 };
+                    g[k] = shifted ^ gf_mul(g[k], root);
+                }
+                len += 1;
+                root = gf_mul(root, root);
+            }
+        }
+        debug_assert!(len == degree + 1);
+
+        // The coefficients are all 0 or 1
+        let generator = g[..degree]
+            .iter()
+            .enumerate()
+            .fold(0u128, |acc, (k, &c)| acc | (u128::from(c & 1) << k));
+
+        Bch {
+            sector_bytes,
+            t,
+            generator,
+        }
+    }
+
+    /// Number of data bytes in each sector
+    pub fn sector_bytes(&self) -> usize {
+        self.sector_bytes
+    }
+
+    /// Number of bit errors that can be corrected in each sector
+    pub fn t(&self) -> usize {
+        self.t
+    }
+
+    /// Number of bytes of code for each sector
+    pub fn ecc_bytes(&self) -> usize {
+        (self.parity_bits() + 7) / 8
+    }
+
+    fn parity_bits(&self) -> usize {
+        M as usize * self.t
+    }
+
+    /// Remainder of `sector` x^(13t) divided by the generator
+    fn remainder(&self, sector: &[u8]) -> u128 {
+        let degree = self.parity_bits();
+        let mask = (1u128 << degree) - 1;
+        let mut remainder = 0u128;
+        for &byte in sector {
+            for bit in (0..8).rev() {
+                let feedback =
+                    u128::from(byte >> bit & 1) ^ (remainder >> (degree - 1));
+                remainder = (remainder << 1) & mask;
+                if feedback & 1 != 0 {
+                    remainder ^= self.generator;
+                }
+            }
+        }
+        remainder
+    }
+
+    /// Convert the code stored in `ecc` to parity bits
+    fn parity_from_bytes(&self, ecc: &[u8]) -> u128 {
+        let pad = self.ecc_bytes() * 8 - self.parity_bits();
+        ecc.iter().fold(0u128, |acc, &b| acc << 8 | u128::from(b)) >> pad
+    }
+
+    /// Compute the code for `sector`, writing it to `ecc`
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `sector` is not [`sector_bytes`](Bch::sector_bytes) long,
+    ///   or `ecc` is not [`ecc_bytes`](Bch::ecc_bytes) long
+    pub fn encode(&self, sector: &[u8], ecc: &mut [u8]) {
+        self.check_lengths(sector, ecc);
+
+        let pad = self.ecc_bytes() * 8 - self.parity_bits();
+        let parity = self.remainder(sector) << pad;
+        let bytes = parity.to_be_bytes();
+        ecc.copy_from_slice(&bytes[bytes.len() - ecc.len()..]);
+    }
+
+    /// Correct bit errors in `sector` using the code `ecc` computed when it
+    /// was written
+    ///
+    /// Returns the number of bits corrected in `sector`. Bit errors in `ecc`
+    /// count towards the `t` correctable bits, but are not included in the
+    /// result. An erased sector, where `sector` and `ecc` are all `0xFF`, has
+    /// no errors.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `sector` is not [`sector_bytes`](Bch::sector_bytes) long,
+    ///   or `ecc` is not [`ecc_bytes`](Bch::ecc_bytes) long
+    pub fn correct(
+        &self,
+        sector: &mut [u8],
+        ecc: &[u8],
+    ) -> Result<usize, EccError> {
+        self.check_lengths(sector, ecc);
+
+        let received = self.remainder(sector) ^ self.parity_from_bytes(ecc);
+        if received == 0 {
+            return Ok(0);
+        }
+        if sector.iter().chain(ecc).all(|&b| b == 0xFF) {
+            return Ok(0);
+        }
+
+        // Syndromes S_1 .. S_2t of the received codeword, evaluated from its
+        // remainder
+        let two_t = 2 * self.t;
+        let mut syndromes = [0u16; 2 * MAX_T];
+        for (j, s) in syndromes[..two_t].iter_mut().enumerate() {
+            let a = alpha(j as u32 + 1);
+            *s = (0..self.parity_bits())
+                .rev()
+                .fold(0, |acc, k| gf_mul(acc, a) ^ (received >> k & 1) as u16);
+        }
+
+        // Berlekamp-Massey for the error locator polynomial
+        let mut locator = [0u16; 2 * MAX_T + 1];
+        let mut previous = [0u16; 2 * MAX_T + 1];
+        locator[0] = 1;
+        previous[0] = 1;
+        let (mut errors, mut shift, mut previous_discrepancy) = (0, 1, 1);
+        for n in 0..two_t {
+            let discrepancy = (1..=errors).fold(syndromes[n], |d, i| {
+                d ^ gf_mul(locator[i], syndromes[n - i])
+            });
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+            let scale = gf_mul(discrepancy, gf_inv(previous_discrepancy));
+            let saved = locator;
+            for i in shift..=two_t {
+                locator[i] ^= gf_mul(scale, previous[i - shift]);
+            }
+            if 2 * errors <= n {
+                errors = n + 1 - errors;
+                previous = saved;
+                previous_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                shift += 1;
+            }
+        }
+        if errors > self.t {
+            return Err(EccError::Uncorrectable);
+        }
+
+        // Chien search over the bit positions of the shortened codeword. The
+        // parity bits are at positions 0 .. 13t, and the last bit of the
+        // sector follows
+        let codeword_bits = self.parity_bits() + self.sector_bytes * 8;
+        let mut terms = locator;
+        let steps: [u16; MAX_T + 1] = {
+            let mut steps = [0; MAX_T + 1];
+            for (i, s) in steps.iter_mut().enumerate().take(errors + 1) {
+                *s = alpha(N - i as u32);
+            }
+            steps
+        };
+        let mut found = 0;
+        let mut flips = [0usize; MAX_T];
+        let mut corrected = 0;
+        for position in 0..codeword_bits {
+            let value = terms[..=errors].iter().fold(0, |acc, &t| acc ^ t);
+            if value == 0 {
+                found += 1;
+                if position >= self.parity_bits() {
+                    flips[corrected] = codeword_bits - 1 - position;
+                    corrected += 1;
+                }
+            }
+            for (term, &step) in terms[..=errors].iter_mut().zip(&steps) {
+                *term = gf_mul(*term, step);
+            }
+        }
+        if found != errors {
+            // The locator does not describe errors within the codeword
+            return Err(EccError::Uncorrectable);
+        }
+        for &bit in &flips[..corrected] {
+            sector[bit / 8] ^= 0x80 >> (bit % 8);
+        }
+
+        Ok(corrected)
+    }
+
+    fn check_lengths(&self, sector: &[u8], ecc: &[u8]) {
+        assert!(
+            sector.len() == self.sector_bytes,
+            "Sector length does not match BCH code"
+        );
+        assert!(
+            ecc.len() == self.ecc_bytes(),
+            "ECC length does not match BCH code"
+        );
+    }
+}
@@ -0,0 +1,491 @@
+//! Lightweight flash translation layer over NAND Flash
+//!
+//! [`Ftl`] presents the device as an array of logical pages that can be read
+//! and rewritten in any order. Each logical block is mapped to a good
+//! physical block. Rewriting a page copies its block to a free physical
+//! block, choosing the one with the fewest erases, and the old block becomes
+//! free.
+//!
+//! The mapping is stored in the spare area of the last page of each block,
+//! which is programmed last. A copy interrupted by a reset is discarded when
+//! the FTL is next mounted, leaving the previous contents. The spare area is
+//! not covered by ECC, so the header is stored twice, each copy with a CRC.
+//!
+//! Rewriting a page costs a block erase and a program of every page in the
+//! block, so writes should be batched into whole blocks where possible.
+
+use core::convert::TryInto;
+
+use super::device::{NandDevice, Status};
+use super::BadBlockTable;
+
+/// Identifies the FTL header in the spare area
+const MAGIC: u16 = 0x4654;
+/// Length in bytes of one copy of the FTL header, including its CRC
+const HEADER_BYTES: usize = 14;
+/// Length in bytes of the FTL metadata in the spare area: two copies of the
+/// header
+pub const METADATA_BYTES: usize = 2 * HEADER_BYTES;
+/// Map entry for a logical block that has not been written
+const UNMAPPED: u16 = u16::MAX;
+/// Flag in the block state for a block that holds a logical block
+const IN_USE: u32 = 1 << 31;
+/// Offset of the FTL header in the spare area of a [`NandDevice`], after
+/// the bad block marker
+const SPARE_OFFSET: usize = 2;
+
+/// Page operations on a NAND Flash device, used by the [`Ftl`]
+///
+/// Pages are numbered from the start of the device. Metadata is stored in
+/// the spare area of a page.
+pub trait FtlFlash {
+    /// Error returned by the device
+    type Error;
+
+    /// Read the data area of `page`
+    fn read_page(
+        &mut self,
+        page: usize,
+        data: &mut [u8],
+    ) -> Result<(), Self::Error>;
+
+    /// Read the metadata of `page`
+    fn read_metadata(
+        &mut self,
+        page: usize,
+        metadata: &mut [u8],
+    ) -> Result<(), Self::Error>;
+
+    /// Program the data area of `page`, and its metadata if `metadata` is
+    /// not empty. Both are programmed by a single program operation, as a
+    /// page may only be programmed once between erases
+    fn program_page(
+        &mut self,
+        page: usize,
+        data: &[u8],
+        metadata: &[u8],
+    ) -> Result<(), Self::Error>;
+
+    /// Erase the block starting at `first_page`
+    fn erase_block(&mut self, first_page: usize) -> Result<(), Self::Error>;
+}
+
+/// Errors from the [`Ftl`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FtlError<E> {
+    /// The device returned an error while reading
+    Flash(E),
+    /// The logical page is beyond the end of the FTL
+    OutOfRange,
+    /// There are no good blocks left to write to
+    NoFreeBlocks,
+}
+
+/// Flash translation layer
+///
+/// The application provides the storage: one `u16` for each logical block,
+/// one `u32` for each physical block, and a buffer of one page.
+///
+/// ```ignore
+/// let mut map = [0u16; 1000];
+/// let mut state = [0u32; 1024];
+/// let mut buffer = [0u8; 2048];
+/// let mut ftl = Ftl::mount(nand_device, table, &mut map, &mut state, &mut buffer)?;
+/// ftl.write(17, &data)?;
+/// ```
+#[derive(Debug)]
+pub struct Ftl<'a, F> {
+    flash: F,
+    bad_blocks: BadBlockTable<'a>,
+    /// Physical block for each logical block
+    map: &'a mut [u16],
+    /// Erase count of each physical block, and the `IN_USE` flag
+    state: &'a mut [u32],
+    buffer: &'a mut [u8],
+    /// Version of the most recently written block
+    version: u32,
+}
+
+/// FTL header stored with each block
+struct Header {
+    logical: u16,
+    version: u32,
+    erase_count: u32,
+}
+
+/// CRC-16/CCITT of `bytes`
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xFFFF_u16;
+    for &byte in bytes {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+impl Header {
+    fn to_bytes(&self) -> [u8; METADATA_BYTES] {
+        let mut copy = [0; HEADER_BYTES];
+        copy[0..2].copy_from_slice(&MAGIC.to_le_bytes());
+        copy[2..4].copy_from_slice(&self.logical.to_le_bytes());
+        copy[4..8].copy_from_slice(&self.version.to_le_bytes());
+        copy[8..12].copy_from_slice(&self.erase_count.to_le_bytes());
+        let crc = crc16(&copy[..12]);
+        copy[12..14].copy_from_slice(&crc.to_le_bytes());
+
+        let mut bytes = [0; METADATA_BYTES];
+        bytes[..HEADER_BYTES].copy_from_slice(&copy);
+        bytes[HEADER_BYTES..].copy_from_slice(&copy);
+        bytes
+    }
+
+    /// Decode the first copy of the header that is intact
+    fn from_bytes(bytes: &[u8; METADATA_BYTES]) -> Option<Self> {
+        bytes.chunks_exact(HEADER_BYTES).find_map(Self::from_copy)
+    }
+
+    fn from_copy(copy: &[u8]) -> Option<Self> {
+        let crc = u16::from_le_bytes(copy[12..14].try_into().unwrap());
+        if u16::from_le_bytes(copy[0..2].try_into().unwrap()) != MAGIC
+            || crc16(&copy[..12]) != crc
+        {
+            return None;
+        }
+        Some(Header {
+            logical: u16::from_le_bytes(copy[2..4].try_into().unwrap()),
+            version: u32::from_le_bytes(copy[4..8].try_into().unwrap()),
+            erase_count: u32::from_le_bytes(copy[8..12].try_into().unwrap()),
+        })
+    }
+}
+
+impl<'a, F: FtlFlash> Ftl<'a, F> {
+    /// Mount the FTL on `flash`, reading the mapping from the device
+    ///
+    /// `bad_blocks` gives the geometry of the device and the blocks that
+    /// must not be used. The number of logical blocks is the length of
+    /// `map`, and `state` must have one element for each physical block.
+    /// `buffer` is the length of the data area of a page. A device that has
+    /// never been used by the FTL must be erased first.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `state` is not the length of `bad_blocks`, or the device
+    ///   has more than 65535 blocks
+    /// * Panics if there are not more good blocks than logical blocks
+    pub fn mount(
+        mut flash: F,
+        bad_blocks: BadBlockTable<'a>,
+        map: &'a mut [u16],
+        state: &'a mut [u32],
+        buffer: &'a mut [u8],
+    ) -> Result<Self, FtlError<F::Error>> {
+        let blocks = bad_blocks.blocks();
+        assert!(
+            state.len() == blocks,
+            "FTL state must have one element for each block"
+        );
+        assert!(blocks < UNMAPPED as usize, "Too many blocks for the FTL");
+        assert!(
+            bad_blocks.good_blocks().count() > map.len(),
+            "FTL needs at least one more good block than logical blocks"
+        );
+
+        for entry in map.iter_mut() {
+            *entry = UNMAPPED;
+        }
+        for word in state.iter_mut() {
+            *word = 0;
+        }
+
+        let pages_per_block = bad_blocks.pages_per_block();
+        let mut version = 0;
+        let mut known_count = 0;
+        for block in bad_blocks.good_blocks() {
+            let mut bytes = [0; METADATA_BYTES];
+            let last = (block + 1) * pages_per_block - 1;
+            flash
+                .read_metadata(last, &mut bytes)
+                .map_err(FtlError::Flash)?;
+            let header = match Header::from_bytes(&bytes) {
+                Some(header) => header,
+                None => continue,
+            };
+            state[block] = header.erase_count & !IN_USE;
+            known_count = known_count.max(state[block]);
+            version = version.max(header.version);
+
+            let logical = header.logical as usize;
+            if logical >= map.len() {
+                continue;
+            }
+            // Keep the most recent copy of each logical block
+            let previous = map[logical];
+            if previous != UNMAPPED {
+                let previous_version = {
+                    let last = (previous as usize + 1) * pages_per_block - 1;
+                    flash
+                        .read_metadata(last, &mut bytes)
+                        .map_err(FtlError::Flash)?;
+                    Header::from_bytes(&bytes).map_or(0, |h| h.version)
+                };
+                if previous_version > header.version {
+                    continue;
+                }
+                state[previous as usize] &= !IN_USE;
+            }
+            map[logical] = block as u16;
+            state[block] |= IN_USE;
+        }
+
+        // Blocks without a header have been erased since they were last
+        // written, so their erase count is unknown
+        for block in bad_blocks.good_blocks() {
+            if state[block] == 0 {
+                state[block] = known_count;
+            }
+        }
+
+        Ok(Ftl {
+            flash,
+            bad_blocks,
+            map,
+            state,
+            buffer,
+            version,
+        })
+    }
+
+    /// Number of bytes in each logical page
+    pub fn page_bytes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Number of logical pages
+    pub fn pages(&self) -> usize {
+        self.map.len() * self.bad_blocks.pages_per_block()
+    }
+
+    /// The bad blocks, including those that failed while in use
+    pub fn bad_blocks(&self) -> &BadBlockTable<'a> {
+        &self.bad_blocks
+    }
+
+    /// Number of times physical `block` has been erased by the FTL
+    pub fn erase_count(&self, block: usize) -> u32 {
+        self.state[block] & !IN_USE
+    }
+
+    /// Release the device
+    pub fn free(self) -> F {
+        self.flash
+    }
+
+    /// Split a logical page into a logical block and page in the block
+    fn locate(
+        &self,
+        page: usize,
+    ) -> Result<(usize, usize), FtlError<F::Error>> {
+        if page >= self.pages() {
+            return Err(FtlError::OutOfRange);
+        }
+        let pages_per_block = self.bad_blocks.pages_per_block();
+        Ok((page / pages_per_block, page % pages_per_block))
+    }
+
+    /// Read logical `page` into `data`
+    ///
+    /// Pages that have never been written read as `0xFF`.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `data` is not [`page_bytes`](Ftl::page_bytes) long
+    pub fn read(
+        &mut self,
+        page: usize,
+        data: &mut [u8],
+    ) -> Result<(), FtlError<F::Error>> {
+        assert!(data.len() == self.page_bytes(), "Data must be one page");
+        let (logical, offset) = self.locate(page)?;
+        match self.map[logical] {
+            UNMAPPED => {
+                for x in data.iter_mut() {
+                    *x = 0xFF;
+                }
+                Ok(())
+            }
+            block => {
+                let pages_per_block = self.bad_blocks.pages_per_block();
+                let physical = block as usize * pages_per_block + offset;
+                self.flash
+                    .read_page(physical, data)
+                    .map_err(FtlError::Flash)
+            }
+        }
+    }
+
+    /// Write `data` to logical `page`
+    ///
+    /// The logical block containing the page is copied to a free block with
+    /// the fewest erases. A block that fails to erase or program is marked
+    /// bad, and another block is tried.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `data` is not [`page_bytes`](Ftl::page_bytes) long
+    pub fn write(
+        &mut self,
+        page: usize,
+        data: &[u8],
+    ) -> Result<(), FtlError<F::Error>> {
+        assert!(data.len() == self.page_bytes(), "Data must be one page");
+        let (logical, offset) = self.locate(page)?;
+
+        loop {
+            let block = self.allocate()?;
+            match self.copy_block(logical, offset, data, block)? {
+                true => {
+                    if self.map[logical] != UNMAPPED {
+                        self.state[self.map[logical] as usize] &= !IN_USE;
+                    }
+                    self.map[logical] = block as u16;
+                    self.state[block] |= IN_USE;
+                    return Ok(());
+                }
+                false => self.bad_blocks.mark_bad(block),
+            }
+        }
+    }
+
+    /// The free good block with the fewest erases
+    fn allocate(&mut self) -> Result<usize, FtlError<F::Error>> {
+        let state = &self.state;
+        self.bad_blocks
+            .good_blocks()
+            .filter(|&block| state[block] & IN_USE == 0)
+            .min_by_key(|&block| state[block])
+            .ok_or(FtlError::NoFreeBlocks)
+    }
+
+    /// Erase `block` and write logical block `logical` to it, with `data` at
+    /// page `offset`. Returns false if the erase or a program fails
+    fn copy_block(
+        &mut self,
+        logical: usize,
+        offset: usize,
+        data: &[u8],
+        block: usize,
+    ) -> Result<bool, FtlError<F::Error>> {
+        let pages_per_block = self.bad_blocks.pages_per_block();
+        let first = block * pages_per_block;
+
+        self.state[block] += 1;
+        if self.flash.erase_block(first).is_err() {
+            return Ok(false);
+        }
+
+        self.version += 1;
+        let header = Header {
+            logical: logical as u16,
+            version: self.version,
+            erase_count: self.erase_count(block),
+        }
+        .to_bytes();
+        let source = self.map[logical];
+        for page in 0..pages_per_block {
+            let metadata: &[u8] = if page == pages_per_block - 1 {
+                &header
+            } else {
+                &[]
+            };
+            let result = if page == offset {
+                self.flash.program_page(first + page, data, metadata)
+            } else {
+                if source == UNMAPPED {
+                    for x in self.buffer.iter_mut() {
+                        *x = 0xFF;
+                    }
+                } else {
+                    let from = source as usize * pages_per_block + page;
+                    self.flash
+                        .read_page(from, self.buffer)
+                        .map_err(FtlError::Flash)?;
+                }
+                self.flash.program_page(first + page, self.buffer, metadata)
+            };
+            if result.is_err() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Data and metadata without ECC. The metadata is stored from the third byte
+/// of the spare area, after the bad block marker, and is programmed with the
+/// data in a single page program, as some devices allow only one program of
+/// each page between erases
+impl FtlFlash for NandDevice {
+    type Error = Status;
+
+    fn read_page(
+        &mut self,
+        page: usize,
+        data: &mut [u8],
+    ) -> Result<(), Status> {
+        let address = self.page_address(page);
+        self.page_read(address, false, data);
+        Ok(())
+    }
+
+    fn read_metadata(
+        &mut self,
+        page: usize,
+        metadata: &mut [u8],
+    ) -> Result<(), Status> {
+        let address = self.page_address(page) + SPARE_OFFSET;
+        self.page_read(address, true, metadata);
+        Ok(())
+    }
+
+    fn program_page(
+        &mut self,
+        page: usize,
+        data: &[u8],
+        metadata: &[u8],
+    ) -> Result<(), Status> {
+        let address = self.page_address(page);
+        self.start_page_program(address, false);
+        self.write_data(data);
+        if !metadata.is_empty() {
+            // Continue the data input into the spare area. Bytes programmed
+            // as 0xFF are left erased, including the bad block marker
+            let page_bytes = self.page_address(1);
+            let padding = page_bytes - data.len() + SPARE_OFFSET;
+            let bus_bytes = self.bus_bytes();
+            for _ in 0..padding / bus_bytes {
+                self.write_data(&[0xFF; 2][..bus_bytes]);
+            }
+            self.write_data(metadata);
+        }
+        match self.finish_page_program() {
+            status @ Status::Fail(_) => Err(status),
+            Status::Success(_) => Ok(()),
+        }
+    }
+
+    fn erase_block(&mut self, first_page: usize) -> Result<(), Status> {
+        let address = self.page_address(first_page);
+        match self.block_erase(address) {
+            status @ Status::Fail(_) => Err(status),
+            Status::Success(_) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! `embedded-storage` traits for NAND Flash
+//!
+//! [`NandStorage`] implements [`ReadNorFlash`] and [`NorFlash`], so that
+//! storage stacks written against `embedded-storage` can use a NAND Flash
+//! device. Writes are whole pages and erases are whole blocks, which are
+//! given as const parameters because the traits require them as constants.
+//!
+//! Reads may start and end at any byte, also on a 16-bit bus.
+//!
+//! NAND Flash pages may only be programmed once between erases, and in
+//! order within a block, so [`MultiwriteNorFlash`] is not implemented. Bad
+//! blocks are not handled, so the storage stack must avoid them or be given
+//! a range of good blocks.
+//!
+//! [`MultiwriteNorFlash`]: embedded_storage::nor_flash::MultiwriteNorFlash
+
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash,
+    NorFlashErrorKind, ReadNorFlash,
+};
+
+use super::device::{NandDevice, Status};
+
+/// A NAND Flash device with `PAGE_BYTES` bytes in the data area of each page
+/// and `PAGES_PER_BLOCK` pages in each block
+///
+/// ```ignore
+/// let mut storage = NandStorage::<4096, 64>::new(nand_device, 4096);
+/// storage.erase(0, 4096 * 64)?;
+/// storage.write(0, &page)?;
+/// ```
+#[derive(Debug)]
+pub struct NandStorage<const PAGE_BYTES: usize, const PAGES_PER_BLOCK: usize> {
+    device: NandDevice,
+    blocks: usize,
+}
+
+impl<const PAGE_BYTES: usize, const PAGES_PER_BLOCK: usize>
+    NandStorage<PAGE_BYTES, PAGES_PER_BLOCK>
+{
+    /// Use the first `blocks` blocks of `device` as storage
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `PAGE_BYTES` does not match the column address bits of
+    ///   the device
+    /// * Panics if the capacity does not fit in a `u32`
+    pub fn new(device: NandDevice, blocks: usize) -> Self {
+        assert!(
+            device.page_address(1) == PAGE_BYTES,
+            "Page length does not match the NAND device"
+        );
+        assert!(
+            Self::capacity_for(blocks).is_some(),
+            "NAND storage too large for embedded-storage"
+        );
+
+        NandStorage { device, blocks }
+    }
+
+    /// Capacity in bytes of `blocks` blocks, or `None` if it cannot be
+    /// addressed by the `u32` offsets of `embedded-storage`
+    pub fn capacity_for(blocks: usize) -> Option<usize> {
+        let bytes = (blocks as u64)
+            .checked_mul(PAGES_PER_BLOCK as u64)?
+            .checked_mul(PAGE_BYTES as u64)?;
+        if bytes <= u64::from(u32::MAX) {
+            Some(bytes as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Release the device
+    pub fn free(self) -> NandDevice {
+        self.device
+    }
+
+    /// Read `bytes` from within one page
+    ///
+    /// On a 16-bit bus, a byte at an odd offset or at the end of an odd
+    /// length is read through a bounce word, as each bus cycle transfers two
+    /// bytes.
+    fn read_page(&mut self, offset: usize, bytes: &mut [u8]) {
+        let bus_bytes = self.device.bus_bytes();
+        let head = offset % bus_bytes;
+        self.device.start_page_read(offset - head, false);
+
+        let mut word = [0u8; 2];
+        let mut bytes = bytes;
+        if head != 0 {
+            self.device.read_data(&mut word);
+            let (first, rest) = bytes.split_at_mut(1);
+            first[0] = word[1];
+            bytes = rest;
+        }
+        let aligned = bytes.len() - bytes.len() % bus_bytes;
+        let (middle, tail) = bytes.split_at_mut(aligned);
+        self.device.read_data(middle);
+        if let Some(last) = tail.first_mut() {
+            self.device.read_data(&mut word);
+            *last = word[0];
+        }
+    }
+}
+
+/// Convert a failed program or erase to an error
+fn check_status(status: Status) -> Result<(), NorFlashErrorKind> {
+    match status {
+        Status::Success(_) => Ok(()),
+        Status::Fail(_) => Err(NorFlashErrorKind::Other),
+    }
+}
+
+impl<const PAGE_BYTES: usize, const PAGES_PER_BLOCK: usize> ErrorType
+    for NandStorage<PAGE_BYTES, PAGES_PER_BLOCK>
+{
+    type Error = NorFlashErrorKind;
+}
+
+impl<const PAGE_BYTES: usize, const PAGES_PER_BLOCK: usize> ReadNorFlash
+    for NandStorage<PAGE_BYTES, PAGES_PER_BLOCK>
+{
+    const READ_SIZE: usize = 1;
+
+    fn read(
+        &mut self,
+        offset: u32,
+        bytes: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+
+        // Each page is a separate read operation
+        let mut offset = offset as usize;
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let len = (PAGE_BYTES - offset % PAGE_BYTES).min(bytes.len());
+            let (chunk, rest) = bytes.split_at_mut(len);
+            self.read_page(offset, chunk);
+            offset += len;
+            bytes = rest;
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.blocks * PAGES_PER_BLOCK * PAGE_BYTES
+    }
+}
+
+impl<const PAGE_BYTES: usize, const PAGES_PER_BLOCK: usize> NorFlash
+    for NandStorage<PAGE_BYTES, PAGES_PER_BLOCK>
+{
+    const WRITE_SIZE: usize = PAGE_BYTES;
+    const ERASE_SIZE: usize = PAGE_BYTES * PAGES_PER_BLOCK;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+
+        for block in (from as usize..to as usize).step_by(Self::ERASE_SIZE) {
+            check_status(self.device.block_erase(block))?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+
+        for (i, page) in bytes.chunks(PAGE_BYTES).enumerate() {
+            let address = offset as usize + i * PAGE_BYTES;
+            check_status(self.device.page_program(address, false, page))?;
+        }
+        Ok(())
+    }
+}
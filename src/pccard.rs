@@ -0,0 +1,246 @@
+//! HAL for FSMC peripheral used to access PC Card / CompactFlash
+//!
+//! The PC Card bank (Bank 4) is only present on the older FSMC peripherals,
+//! for example on STM32F1, STM32F2 and STM32F4 parts. On FMC peripherals the
+//! corresponding registers are reserved.
+
+use core::cmp;
+use core::marker::PhantomData;
+
+use embedded_hal::delay::DelayNs;
+
+use crate::fmc::{FmcBank, FmcRegisters};
+use crate::FmcPeripheral;
+
+use crate::ral::{fmc, modify_reg};
+
+pub mod ata;
+
+/// FSMC PC Card Physical Interface Configuration
+///
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PccardConfiguration {
+    /// Data path width in bits
+    pub data_width: u8,
+    /// Extend accesses using the card's NWAIT signal
+    pub wait_feature: bool,
+}
+
+/// Timing parameters for one PC Card memory space
+///
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PccardSpaceTiming {
+    /// Address setup time before the command (nOE, nWE, nIORD, nIOWR) is
+    /// asserted
+    pub setup_ns: i32,
+    /// Command pulse width
+    pub command_ns: i32,
+    /// Address hold time after the command is deasserted
+    pub hold_ns: i32,
+    /// Time for which the data bus is kept high-impedance at the start of a
+    /// write
+    pub hiz_ns: i32,
+}
+
+/// FSMC PC Card Timing parameters
+///
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PccardTiming {
+    /// Common memory space
+    pub common: PccardSpaceTiming,
+    /// Attribute memory space
+    pub attribute: PccardSpaceTiming,
+    /// I/O space
+    pub io: PccardSpaceTiming,
+}
+
+/// Respresents a model of PC Card or CompactFlash card
+pub trait PccardChip {
+    /// PC Card controller configuration
+    const CONFIG: PccardConfiguration;
+    /// Timing parameters
+    const TIMING: PccardTiming;
+}
+
+/// FSMC Peripheral specialized as a PC Card Controller. Not yet initialized.
+#[allow(missing_debug_implementations)]
+pub struct Pccard<FMC, IC> {
+    /// Parameters for the card
+    _chip: PhantomData<IC>,
+    /// FMC peripheral
+    fmc: FMC,
+    /// Register access
+    regs: FmcRegisters,
+}
+
+/// Memory spaces of an initialized PC Card
+#[derive(Clone, Debug, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct PccardDevice {
+    common_memory: *mut u8,
+    attribute_memory: *mut u8,
+    io_space: *mut u8,
+}
+
+impl<IC: PccardChip, FMC: FmcPeripheral> Pccard<FMC, IC> {
+    /// New PC Card instance
+    ///
+    /// # Safety
+    ///
+    /// This method does not ensure that IO pins are configured
+    /// correctly. Misconfiguration may result in a bus lockup or stall when
+    /// attempting to access the card.
+    ///
+    /// The FMC peripheral must be an FSMC with a PC Card bank.
+    pub unsafe fn new_unchecked(fmc: FMC, _chip: IC) -> Self {
+        Pccard {
+            _chip: PhantomData,
+            fmc,
+            regs: FmcRegisters::new::<FMC>(),
+        }
+    }
+
+    /// Initialise PC Card instance. `delay` is used to wait 1µs after enabling
+    /// the memory controller.
+    ///
+    /// Returns a [`PccardDevice`](PccardDevice) instance.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if any setting in `IC::CONFIG` cannot be achieved
+    /// * Panics if the FMC Kernel Clock is too fast to achieve the timing
+    ///   required by the card
+    pub fn init<D>(&mut self, delay: &mut D) -> PccardDevice
+    where
+        D: DelayNs,
+    {
+        // calculate clock period, round down
+        let fmc_source_ck_hz = self.fmc.source_clock_hz();
+        let ker_clk_period_ns = 1_000_000_000u32 / fmc_source_ck_hz;
+
+        // enable memory controller AHB register access. Skipped if another
+        // memory controller is running, as this may reset the FMC
+        if !self.regs.in_use() {
+            self.fmc.enable();
+        }
+
+        // device features and timing
+        self.set_features_timings(IC::CONFIG, IC::TIMING, ker_clk_period_ns);
+
+        // enable memory controller
+        self.fmc.memory_controller_enable();
+        delay.delay_us(1);
+
+        // PC Card is always on Bank 4
+        let ptr = FmcBank::Bank4.ptr() as *mut u8;
+        PccardDevice {
+            common_memory: ptr,
+            attribute_memory: ptr.wrapping_add(0x800_0000),
+            io_space: ptr.wrapping_add(0xC00_0000),
+        }
+    }
+
+    /// Program memory device features and timings
+    fn set_features_timings(
+        &mut self,
+        config: PccardConfiguration,
+        timing: PccardTiming,
+        period_ns: u32,
+    ) {
+        let period_ns = period_ns as i32;
+        let n_clock_periods = |time_ns: i32| {
+            (time_ns + period_ns - 1) / period_ns // round up
+        };
+        let space = |t: PccardSpaceTiming| {
+            // setup time is SET + 1 cycles
+            let set = cmp::max(n_clock_periods(t.setup_ns), 1) - 1;
+            assert!(set < 255, "FMC ker clock too fast"); // 255 = reserved
+
+            // command assertion time is WAIT + 1 cycles (minimum = 2)
+            let wait = cmp::max(n_clock_periods(t.command_ns), 2) - 1;
+            assert!(wait < 255, "FMC ker clock too fast"); // 255 = reserved
+
+            // hold time after command deassertion (minimum = 1)
+            let hold = cmp::max(n_clock_periods(t.hold_ns), 1);
+            assert!(hold < 255, "FMC ker clock too fast"); // 255 = reserved
+
+            // data bus high-impedance time
+            let hiz = cmp::max(n_clock_periods(t.hiz_ns), 0);
+            assert!(hiz < 255, "FMC ker clock too fast"); // 255 = reserved
+
+            (set as u32, wait as u32, hold as u32, hiz as u32)
+        };
+
+        let data_width = match config.data_width {
+            8 => 0,
+            16 => 1,
+            _ => panic!("not possible"),
+        };
+
+        // PCR4
+        #[rustfmt::skip]
+        modify_reg!(fmc, self.regs.global(), PCR4,
+                    ECCEN: 0,   // 0b0: ECC computation disabled
+                    PWID: data_width,
+                    PTYP: 0,    // 0b0: PC Card, CompactFlash, CF+ or PCMCIA
+                    PWAITEN: config.wait_feature as u32
+        );
+
+        // PMEM4: Common memory space timing register
+        let (set, wait, hold, hiz) = space(timing.common);
+        #[rustfmt::skip]
+        modify_reg!(fmc, self.regs.global(), PMEM4,
+                    MEMHIZ: hiz,
+                    MEMHOLD: hold,
+                    MEMWAIT: wait,
+                    MEMSET: set);
+
+        // PATT4: Attribute memory space timing register
+        let (set, wait, hold, hiz) = space(timing.attribute);
+        #[rustfmt::skip]
+        modify_reg!(fmc, self.regs.global(), PATT4,
+                    ATTHIZ: hiz,
+                    ATTHOLD: hold,
+                    ATTWAIT: wait,
+                    ATTSET: set);
+
+        // PIO4: I/O space timing register
+        let (set, wait, hold, hiz) = space(timing.io);
+        #[rustfmt::skip]
+        modify_reg!(fmc, self.regs.global(), PIO4,
+                    IOHIZ: hiz,
+                    IOHOLD: hold,
+                    IOWAIT: wait,
+                    IOSET: set);
+
+        // Enable
+        #[rustfmt::skip]
+        modify_reg!(fmc, self.regs.global(), PCR4,
+                    PBKEN: 1);
+    }
+}
+
+/// Methods to allow users to access the memory spaces of the card using
+/// `unsafe`.
+///
+impl PccardDevice {
+    /// Return a Raw Pointer to the common memory space. This memory-mapped
+    /// region is accessed with the nOE / nWE strobes.
+    pub fn common_memory(&mut self) -> *mut u8 {
+        self.common_memory
+    }
+    /// Return a Raw Pointer to the attribute memory space. This memory-mapped
+    /// region is accessed with nREG asserted, and contains the Card
+    /// Information Structure (CIS) and configuration registers.
+    pub fn attribute_memory(&mut self) -> *mut u8 {
+        self.attribute_memory
+    }
+    /// Return a Raw Pointer to the I/O space. This memory-mapped region is
+    /// accessed with the nIORD / nIOWR strobes.
+    pub fn io_space(&mut self) -> *mut u8 {
+        self.io_space
+    }
+}
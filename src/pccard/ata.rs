@@ -0,0 +1,307 @@
+//! ATA register-level access to a CompactFlash card through the FSMC PC Card
+//! bank
+//!
+//! The card is switched to contiguous I/O mode by writing its configuration
+//! option register, and the task file registers are then accessed at offsets
+//! 0x00 - 0x0F of the I/O space. Data transfers use PIO and require a 16-bit
+//! data bus. Registers and commands are referenced to the CF+ and CompactFlash
+//! Specification Revision 6.1.
+//!
+//! The card is used in PC Card I/O mode rather than True IDE mode. True IDE
+//! mode is selected by holding the card's -OE pin low at power on, but the
+//! FSMC drives -OE as NOE of the PC Card bank and holds it high while idle.
+//! Contiguous I/O mode gives access to the same task file registers, so it
+//! needs no extra wiring.
+//!
+//! Reads from the data and status registers have side effects, so the
+//! external memory region used for the I/O space (0x9000_0000 -
+//! 0x9FFF_FFFF) should be configured as Device memory using the MPU to
+//! prevent speculative accesses.
+
+use core::ptr;
+use core::sync::atomic::{fence, Ordering};
+
+use super::PccardDevice;
+
+/// Offset of the configuration option register in the attribute memory
+const CONFIGURATION_OPTION: usize = 0x200;
+/// Configuration index for contiguous I/O mode, where the card decodes
+/// address lines A3 - A0 only
+const CONTIGUOUS_IO: u8 = 0x01;
+/// Offset of the alternate status / device control register in contiguous
+/// I/O mode
+const ALTERNATE_STATUS: usize = 0x0E;
+
+/// Number of times the status register is read while waiting for the card,
+/// before [`AtaError::Timeout`] is returned. Each read is a PC Card I/O cycle
+/// of a few hundred nanoseconds, so this allows the card a few seconds
+const STATUS_POLLS: u32 = 10_000_000;
+
+/// Bytes in one ATA sector
+pub const SECTOR_SIZE: usize = 512;
+
+/// Task file registers
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+    /// Data register (16-bit)
+    Data = 0,
+    /// Error register (read) / Features register (write)
+    ErrorFeatures = 1,
+    /// Sector Count register
+    SectorCount = 2,
+    /// Sector Number register, LBA bits 7-0
+    SectorNumber = 3,
+    /// Cylinder Low register, LBA bits 15-8
+    CylinderLow = 4,
+    /// Cylinder High register, LBA bits 23-16
+    CylinderHigh = 5,
+    /// Drive/Head register, LBA bits 27-24
+    DriveHead = 6,
+    /// Status register (read) / Command register (write)
+    StatusCommand = 7,
+}
+
+/// ATA Commands
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(unused)]
+enum Command {
+    /// 0x20 Read Sector(s)
+    ReadSectors = 0x20,
+    /// 0x30 Write Sector(s)
+    WriteSectors = 0x30,
+    /// 0xEC Identify Device
+    IdentifyDevice = 0xEC,
+}
+
+/// Status register bits
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Status(pub u8);
+impl Status {
+    /// BSY: The card is busy
+    pub fn busy(&self) -> bool {
+        self.0 & 0x80 != 0
+    }
+    /// RDY: The card is ready to accept a command
+    pub fn ready(&self) -> bool {
+        self.0 & 0x40 != 0
+    }
+    /// DWF: A write fault has occurred
+    pub fn write_fault(&self) -> bool {
+        self.0 & 0x20 != 0
+    }
+    /// DRQ: The card is ready to transfer data
+    pub fn data_request(&self) -> bool {
+        self.0 & 0x08 != 0
+    }
+    /// ERR: The previous command ended in an error
+    pub fn error(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+}
+
+/// Errors reported by the card
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtaError {
+    /// The ERR bit was set in the status register. Contains the value of the
+    /// error register
+    Error(u8),
+    /// The DWF bit was set in the status register
+    WriteFault,
+    /// The card stayed busy, or did not request a data transfer
+    Timeout,
+}
+
+/// CompactFlash card accessed through the ATA task file registers
+#[derive(Clone, Debug, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct Ata {
+    registers: *mut u8,
+}
+
+unsafe fn write_volatile_sync<T>(dest: *mut T, src: T) {
+    ptr::write_volatile(dest, src);
+
+    // Ensure that the write is committed before continuing. In the default
+    // ARMv7-M address map the space 0x8000_0000-0x9FFF_FFFF is Normal Memory
+    // with write-though cache attribute.
+    fence(Ordering::SeqCst);
+}
+
+impl PccardDevice {
+    /// Access the card using the ATA task file registers in the I/O space
+    ///
+    /// The card is switched from memory mode, which it is in after reset, to
+    /// contiguous I/O mode by writing its configuration option register.
+    pub fn into_ata(self) -> Ata {
+        unsafe {
+            write_volatile_sync(
+                self.attribute_memory.add(CONFIGURATION_OPTION),
+                CONTIGUOUS_IO,
+            );
+        }
+        Ata {
+            registers: self.io_space,
+        }
+    }
+}
+
+impl Ata {
+    /// Read a task file register
+    pub fn read_register(&mut self, register: Register) -> u8 {
+        unsafe { ptr::read_volatile(self.registers.add(register as usize)) }
+    }
+    /// Write a task file register
+    pub fn write_register(&mut self, register: Register, value: u8) {
+        unsafe {
+            write_volatile_sync(self.registers.add(register as usize), value);
+        }
+    }
+    /// Read the alternate status register. Unlike the status register this
+    /// does not acknowledge a pending interrupt
+    pub fn status(&mut self) -> Status {
+        unsafe {
+            Status(ptr::read_volatile(self.registers.add(ALTERNATE_STATUS)))
+        }
+    }
+    /// Write a command to the command register
+    pub fn command(&mut self, command: u8) {
+        self.write_register(Register::StatusCommand, command);
+    }
+    /// Wait until the card is not busy, then check for errors
+    ///
+    /// Returns [`AtaError::Timeout`] if the card is still busy after a few
+    /// seconds.
+    pub fn wait_not_busy(&mut self) -> Result<Status, AtaError> {
+        for _ in 0..STATUS_POLLS {
+            let status = self.status();
+            if !status.busy() {
+                return self.check_status(status);
+            }
+        }
+        Err(AtaError::Timeout)
+    }
+    /// Check the error bits of a status that is not busy
+    fn check_status(&mut self, status: Status) -> Result<Status, AtaError> {
+        if status.error() {
+            Err(AtaError::Error(self.read_register(Register::ErrorFeatures)))
+        } else if status.write_fault() {
+            Err(AtaError::WriteFault)
+        } else {
+            Ok(status)
+        }
+    }
+    /// Wait until the card is not busy, without returning the status
+    fn wait_idle(&mut self) -> Result<(), AtaError> {
+        self.wait_not_busy().map(|_| ())
+    }
+    /// Wait until the card requests a data transfer
+    fn wait_data_request(&mut self) -> Result<(), AtaError> {
+        for _ in 0..STATUS_POLLS {
+            let status = self.status();
+            if !status.busy() && self.check_status(status)?.data_request() {
+                return Ok(());
+            }
+        }
+        Err(AtaError::Timeout)
+    }
+    /// Read words from the data register
+    pub fn read_data(&mut self, data: &mut [u16]) {
+        let register = self.registers as *mut u16;
+        for x in data {
+            unsafe {
+                *x = ptr::read_volatile(register);
+            }
+        }
+    }
+    /// Write words to the data register
+    pub fn write_data(&mut self, data: &[u16]) {
+        let register = self.registers as *mut u16;
+        for x in data {
+            unsafe {
+                write_volatile_sync(register, *x);
+            }
+        }
+    }
+    /// Select the first `count` sectors starting from `lba` in LBA mode
+    fn select_sectors(&mut self, lba: u32, count: usize) {
+        assert!(lba < (1 << 28), "LBA out of range");
+        assert!(
+            (1..=256).contains(&count),
+            "Between 1 and 256 sectors can be transferred by a single command"
+        );
+
+        self.write_register(Register::SectorCount, count as u8); // 0 = 256
+        self.write_register(Register::SectorNumber, lba as u8);
+        self.write_register(Register::CylinderLow, (lba >> 8) as u8);
+        self.write_register(Register::CylinderHigh, (lba >> 16) as u8);
+        // LBA mode, drive 0
+        self.write_register(Register::DriveHead, 0xE0 | (lba >> 24) as u8);
+    }
+
+    /// 0xEC Identify Device
+    ///
+    /// Reads the 256 word identify device information
+    pub fn identify(&mut self, info: &mut [u16; 256]) -> Result<(), AtaError> {
+        self.wait_idle()?;
+        self.command(Command::IdentifyDevice as u8);
+        self.wait_data_request()?;
+        self.read_data(info);
+        self.wait_idle()
+    }
+
+    /// 0x20 Read Sector(s)
+    ///
+    /// Reads sectors starting from `lba` into `data`. The length of `data`
+    /// determines the number of sectors read, and must be a multiple of the
+    /// sector size.
+    pub fn read_sectors(
+        &mut self,
+        lba: u32,
+        data: &mut [u8],
+    ) -> Result<(), AtaError> {
+        assert!(data.len() % SECTOR_SIZE == 0);
+        self.wait_idle()?;
+        self.select_sectors(lba, data.len() / SECTOR_SIZE);
+        self.command(Command::ReadSectors as u8);
+
+        let mut words = [0u16; SECTOR_SIZE / 2];
+        for sector in data.chunks_exact_mut(SECTOR_SIZE) {
+            self.wait_data_request()?;
+            self.read_data(&mut words);
+            for (bytes, word) in sector.chunks_exact_mut(2).zip(words.iter()) {
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+        }
+        self.wait_idle()
+    }
+
+    /// 0x30 Write Sector(s)
+    ///
+    /// Writes `data` to sectors starting from `lba`, and waits for the write
+    /// to complete. The length of `data` determines the number of sectors
+    /// written, and must be a multiple of the sector size.
+    pub fn write_sectors(
+        &mut self,
+        lba: u32,
+        data: &[u8],
+    ) -> Result<(), AtaError> {
+        assert!(data.len() % SECTOR_SIZE == 0);
+        self.wait_idle()?;
+        self.select_sectors(lba, data.len() / SECTOR_SIZE);
+        self.command(Command::WriteSectors as u8);
+
+        let mut words = [0u16; SECTOR_SIZE / 2];
+        for sector in data.chunks_exact(SECTOR_SIZE) {
+            for (word, bytes) in words.iter_mut().zip(sector.chunks_exact(2)) {
+                *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+            }
+            self.wait_data_request()?;
+            self.write_data(&words);
+        }
+        self.wait_idle()
+    }
+}
@@ -3,6 +3,7 @@
 pub mod peripherals;
 pub mod register;
 
+#[allow(unused_imports)]
 pub use crate::{modify_reg, read_reg, write_reg};
 
 pub mod fmc {
@@ -1,5 +1,6 @@
 #![allow(non_snake_case, non_upper_case_globals)]
 #![allow(non_camel_case_types)]
+#![allow(unexpected_cfgs)]
 //! Flexible memory controller
 //!
 //! With the caveat that SDCMR:MRD is 13-bits wide on these parts:
@@ -7,7 +8,7 @@
 //! Used by: stm32f469, stm32f479
 //! Used by: stm32f745, stm32f765, stm32f7x6, stm32f7x7, stm32f7x9
 //!
-//! With the caveat that BCR1:{FMCEN,BMAP} are not included:
+//! With BCR1:{FMCEN,BMAP}, which are reserved on other parts:
 //!
 //! Used by: stm32h743, stm32h743v, stm32h747cm4, stm32h747cm7, stm32h753, stm32h753v
 
@@ -18,6 +19,51 @@ use core::marker::PhantomData;
 /// SRAM/NOR-Flash chip-select control register 1
 pub mod BCR1 {
 
+    /// FMCEN
+    pub mod FMCEN {
+        /// Offset (31 bits)
+        pub const offset: u32 = 31;
+        /// Mask (1 bit: 1 << 31)
+        pub const mask: u32 = 1 << offset;
+        /// Read-only values (empty)
+        pub mod R {}
+        /// Write-only values (empty)
+        pub mod W {}
+        /// Read-write values
+        pub mod RW {
+
+            /// 0b0: Disable the FMC controller
+            pub const Disabled: u32 = 0b0;
+
+            /// 0b1: Enable the FMC controller
+            pub const Enabled: u32 = 0b1;
+        }
+    }
+
+    /// BMAP
+    pub mod BMAP {
+        /// Offset (24 bits)
+        pub const offset: u32 = 24;
+        /// Mask (2 bits: 0b11 << 24)
+        pub const mask: u32 = 0b11 << offset;
+        /// Read-only values (empty)
+        pub mod R {}
+        /// Write-only values (empty)
+        pub mod W {}
+        /// Read-write values
+        pub mod RW {
+
+            /// 0b00: Default mapping
+            pub const Default: u32 = 0b00;
+
+            /// 0b01: NOR/PSRAM bank and SDRAM bank 1/bank 2 are swapped
+            pub const Swapped: u32 = 0b01;
+
+            /// 0b10: SDRAM bank 2 remapped on FMC bank 2 and still accessible at default mapping
+            pub const Remapped: u32 = 0b10;
+        }
+    }
+
     /// CCLKEN
     pub mod CCLKEN {
         /// Offset (20 bits)
@@ -1016,6 +1062,9 @@ pub mod PCR {
         /// Read-write values
         pub mod RW {
 
+            /// 0b0: PC Card, CompactFlash, CF+ or PCMCIA
+            pub const PCCard: u32 = 0b0;
+
             /// 0b1: NAND Flash
             pub const NANDFlash: u32 = 0b1;
         }
@@ -1353,6 +1402,105 @@ pub mod ECCR {
     }
 }
 
+/// PC Card control register 4 (FSMC only)
+pub mod PCR4 {
+    pub use super::PCR::ECCEN;
+    pub use super::PCR::ECCPS;
+    pub use super::PCR::PBKEN;
+    pub use super::PCR::PTYP;
+    pub use super::PCR::PWAITEN;
+    pub use super::PCR::PWID;
+    pub use super::PCR::TAR;
+    pub use super::PCR::TCLR;
+}
+
+/// FIFO status and interrupt register 4 (FSMC only)
+pub mod SR4 {
+    pub use super::SR::FEMPT;
+    pub use super::SR::IFEN;
+    pub use super::SR::IFS;
+    pub use super::SR::ILEN;
+    pub use super::SR::ILS;
+    pub use super::SR::IREN;
+    pub use super::SR::IRS;
+}
+
+/// Common memory space timing register 4 (FSMC only)
+pub mod PMEM4 {
+    pub use super::PMEM::MEMHIZ;
+    pub use super::PMEM::MEMHOLD;
+    pub use super::PMEM::MEMSET;
+    pub use super::PMEM::MEMWAIT;
+}
+
+/// Attribute memory space timing register 4 (FSMC only)
+pub mod PATT4 {
+    pub use super::PATT::ATTHIZ;
+    pub use super::PATT::ATTHOLD;
+    pub use super::PATT::ATTSET;
+    pub use super::PATT::ATTWAIT;
+}
+
+/// I/O space timing register 4 (FSMC only)
+pub mod PIO4 {
+
+    /// IOHIZx
+    pub mod IOHIZ {
+        /// Offset (24 bits)
+        pub const offset: u32 = 24;
+        /// Mask (8 bits: 0xff << 24)
+        pub const mask: u32 = 0xff << offset;
+        /// Read-only values (empty)
+        pub mod R {}
+        /// Write-only values (empty)
+        pub mod W {}
+        /// Read-write values (empty)
+        pub mod RW {}
+    }
+
+    /// IOHOLDx
+    pub mod IOHOLD {
+        /// Offset (16 bits)
+        pub const offset: u32 = 16;
+        /// Mask (8 bits: 0xff << 16)
+        pub const mask: u32 = 0xff << offset;
+        /// Read-only values (empty)
+        pub mod R {}
+        /// Write-only values (empty)
+        pub mod W {}
+        /// Read-write values (empty)
+        pub mod RW {}
+    }
+
+    /// IOWAITx
+    pub mod IOWAIT {
+        /// Offset (8 bits)
+        pub const offset: u32 = 8;
+        /// Mask (8 bits: 0xff << 8)
+        pub const mask: u32 = 0xff << offset;
+        /// Read-only values (empty)
+        pub mod R {}
+        /// Write-only values (empty)
+        pub mod W {}
+        /// Read-write values (empty)
+        pub mod RW {}
+    }
+
+    /// IOSETx
+    pub mod IOSET {
+        /// Offset (0 bits)
+        pub const offset: u32 = 0;
+        /// Mask (8 bits: 0xff << 0)
+        pub const mask: u32 = 0xff << offset;
+        /// Read-only values (empty)
+        pub mod R {}
+        /// Write-only values (empty)
+        pub mod W {}
+        /// Read-write values (empty)
+        pub mod RW {}
+    }
+}
+
 /// SRAM/NOR-Flash write timing registers 1
 pub mod BWTR1 {
 
@@ -2110,7 +2258,24 @@ pub struct RegisterBlock {
     /// ECC result register
     pub ECCR: RORegister<u32>,
 
-    _reserved3: [u32; 27],
+    _reserved3: [u32; 2],
+
+    /// PC Card control register 4 (FSMC only)
+    pub PCR4: RWRegister<u32>,
+
+    /// FIFO status and interrupt register 4 (FSMC only)
+    pub SR4: RWRegister<u32>,
+
+    /// Common memory space timing register 4 (FSMC only)
+    pub PMEM4: RWRegister<u32>,
+
+    /// Attribute memory space timing register 4 (FSMC only)
+    pub PATT4: RWRegister<u32>,
+
+    /// I/O space timing register 4 (FSMC only)
+    pub PIO4: RWRegister<u32>,
+
+    _reserved3a: [u32; 20],
 
     /// SRAM/NOR-Flash write timing registers 1
     pub BWTR1: RWRegister<u32>,
@@ -2167,6 +2332,11 @@ pub struct ResetValues {
     pub PMEM: u32,
     pub PATT: u32,
     pub ECCR: u32,
+    pub PCR4: u32,
+    pub SR4: u32,
+    pub PMEM4: u32,
+    pub PATT4: u32,
+    pub PIO4: u32,
     pub BWTR1: u32,
     pub BWTR2: u32,
     pub BWTR3: u32,
@@ -1,15 +1,44 @@
 //! HAL for external SDRAM
 
 use core::cmp;
-use core::convert::TryInto;
+use core::fmt;
 use core::marker::PhantomData;
+use core::mem::size_of;
+use core::ptr;
+use core::slice;
 
 use embedded_hal::delay::DelayNs;
 
 use crate::fmc::{AddressPinSet, FmcBank, FmcRegisters};
 use crate::FmcPeripheral;
 
-use crate::ral::{fmc, modify_reg, write_reg};
+use crate::ral::{fmc, modify_reg, read_reg, write_reg};
+
+mod external_ram;
+#[cfg(feature = "embedded-dma")]
+pub use external_ram::DmaBuffer;
+pub use external_ram::{ExternalRam, ExternalRamWord, Partition};
+
+mod retained;
+pub use retained::{SealError, SEAL_HEADER_LEN};
+
+#[cfg(feature = "dcache")]
+mod cache;
+
+#[cfg(feature = "graphics")]
+mod framebuffer;
+#[cfg(feature = "graphics")]
+pub use framebuffer::{DoubleBuffer, Framebuffer};
+
+#[cfg(feature = "alloc")]
+mod heap;
+#[cfg(feature = "alloc")]
+pub use heap::ExternalHeap;
+
+mod mode_register;
+pub use mode_register::{
+    Cas, DriveStrength, ExtendedModeRegister, ModeRegister, Pasr, Tcsr,
+};
 
 /// FMC SDRAM Configuration Structure definition
 ///
@@ -29,11 +58,24 @@ pub struct SdramConfiguration {
     /// Enables the SDRAM device to be accessed in write mode
     pub write_protection: bool,
     /// This bit enable the SDRAM controller to anticipate the next read
+    ///
+    /// Check the errata sheet for your device before enabling this. Read
+    /// bursts can be forced off for an `SdramChip` with
+    /// [`override_read_burst`](Sdram::override_read_burst).
     pub read_burst: bool,
     /// Delay in system clock cycles on read data path
     pub read_pipe_delay_cycles: u8,
 }
 
+impl SdramConfiguration {
+    /// Capacity of the SDRAM in bytes
+    pub const fn size_bytes(&self) -> usize {
+        (1 << (self.row_bits + self.column_bits))
+            * self.internal_banks as usize
+            * (self.memory_data_width as usize / 8)
+    }
+}
+
 /// FMC SDRAM Timing parameters structure definition
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -57,6 +99,91 @@ pub struct SdramTiming {
     pub row_precharge: u32,
     /// Delay between an ACTIVATE command and READ/WRITE command
     pub row_to_column: u32,
+    /// Number of auto-refresh commands issued during initialisation, 1 - 15.
+    /// Usually 8
+    pub auto_refresh_commands: u8,
+}
+
+/// FMC SDRAM Timing parameters, with all delays in nanoseconds
+///
+/// Unlike [`SdramTiming`](SdramTiming) this does not depend on the SD clock
+/// frequency. The delays are converted to SD clock cycles when the SDRAM is
+/// initialised, rounding up. Use with
+/// [`Sdram::set_timing_ns`](Sdram::set_timing_ns).
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SdramTimingNs {
+    /// Time between applying a valid clock and any command other than
+    /// COMMAND INHIBIT or NOP
+    pub startup_delay_ns: u32,
+    /// Maximum SD clock frequency to make timing
+    pub max_sd_clock_hz: u32,
+    /// Period between refresh cycles in nanoseconds
+    pub refresh_period_ns: u32,
+    /// Delay between a LOAD MODE register command and an ACTIVATE command
+    /// (tMRD)
+    pub mode_register_to_active_ns: u32,
+    /// Delay from releasing self refresh to next command (tXSR)
+    pub exit_self_refresh_ns: u32,
+    /// Delay between an ACTIVATE and a PRECHARGE command (tRAS)
+    pub active_to_precharge_ns: u32,
+    /// Auto refresh command duration (tRC)
+    pub row_cycle_ns: u32,
+    /// Delay between a PRECHARGE command and another command (tRP)
+    pub row_precharge_ns: u32,
+    /// Delay between an ACTIVATE command and READ/WRITE command (tRCD)
+    pub row_to_column_ns: u32,
+    /// Number of auto-refresh commands issued during initialisation, 1 - 15.
+    /// Usually 8
+    pub auto_refresh_commands: u8,
+}
+
+impl SdramTimingNs {
+    /// Convert to timing parameters in cycles of an SD clock of
+    /// `sd_clock_hz`. Each delay is rounded up to at least one cycle
+    pub fn cycles(&self, sd_clock_hz: u32) -> SdramTiming {
+        let cycles = |time_ns: u32| {
+            let n = (time_ns as u64 * sd_clock_hz as u64 + 999_999_999)
+                / 1_000_000_000;
+            cmp::max(n, 1) as u32
+        };
+
+        SdramTiming {
+            startup_delay_ns: self.startup_delay_ns,
+            max_sd_clock_hz: self.max_sd_clock_hz,
+            refresh_period_ns: self.refresh_period_ns,
+            mode_register_to_active: cycles(self.mode_register_to_active_ns),
+            exit_self_refresh: cycles(self.exit_self_refresh_ns),
+            active_to_precharge: cycles(self.active_to_precharge_ns),
+            row_cycle: cycles(self.row_cycle_ns),
+            row_precharge: cycles(self.row_precharge_ns),
+            row_to_column: cycles(self.row_to_column_ns),
+            auto_refresh_commands: self.auto_refresh_commands,
+        }
+    }
+}
+
+/// Per-board corrections to the SDRAM timing, applied at initialisation
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SdramCalibration {
+    /// Delay in system clock cycles on read data path, in addition to
+    /// `SdramConfiguration::read_pipe_delay_cycles`
+    pub extra_read_pipe_delay_cycles: u8,
+    /// Maximum SD clock frequency, if lower than
+    /// `SdramTiming::max_sd_clock_hz`
+    pub max_sd_clock_hz: Option<u32>,
+}
+
+/// A source of per-board SDRAM calibration data
+///
+/// Implement this to load timing corrections from user storage, for example
+/// option bytes or an EEPROM, so that products with marginal layouts can
+/// apply per-unit corrections without rebuilding firmware.
+pub trait CalibrationSource {
+    /// Load the calibration data. Returns `None` if no calibration is
+    /// available, in which case the nominal timing of the chip is used
+    fn calibration(&mut self) -> Option<SdramCalibration>;
 }
 
 /// Respresents a model of SDRAM chip
@@ -69,34 +196,148 @@ pub trait SdramChip {
 
     /// Timing parameters
     const TIMING: SdramTiming;
+
+    /// Capacity of the SDRAM in bytes
+    const SIZE_BYTES: usize = Self::CONFIG.size_bytes();
+}
+
+/// Check the constants of an [`SdramChip`] at compile time
+///
+/// The configuration, the CAS latency in the mode register, the delays in
+/// the timing parameters and the refresh period at `max_sd_clock_hz` are
+/// checked as in [`Sdram::try_init`](Sdram::try_init). Evaluate this in a
+/// constant item next to the chip definition, so that an impossible
+/// definition fails to compile:
+///
+/// ```
+/// # use stm32_fmc::devices::is42s32800g_6::Is42s32800g as MyChip;
+/// const _: () = stm32_fmc::check_sdram_chip::<MyChip>();
+/// ```
+///
+/// # Panics
+///
+/// * Panics if `init` would fail for any FMC source clock, which is a
+///   compile error in a constant item
+pub const fn check_sdram_chip<IC: SdramChip>() {
+    let result = match check_configuration(&IC::CONFIG) {
+        Ok(()) => match check_cas_latency(IC::MODE_REGISTER, &IC::CONFIG) {
+            Ok(()) => check_timing(&IC::TIMING),
+            e => e,
+        },
+        e => e,
+    };
+    let result = match result {
+        // If the refresh timer underflows at the fastest SD clock, it
+        // underflows at every SD clock
+        Ok(()) => match refresh_counter(
+            IC::TIMING.refresh_period_ns,
+            IC::TIMING.max_sd_clock_hz,
+            20,
+        ) {
+            Err(SdramInitError::RefreshCounterUnderflow) => {
+                Err(SdramInitError::RefreshCounterUnderflow)
+            }
+            _ => Ok(()),
+        },
+        e => e,
+    };
+
+    if let Err(e) = result {
+        panic!("{}", init_error_message(e));
+    }
+}
+
+/// Represents a model of mobile (low power) SDR SDRAM chip, which also has an
+/// extended mode register
+///
+/// See [`Sdram::init_mobile`](Sdram::init_mobile). Loading the extended mode
+/// register is only possible on the STM32H7, see
+/// [`ExtendedModeRegister`](ExtendedModeRegister).
+pub trait MobileSdramChip: SdramChip {
+    /// Value of the extended mode register, including the select bit. See
+    /// [`ExtendedModeRegister`](ExtendedModeRegister)
+    const EXTENDED_MODE_REGISTER: u16;
 }
 
 /// SDRAM Controller
-#[allow(missing_debug_implementations)]
-pub struct Sdram<FMC, IC> {
+///
+/// `IC` is the [`SdramChip`](SdramChip) definition, or `()` when the
+/// parameters are given at runtime with
+/// [`new_with_config`](Sdram::new_with_config). `PINS` is the pin set passed
+/// to [`new`](Sdram::new), which is returned by [`free`](Sdram::free).
+///
+/// `STATE` is [`Uninitialized`](Uninitialized) until one of the `init`
+/// methods is called, which consumes the instance and returns it in the
+/// [`Initialized`](Initialized) state. Accessors for the memory, refresh
+/// tuning and power management are only available once initialised.
+pub struct Sdram<FMC, IC, PINS = (), STATE = Uninitialized> {
     /// SDRAM bank
     target_bank: SdramTargetBank,
     /// FMC memory bank to use
     fmc_bank: FmcBank,
+    /// Value to load into the SDRAM's mode register
+    mode_register: u16,
+    /// SDRAM controller configuration
+    config: SdramConfiguration,
+    /// Timing parameters
+    timing: SdramTiming,
+    /// Timing parameters in nanoseconds, converted at initialisation
+    timing_ns: Option<SdramTimingNs>,
+    /// Refresh period set after initialisation, in place of the one in the
+    /// timing parameters
+    refresh_period_ns: Option<u32>,
+    /// Set by `suspend`, so that `resume` programs the refresh timer again
+    suspended: bool,
+    /// Cycles subtracted from the refresh timer count
+    refresh_margin: u32,
     /// Parameters for the SDRAM IC
     _chip: PhantomData<IC>,
+    /// Typestate
+    _state: PhantomData<STATE>,
     /// FMC peripheral
     fmc: FMC,
+    /// Pins
+    pins: PINS,
     /// Register access
     regs: FmcRegisters,
 }
 
+impl<FMC, IC, PINS, STATE> fmt::Debug for Sdram<FMC, IC, PINS, STATE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sdram")
+            .field("target_bank", &self.target_bank)
+            .field("fmc_bank", &self.fmc_bank)
+            .field("config", &self.config)
+            .field("timing", &self.timing)
+            .finish()
+    }
+}
+
+/// Typestate of an [`Sdram`](Sdram) that has not been initialised
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uninitialized;
+
+/// Typestate of an [`Sdram`](Sdram) that has been initialised
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Initialized;
+
 /// SDRAM Commands
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq)]
-#[allow(unused)]
-enum SdramCommand {
+pub enum SdramCommand {
+    /// Normal mode. Exits self-refresh and power-down modes
     NormalMode,
+    /// Start providing the SD clock to the SDRAM
     ClkEnable,
+    /// Precharge all internal banks
     Pall,
+    /// Issue a burst of 1 - 15 consecutive auto-refresh commands
     Autorefresh(u8),
+    /// Load the given value into the mode register
     LoadMode(u16),
+    /// Enter self-refresh mode
     Selfrefresh,
+    /// Enter power-down mode
     Powerdown,
 }
 /// Target bank for SDRAM commands
@@ -108,7 +349,8 @@ pub enum SdramTargetBank {
     Bank1,
     /// Targeting the 2nd SDRAM bank
     Bank2,
-    /// Targeting both SDRAM banks
+    /// Targeting both SDRAM banks, with identical chips on each. The chips
+    /// are configured identically, and commands are sent to both at once
     Both,
 }
 impl From<u32> for SdramTargetBank {
@@ -121,6 +363,156 @@ impl From<u32> for SdramTargetBank {
     }
 }
 
+/// Errors detected when initialising an SDRAM or changing its timing
+///
+/// Except for `SelfRefresh`, these are checked before any registers are
+/// written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SdramInitError {
+    /// The FMC source clock is too fast for the maximum SD clock. The maximum
+    /// division ratio is 3
+    ClockTooFast {
+        /// SD clock divider that would be needed at this FMC source clock
+        required_divider: u32,
+        /// Fastest FMC source clock that can be used, in Hz
+        max_source_clock_hz: u32,
+    },
+    /// The CAS latency is not 1 - 3 cycles
+    CasLatencyOutOfRange,
+    /// The CAS latency in the mode register differs from the CAS latency in
+    /// the configuration
+    CasLatencyMismatch,
+    /// The number of row address bits is not 11 - 13
+    RowBitsOutOfRange,
+    /// The number of column address bits is not 8 - 11
+    ColumnBitsOutOfRange,
+    /// The read pipe delay is not 0 - 2 cycles
+    ReadPipeDelayOutOfRange,
+    /// The number of internal banks is not 2 or 4
+    InternalBanksOutOfRange,
+    /// The memory data width is not 8, 16 or 32 bits
+    DataWidthOutOfRange,
+    /// The row cycle delay (tRC) is not 1 - 16 cycles
+    RowCycleOutOfRange,
+    /// The load mode register to active delay (tMRD) is not 1 - 16 cycles
+    ModeRegisterToActiveOutOfRange,
+    /// The exit self-refresh delay (tXSR) is not 1 - 16 cycles
+    ExitSelfRefreshOutOfRange,
+    /// The active to precharge delay (tRAS) is not 1 - 16 cycles
+    ActiveToPrechargeOutOfRange,
+    /// The row precharge delay (tRP) is not 1 - 16 cycles
+    RowPrechargeOutOfRange,
+    /// The row to column delay (tRCD) is not 1 - 16 cycles
+    RowToColumnOutOfRange,
+    /// The number of auto-refresh commands during initialisation is not
+    /// 1 - 15
+    AutoRefreshCommandsOutOfRange,
+    /// The active to precharge delay (tRAS) is shorter than the row to
+    /// column delay (tRCD), so the write recovery delay cannot be derived
+    ActiveToPrechargeBelowRowToColumn,
+    /// The row cycle delay (tRC) is shorter than the row to column delay
+    /// (tRCD) plus the row precharge delay (tRP), so the write recovery delay
+    /// cannot be derived
+    RowCycleBelowRowToColumnAndPrecharge,
+    /// The refresh period is too long for the refresh timer at this SD clock
+    RefreshCounterOverflow,
+    /// The refresh period is too short for the refresh timer at this SD clock
+    RefreshCounterUnderflow,
+    /// The SDRAM did not enter or leave self-refresh mode when the timing
+    /// was changed. Contains the mode reported by the FMC
+    SelfRefresh(SdramMode),
+}
+
+/// Status of the SDRAM controller, as reported by the FMC
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SdramStatus {
+    /// Mode of the SDRAM on bank 1
+    pub bank1: SdramMode,
+    /// Mode of the SDRAM on bank 2
+    pub bank2: SdramMode,
+    /// The SDRAM controller is busy, and cannot accept a new command
+    pub busy: bool,
+    /// A refresh request was not serviced in time
+    pub refresh_error: bool,
+}
+
+/// SD clock, timings and refresh timer programmed into the SDRAM controller
+///
+/// All delays are in SD clock cycles. See
+/// [`Sdram::timing_report`](Sdram::timing_report).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SdramTimingReport {
+    /// Division ratio from the FMC source clock to the SD clock
+    pub sd_clock_divider: u32,
+    /// SD clock in Hz
+    pub sd_clock_hz: u32,
+    /// Load mode register to active delay (tMRD)
+    pub mode_register_to_active: u32,
+    /// Exit self-refresh delay (tXSR)
+    pub exit_self_refresh: u32,
+    /// Self refresh time, at least the active to precharge delay (tRAS)
+    pub self_refresh: u32,
+    /// Row cycle delay (tRC)
+    pub row_cycle: u32,
+    /// Write recovery delay (tWR)
+    pub write_recovery: u32,
+    /// Row precharge delay (tRP)
+    pub row_precharge: u32,
+    /// Row to column delay (tRCD)
+    pub row_to_column: u32,
+    /// Refresh timer count
+    pub refresh_counter: u32,
+}
+
+/// Fields of the SDRAM controller registers that differ from the expected
+/// values
+///
+/// Each member is a mask of the register fields that differ, using the bit
+/// positions of the register. A mask of zero means that the register is as
+/// expected. When targeting both banks, the masks for the two banks are
+/// combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SdramRegisterDiff {
+    /// SDCRx: Control register
+    pub sdcr: u32,
+    /// SDTRx: Timing register
+    pub sdtr: u32,
+    /// SDRTR: Refresh timer register
+    pub sdrtr: u32,
+}
+
+impl SdramRegisterDiff {
+    /// Return `true` if no fields differ
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Status mode of an SDRAM bank, as reported by the FMC
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SdramMode {
+    /// Normal mode
+    Normal,
+    /// Self-refresh mode
+    SelfRefresh,
+    /// Power-down mode
+    PowerDown,
+}
+impl From<u32> for SdramMode {
+    fn from(modes: u32) -> Self {
+        match modes {
+            0b00 => SdramMode::Normal,
+            0b01 => SdramMode::SelfRefresh,
+            _ => SdramMode::PowerDown,
+        }
+    }
+}
+
 /// SDRAM target bank and corresponding FMC Bank
 pub trait SdramPinSet {
     /// External SDRAM bank
@@ -151,9 +543,37 @@ impl SdramPinSet for SdramBank2 {
 pub trait PinsSdram<Bank: SdramPinSet, Address: AddressPinSet> {
     /// The number of SDRAM banks addressable with this set of pins
     const NUMBER_INTERNAL_BANKS: u8;
+    /// The number of byte lanes (NBL pins) in this set of pins
+    const N_BYTE_LANES: u8;
+}
+
+/// Pins for an SDRAM, described at runtime
+///
+/// For use with HALs that have type-erased pins, where the pin constraints
+/// cannot be checked at compile time. See [`Sdram::new_dyn`](Sdram::new_dyn).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DynPinsSdram {
+    /// Number of address lines A0 - An
+    pub address_lines: u8,
+    /// Number of SDRAM internal banks addressable with the bank address lines
+    pub banks: u8,
+    /// Data bus width in bits
+    pub width: u8,
 }
 
-/// Like `modfiy_reg`, but applies to bank 1 or 2 based on a varaiable
+/// Fields of SDCR1 and SDCR2 that are specific to each bank
+const SDCR_BANK_FIELDS: u32 = fmc::SDCR1::WP::mask
+    | fmc::SDCR1::CAS::mask
+    | fmc::SDCR1::NB::mask
+    | fmc::SDCR1::MWID::mask
+    | fmc::SDCR1::NR::mask
+    | fmc::SDCR1::NC::mask;
+/// Reset value of SDCR1 and SDCR2
+const SDCR_RESET: u32 = 0x2D0;
+
+/// Like `modfiy_reg`, but applies to bank 1, bank 2 or both based on a
+/// varaiable
 macro_rules! modify_reg_banked {
     ( $periph:path, $instance:expr, $bank:expr, $reg1:ident, $reg2:ident, $( $field:ident : $value:expr ),+ ) => {{
         use SdramTargetBank::*;
@@ -161,25 +581,31 @@ macro_rules! modify_reg_banked {
         match $bank {
             Bank1 => modify_reg!( $periph, $instance, $reg1, $( $field : $value ),*),
             Bank2 => modify_reg!( $periph, $instance, $reg2, $( $field : $value ),*),
-            _ => panic!(),
+            Both => {
+                modify_reg!( $periph, $instance, $reg1, $( $field : $value ),*);
+                modify_reg!( $periph, $instance, $reg2, $( $field : $value ),*);
+            }
         }
     }};
 }
 
-impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
+impl<IC: SdramChip, FMC: FmcPeripheral, PINS> Sdram<FMC, IC, PINS> {
     /// New SDRAM instance
     ///
-    /// `_pins` must be a set of pins connecting to an SDRAM on the FMC
+    /// `pins` must be a set of pins connecting to an SDRAM on the FMC
     /// controller
     ///
     /// # Panics
     ///
     /// * Panics if there are not enough address lines in `PINS` to access the
-    /// whole SDRAM
+    ///   whole SDRAM
     ///
     /// * Panics if there are not enough bank address lines in `PINS` to access
-    /// the whole SDRAM
-    pub fn new<PINS, BANK, ADDR>(fmc: FMC, _pins: PINS, _chip: IC) -> Self
+    ///   the whole SDRAM
+    ///
+    /// * Panics if there are not enough byte lane pins in `PINS` for the
+    ///   SDRAM data width
+    pub fn new<BANK, ADDR>(fmc: FMC, pins: PINS, _chip: IC) -> Self
     where
         PINS: PinsSdram<BANK, ADDR>,
         ADDR: AddressPinSet,
@@ -197,22 +623,71 @@ impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
             PINS::NUMBER_INTERNAL_BANKS >= IC::CONFIG.internal_banks,
             "Not enough bank address pins to access all internal banks"
         );
+        assert!(
+            PINS::N_BYTE_LANES * 8 >= IC::CONFIG.memory_data_width,
+            "Not enough byte lane pins to access the SDRAM"
+        );
 
         fmc_trace!("Bank selected via pins: {}.", BANK::TARGET);
 
-        Sdram {
-            target_bank: BANK::TARGET,
-            fmc_bank: BANK::FMC,
-            _chip: PhantomData,
+        Self::from_parameters(
             fmc,
-            regs: FmcRegisters::new::<FMC>(),
-        }
+            pins,
+            BANK::TARGET,
+            IC::MODE_REGISTER,
+            IC::CONFIG,
+            IC::TIMING,
+        )
     }
+}
 
-    /// New SDRAM instance
+impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
+    /// New SDRAM instance, with pins described at runtime
     ///
     /// `bank` denotes which SDRAM bank to target. This can be either bank 1 or
-    /// bank 2.
+    /// bank 2. `pins` describes the pins connecting to an SDRAM on the FMC
+    /// controller. The same checks as [`new`](Self::new) are performed, but at
+    /// runtime.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if there are not enough address lines in `pins` to access the
+    ///   whole SDRAM
+    ///
+    /// * Panics if there are not enough bank address lines in `pins` to access
+    ///   the whole SDRAM
+    ///
+    /// * Panics if the data bus in `pins` is narrower than the SDRAM
+    pub fn new_dyn(
+        fmc: FMC,
+        bank: impl Into<SdramTargetBank>,
+        pins: DynPinsSdram,
+        chip: IC,
+    ) -> Self {
+        assert!(
+            pins.address_lines >= IC::CONFIG.row_bits,
+            "Not enough address pins to access all SDRAM rows"
+        );
+        assert!(
+            pins.address_lines >= IC::CONFIG.column_bits,
+            "Not enough address pins to access all SDRAM colums"
+        );
+        assert!(
+            pins.banks >= IC::CONFIG.internal_banks,
+            "Not enough bank address pins to access all internal banks"
+        );
+        assert!(
+            pins.width >= IC::CONFIG.memory_data_width,
+            "Not enough data pins to access the SDRAM"
+        );
+
+        Self::new_unchecked(fmc, bank, chip)
+    }
+
+    /// New SDRAM instance
+    ///
+    /// `bank` denotes which SDRAM bank to target. This can be bank 1, bank 2
+    /// or [`Both`](SdramTargetBank::Both) for identical chips on each bank.
     ///
     /// # Safety
     ///
@@ -224,116 +699,600 @@ impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
         bank: impl Into<SdramTargetBank>,
         _chip: IC,
     ) -> Self {
-        // Select default bank mapping
-        let target_bank = bank.into();
+        Self::from_parameters(
+            fmc,
+            (),
+            bank.into(),
+            IC::MODE_REGISTER,
+            IC::CONFIG,
+            IC::TIMING,
+        )
+    }
+}
+
+impl<IC: MobileSdramChip, FMC: FmcPeripheral, PINS> Sdram<FMC, IC, PINS> {
+    /// Initialise a mobile SDRAM instance. Delay is used to wait the SDRAM
+    /// powerup delay
+    ///
+    /// After the initialisation sequence of [`init`](Sdram::init), the
+    /// extended mode register is loaded with
+    /// `IC::EXTENDED_MODE_REGISTER`.
+    ///
+    /// STM32H7 only. On other parts, use [`init`](Sdram::init) and leave the
+    /// extended mode register at its power on value.
+    ///
+    /// # Panics
+    ///
+    /// * Panics under the same conditions as [`init`](Sdram::init)
+    pub fn init_mobile<D>(
+        self,
+        delay: &mut D,
+    ) -> Sdram<FMC, IC, PINS, Initialized>
+    where
+        D: DelayNs,
+    {
+        let mut sdram = self.init(delay);
+        sdram.load_extended_mode_register(IC::EXTENDED_MODE_REGISTER);
+
+        sdram
+    }
+}
+
+impl<FMC: FmcPeripheral> Sdram<FMC, ()> {
+    /// New SDRAM instance, with parameters given at runtime
+    ///
+    /// This can be used in place of an [`SdramChip`](SdramChip)
+    /// implementation, for example when the SDRAM is selected at runtime or
+    /// its parameters are loaded from option bytes or an EEPROM. `bank`
+    /// denotes which SDRAM bank to target. This can be bank 1, bank 2 or
+    /// both.
+    ///
+    /// # Safety
+    ///
+    /// The pins are not checked against the requirements for the SDRAM chip. So
+    /// you may be able to initialise a SDRAM without enough pins to access the
+    /// whole memory
+    pub fn new_with_config(
+        fmc: FMC,
+        bank: impl Into<SdramTargetBank>,
+        mode_register: u16,
+        config: SdramConfiguration,
+        timing: SdramTiming,
+    ) -> Self {
+        Self::from_parameters(
+            fmc,
+            (),
+            bank.into(),
+            mode_register,
+            config,
+            timing,
+        )
+    }
+}
+
+impl<IC, FMC: FmcPeripheral, PINS> Sdram<FMC, IC, PINS, Uninitialized> {
+    /// New SDRAM instance on `target_bank` with the given parameters
+    fn from_parameters(
+        fmc: FMC,
+        pins: PINS,
+        target_bank: SdramTargetBank,
+        mode_register: u16,
+        config: SdramConfiguration,
+        timing: SdramTiming,
+    ) -> Self {
+        // Select default bank mapping. When both banks are used, the second
+        // chip is at FmcBank::Bank6
         let fmc_bank = match target_bank {
-            SdramTargetBank::Bank1 => FmcBank::Bank5,
+            SdramTargetBank::Bank1 | SdramTargetBank::Both => FmcBank::Bank5,
             SdramTargetBank::Bank2 => FmcBank::Bank6,
-            _ => unimplemented!(),
         };
 
         Sdram {
             target_bank,
             fmc_bank,
+            mode_register,
+            config,
+            timing,
+            timing_ns: None,
+            refresh_period_ns: None,
+            suspended: false,
+            refresh_margin: 20,
             _chip: PhantomData,
+            _state: PhantomData,
             fmc,
+            pins,
             regs: FmcRegisters::new::<FMC>(),
         }
     }
 
+    /// Swap the SDRAM and NOR/PSRAM regions in the memory map (STM32H7 only)
+    ///
+    /// When `remap` is true, `init` programs the BMAP field so that SDRAM
+    /// bank 1 is accessed at [`FmcBank::Bank1`](crate::FmcBank::Bank1)
+    /// (0x6000_0000) and SDRAM bank 2 at
+    /// [`FmcBank::Bank2`](crate::FmcBank::Bank2) (0x7000_0000). In the
+    /// default Cortex-M memory map this region has Normal memory attributes,
+    /// so it can be cached and used for unaligned accesses without
+    /// configuring the MPU. Any NOR/PSRAM/SRAM on Bank 1 moves to
+    /// 0xC000_0000.
+    ///
+    /// Must be called before `init`. The BMAP field is reserved on other
+    /// parts, so this must not be used there.
+    pub fn set_bank_remap(&mut self, remap: bool) {
+        self.fmc_bank = match (self.target_bank, remap) {
+            (SdramTargetBank::Bank2, false) => FmcBank::Bank6,
+            (SdramTargetBank::Bank2, true) => FmcBank::Bank2,
+            (_, false) => FmcBank::Bank5,
+            (_, true) => FmcBank::Bank1,
+        };
+    }
+
+    /// Use timing parameters given in nanoseconds
+    ///
+    /// This replaces the timing from the `SdramChip` or `new_with_config`. The
+    /// delays are converted to cycles of the SD clock that is selected by
+    /// `init`, so the same parameters can be used whatever the FMC source
+    /// clock. Must be called before `init`.
+    pub fn set_timing_ns(&mut self, timing: SdramTimingNs) {
+        // Until the SD clock is known, assume the fastest SD clock
+        self.timing = timing.cycles(timing.max_sd_clock_hz);
+        self.timing_ns = Some(timing);
+    }
+
+    /// Override the read burst setting of the configuration
+    ///
+    /// Most `SdramChip` definitions enable read bursts. Some STM32F4 parts
+    /// have errata affecting SDRAM read bursts, so on those parts call this
+    /// with `false` as recommended by the errata sheet. The setting is common
+    /// to both banks, and read bursts stay disabled if either SDRAM disables
+    /// them. Must be called before `init`.
+    pub fn override_read_burst(&mut self, read_burst: bool) {
+        self.config.read_burst = read_burst;
+    }
+
     /// Initialise SDRAM instance. Delay is used to wait the SDRAM powerup
     /// delay
     ///
-    /// Returns a raw pointer to the memory-mapped SDRAM block
+    /// Returns the initialised instance. The memory-mapped SDRAM block is
+    /// then available from [`as_ptr`](Sdram::as_ptr).
     ///
     /// # Panics
     ///
-    /// * Panics if any setting in `IC::CONFIG` cannot be achieved
+    /// * Panics if any setting in the configuration cannot be achieved
     ///
     /// * Panics if the FMC source clock is too fast for
-    /// maximum SD clock in `IC::TIMING`
-    pub fn init<D>(&mut self, delay: &mut D) -> *mut u32
+    ///   maximum SD clock in the timing parameters
+    ///
+    /// See [`try_init`](Sdram::try_init) for a version that returns an error
+    /// instead.
+    pub fn init<D>(self, delay: &mut D) -> Sdram<FMC, IC, PINS, Initialized>
     where
         D: DelayNs,
     {
-        use SdramCommand::*;
-
-        // Select bank
-        let bank = self.target_bank;
+        expect_init(self.try_init(delay))
+    }
 
-        // Calcuate SD clock
-        let (sd_clock_hz, divide) = {
-            let fmc_source_ck_hz = self.fmc.source_clock_hz();
-            let sd_clock_wanted = IC::TIMING.max_sd_clock_hz;
+    /// Initialise SDRAM instance. Delay is used to wait the SDRAM powerup
+    /// delay
+    ///
+    /// Returns the initialised instance, or an error if the configuration or
+    /// timing cannot be achieved. In that case the FMC is not modified, and
+    /// the uninitialised instance is returned with the error.
+    #[allow(clippy::result_large_err)]
+    pub fn try_init<D>(
+        mut self,
+        delay: &mut D,
+    ) -> Result<Sdram<FMC, IC, PINS, Initialized>, (Self, SdramInitError)>
+    where
+        D: DelayNs,
+    {
+        let result = self.init_with(delay, self.config, self.timing);
+        self.initialized(result)
+    }
 
-            // Divider, round up. At least 2
-            let divide: u32 = cmp::max(
-                (fmc_source_ck_hz + sd_clock_wanted - 1) / sd_clock_wanted,
-                2,
-            );
+    /// Initialise SDRAM instance. The SDRAM powerup delay is awaited on
+    /// `delay`
+    ///
+    /// Returns the initialised instance.
+    ///
+    /// # Panics
+    ///
+    /// * Panics under the same conditions as [`init`](Sdram::init)
+    #[cfg(feature = "async")]
+    pub async fn init_async<D>(
+        self,
+        delay: &mut D,
+    ) -> Sdram<FMC, IC, PINS, Initialized>
+    where
+        D: embedded_hal_async::delay::DelayNs,
+    {
+        expect_init(self.try_init_async(delay).await)
+    }
 
-            // Max 3
-            assert!(divide <= 3,
-                    "Source clock too fast for required SD_CLOCK. The maximum division ratio is 3");
+    /// Initialise SDRAM instance. The SDRAM powerup delay is awaited on
+    /// `delay`
+    ///
+    /// Returns the initialised instance, or an error under the same
+    /// conditions as [`try_init`](Sdram::try_init).
+    #[cfg(feature = "async")]
+    #[allow(clippy::result_large_err)]
+    pub async fn try_init_async<D>(
+        mut self,
+        delay: &mut D,
+    ) -> Result<Sdram<FMC, IC, PINS, Initialized>, (Self, SdramInitError)>
+    where
+        D: embedded_hal_async::delay::DelayNs,
+    {
+        let (startup_delay_us, auto_refresh_commands, refresh_counter_top) =
+            match self.init_start(self.config, self.timing) {
+                Ok(steps) => steps,
+                Err(e) => return Err((self, e)),
+            };
 
-            let sd_clock_hz = fmc_source_ck_hz / divide;
-            (sd_clock_hz, divide)
-        };
+        // Step 2: SDRAM powerup delay
+        delay.delay_us(startup_delay_us).await;
 
-        fmc_trace!(
-            "FMC clock {:?} (/{}, Max {:?})",
-            sd_clock_hz,
-            divide,
-            IC::TIMING.max_sd_clock_hz
-        );
+        self.init_finish(auto_refresh_commands, refresh_counter_top);
+        Ok(self.into_state())
+    }
 
-        unsafe {
-            // Enable memory controller AHB register access
-            self.fmc.enable();
+    /// Initialise SDRAM instance, preserving its contents if the SDRAM
+    /// controller is already running. Delay is used to wait the SDRAM powerup
+    /// delay
+    ///
+    /// After a soft reset the FMC keeps its configuration and continues to
+    /// refresh the SDRAM. If [`is_configured`](Sdram::is_configured) returns
+    /// true, the power-up sequence (precharge all, auto-refresh and load
+    /// mode register) is skipped. The SDRAM is returned to normal mode if it
+    /// was in self-refresh, and the refresh timer is reprogrammed if it is
+    /// too slow for the timing parameters. Otherwise the SDRAM is initialised
+    /// as by [`try_init`](Sdram::try_init), and its contents are lost.
+    ///
+    /// Returns the initialised instance, or an error if the configuration or
+    /// timing cannot be achieved.
+    #[allow(clippy::result_large_err)]
+    pub fn try_init_warm<D>(
+        mut self,
+        delay: &mut D,
+    ) -> Result<Sdram<FMC, IC, PINS, Initialized>, (Self, SdramInitError)>
+    where
+        D: DelayNs,
+    {
+        if !self.is_configured() {
+            return self.try_init(delay);
+        }
+        fmc_trace!("SDRAM controller already configured");
 
-            // Program device features and timing
-            self.set_features_timings(IC::CONFIG, IC::TIMING, divide);
+        if self.check_mode(SdramMode::SelfRefresh).is_ok() {
+            unsafe {
+                self.send_command(SdramCommand::NormalMode, self.target_bank);
+            }
+        }
 
-            // Enable memory controller
-            self.fmc.memory_controller_enable();
+        if let Err(diff) = self.verify_configuration() {
+            if diff.sdrtr != 0 {
+                let sd_clock_hz = self.sd_clock_hz().unwrap_or(0);
+                match refresh_counter(
+                    self.refresh_period_ns(),
+                    sd_clock_hz,
+                    self.refresh_margin,
+                ) {
+                    Ok(top) => self.set_refresh_counter(top),
+                    Err(e) => return Err((self, e)),
+                }
+            }
+        }
 
-            // Step 1: Send a clock configuration enable command
-            self.send_command(ClkEnable, bank);
+        Ok(self.into_state())
+    }
 
-            // Step 2: SDRAM powerup delay
-            let startup_delay_us = (IC::TIMING.startup_delay_ns + 999) / 1000;
-            fmc_trace!("Startup delay: {} us", startup_delay_us);
+    /// Initialise SDRAM instance, applying per-board calibration data loaded
+    /// from `source`. Delay is used to wait the SDRAM powerup delay
+    ///
+    /// Returns the initialised instance. The calibrated configuration and
+    /// timing are kept, and used if the SDRAM controller is programmed again.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if any setting in the configuration cannot be achieved,
+    ///   including after the calibration is applied
+    ///
+    /// * Panics if the FMC source clock is too fast for
+    ///   maximum SD clock in the timing parameters or the calibration
+    ///
+    /// See [`try_init_calibrated`](Sdram::try_init_calibrated) for a version
+    /// that returns an error instead.
+    pub fn init_calibrated<D, S>(
+        self,
+        delay: &mut D,
+        source: &mut S,
+    ) -> Sdram<FMC, IC, PINS, Initialized>
+    where
+        D: DelayNs,
+        S: CalibrationSource,
+    {
+        expect_init(self.try_init_calibrated(delay, source))
+    }
 
-            delay.delay_us(startup_delay_us.try_into().unwrap());
+    /// Initialise SDRAM instance, applying per-board calibration data loaded
+    /// from `source`. Delay is used to wait the SDRAM powerup delay
+    ///
+    /// Returns the initialised instance, or an error if the configuration or
+    /// timing cannot be achieved after the calibration is applied. In that
+    /// case the FMC is not modified, and the uninitialised instance is
+    /// returned with the error.
+    #[allow(clippy::result_large_err)]
+    pub fn try_init_calibrated<D, S>(
+        mut self,
+        delay: &mut D,
+        source: &mut S,
+    ) -> Result<Sdram<FMC, IC, PINS, Initialized>, (Self, SdramInitError)>
+    where
+        D: DelayNs,
+        S: CalibrationSource,
+    {
+        let mut config = self.config;
+        let mut timing = self.timing;
 
-            // Step 3: Send a PALL (precharge all) command
-            self.send_command(Pall, bank);
+        if let Some(calibration) = source.calibration() {
+            fmc_trace!("Calibration: {:?}", calibration);
 
-            // Step 4: Send eight auto refresh commands
-            self.send_command(Autorefresh(8), bank);
+            config.read_pipe_delay_cycles = match config
+                .read_pipe_delay_cycles
+                .checked_add(calibration.extra_read_pipe_delay_cycles)
+            {
+                Some(cycles) => cycles,
+                None => {
+                    return Err((self, SdramInitError::ReadPipeDelayOutOfRange))
+                }
+            };
+            if let Some(max_sd_clock_hz) = calibration.max_sd_clock_hz {
+                timing.max_sd_clock_hz =
+                    cmp::min(timing.max_sd_clock_hz, max_sd_clock_hz);
+            }
+        }
 
-            // Step 5: Program the SDRAM's mode register
-            self.send_command(LoadMode(IC::MODE_REGISTER), bank);
+        let result = self.init_with(delay, config, timing);
+        if result.is_ok() {
+            self.config = config;
+            self.timing = timing;
+        }
+        self.initialized(result)
+    }
 
-            // Step 6: Set the refresh rate counter
-            // period (ns) * frequency (hz) / 10^9 = count
-            let refresh_counter_top = ((IC::TIMING.refresh_period_ns as u64
-                * sd_clock_hz as u64)
-                / 1_000_000_000)
-                - 20;
-            assert!(
-                refresh_counter_top >= 41 && refresh_counter_top < (1 << 13),
-                "Impossible configuration for H7 FMC Controller"
-            );
+    /// Initialise the SDRAM, and detect its row, column and internal bank
+    /// address bits. Delay is used to wait the SDRAM powerup delay
+    ///
+    /// The SDRAM is first initialised with the largest geometry supported by
+    /// the FMC. A pattern is then written to the first location, and to
+    /// locations that differ from it by one address bit. If the pattern at
+    /// the first location is overwritten, that address bit is not decoded by
+    /// the SDRAM. The memory data width, CAS latency and other settings are
+    /// taken from the configuration of this instance, and the timing must be
+    /// valid for the SDRAM.
+    ///
+    /// On success the SDRAM is left initialised with the discovered
+    /// configuration. Returns the configuration, and the initialised
+    /// instance. The contents of the SDRAM are destroyed.
+    ///
+    /// This is intended for board bring-up. A faulty data or address bus
+    /// can cause a wrong result, so check the memory with a memory test
+    /// afterwards.
+    #[allow(clippy::result_large_err, clippy::type_complexity)]
+    pub fn probe_geometry<D>(
+        mut self,
+        delay: &mut D,
+    ) -> Result<
+        (SdramConfiguration, Sdram<FMC, IC, PINS, Initialized>),
+        (Self, SdramInitError),
+    >
+    where
+        D: DelayNs,
+    {
+        // Largest geometry: 11 column bits, 4 internal banks, 13 row bits
+        let mut config = SdramConfiguration {
+            column_bits: 11,
+            row_bits: 13,
+            internal_banks: 4,
+            ..self.config
+        };
+        let base = match self.init_with(delay, config, self.timing) {
+            Ok(base) => base,
+            Err(e) => return Err((self, e)),
+        };
 
-            fmc_trace!("SDRTR: count {}", refresh_counter_top);
+        // Locations are addressed as [row][bank][column]
+        let bytes = config.memory_data_width as usize / 8;
+        let aliases = |bit: u8| unsafe {
+            let other = (base as usize + (bytes << bit)) as *mut u32;
+            ptr::write_volatile(base, 0x5555_AAAA);
+            ptr::write_volatile(other, 0xAAAA_5555);
+            ptr::read_volatile(base) != 0x5555_AAAA
+        };
 
-            modify_reg!(
-                fmc,
-                self.regs.global(),
-                SDRTR,
-                COUNT: refresh_counter_top as u32
-            );
+        config.column_bits = (8..11).find(|&c| aliases(c)).unwrap_or(11);
+        config.internal_banks = if aliases(11 + 1) { 2 } else { 4 };
+        config.row_bits = (11..13).find(|&r| aliases(13 + r)).unwrap_or(13);
+        fmc_trace!("Probed SDRAM geometry: {:?}", config);
+
+        // Reprogram the address decoding
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, self.regs.global(),
+                           self.target_bank, SDCR1, SDCR2,
+                           NB: (config.internal_banks == 4) as u32,
+                           NR: config.row_bits as u32 - 11,
+                           NC: config.column_bits as u32 - 8);
+        self.config = config;
+
+        Ok((config, self.into_state()))
+    }
+
+    /// Move to the initialised state if `result` is `Ok`, otherwise return
+    /// this instance with the error
+    #[allow(clippy::result_large_err, clippy::type_complexity)]
+    fn initialized<T>(
+        self,
+        result: Result<T, SdramInitError>,
+    ) -> Result<Sdram<FMC, IC, PINS, Initialized>, (Self, SdramInitError)> {
+        match result {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    /// Initialise SDRAM instance with the given configuration and timing
+    fn init_with<D>(
+        &mut self,
+        delay: &mut D,
+        config: SdramConfiguration,
+        timing: SdramTiming,
+    ) -> Result<*mut u32, SdramInitError>
+    where
+        D: DelayNs,
+    {
+        let (startup_delay_us, auto_refresh_commands, refresh_counter_top) =
+            self.init_start(config, timing)?;
+
+        // Step 2: SDRAM powerup delay
+        delay.delay_us(startup_delay_us);
+
+        self.init_finish(auto_refresh_commands, refresh_counter_top);
+
+        // Memory now initialised. Return base address
+        Ok(self.fmc_bank.ptr())
+    }
+
+    /// Program the SDRAM controller and start the SD clock, the first part of
+    /// the initialisation sequence
+    ///
+    /// Returns the SDRAM powerup delay in microseconds, then the number of
+    /// auto-refresh commands and the refresh timer count for `init_finish`.
+    fn init_start(
+        &mut self,
+        config: SdramConfiguration,
+        timing: SdramTiming,
+    ) -> Result<(u32, u8, u32), SdramInitError> {
+        use SdramCommand::*;
+
+        // Select bank
+        let bank = self.target_bank;
+
+        // The SD clock, read pipe, tRC, tRP and refresh timer are common to
+        // both banks. If the controller is already running for the other
+        // bank, the common settings must satisfy both SDRAMs. SDCLK is zero
+        // after reset, or if the FMC is not clocked
+        let running_divide = read_reg!(fmc, self.regs.global(), SDCR1, SDCLK);
+        let running = running_divide != 0;
+
+        // Calcuate SD clock
+        let (sd_clock_hz, divide) = {
+            let fmc_source_ck_hz = self.fmc.source_clock_hz();
+            let divide =
+                sd_clock_divide(fmc_source_ck_hz, timing.max_sd_clock_hz)?;
+
+            // Slow enough for both SDRAMs
+            let divide = cmp::max(divide, running_divide);
+
+            let sd_clock_hz = fmc_source_ck_hz / divide;
+            (sd_clock_hz, divide)
+        };
+
+        // Convert nanosecond timings at the actual SD clock
+        let timing = match self.timing_ns {
+            Some(timing_ns) => SdramTiming {
+                max_sd_clock_hz: timing.max_sd_clock_hz,
+                ..timing_ns.cycles(sd_clock_hz)
+            },
+            None => timing,
+        };
+
+        // Check everything before touching the FMC
+        check_configuration(&config)?;
+        check_cas_latency(self.mode_register, &config)?;
+        check_timing(&timing)?;
+        let mut refresh_counter_top = refresh_counter(
+            timing.refresh_period_ns,
+            sd_clock_hz,
+            self.refresh_margin,
+        )?;
+        if running {
+            // Keep the refresh period of the other bank, at the new SD clock
+            let running_top = read_reg!(fmc, self.regs.global(), SDRTR, COUNT);
+            let margin = self.refresh_margin;
+            let running_top = ((running_top + margin) * running_divide
+                / divide)
+                .saturating_sub(margin);
+            refresh_counter_top = cmp::min(refresh_counter_top, running_top);
+        }
+
+        fmc_trace!(
+            "FMC clock {:?} (/{}, Max {:?})",
+            sd_clock_hz,
+            divide,
+            timing.max_sd_clock_hz
+        );
+
+        unsafe {
+            // Enable memory controller AHB register access. Skipped if
+            // another memory controller is running, as this may reset the FMC
+            if !self.regs.in_use() {
+                self.fmc.enable();
+            }
+
+            // Program device features and timing
+            self.set_features_timings(config, timing, divide, running);
+
+            // Swap the SDRAM into the NOR/PSRAM region
+            if let FmcBank::Bank1 | FmcBank::Bank2 = self.fmc_bank {
+                modify_reg!(fmc, self.regs.global(), BCR1, BMAP: Swapped);
+            }
+
+            // Enable memory controller
+            self.fmc.memory_controller_enable();
+
+            // Step 0: An SDRAM released by `free` is in self-refresh mode
+            if running && self.check_mode(SdramMode::SelfRefresh).is_ok() {
+                self.send_command(NormalMode, bank);
+            }
+
+            // Step 1: Send a clock configuration enable command
+            self.send_command(ClkEnable, bank);
+        }
+
+        let startup_delay_us = (timing.startup_delay_ns + 999) / 1000;
+        fmc_trace!("Startup delay: {} us", startup_delay_us);
+
+        Ok((
+            startup_delay_us,
+            timing.auto_refresh_commands,
+            refresh_counter_top,
+        ))
+    }
+
+    /// Initialise the SDRAM after the powerup delay, the second part of the
+    /// initialisation sequence
+    fn init_finish(
+        &mut self,
+        auto_refresh_commands: u8,
+        refresh_counter_top: u32,
+    ) {
+        use SdramCommand::*;
+
+        // Select bank
+        let bank = self.target_bank;
+
+        unsafe {
+            // Step 3: Send a PALL (precharge all) command
+            self.send_command(Pall, bank);
+
+            // Step 4: Send auto refresh commands
+            self.send_auto_refresh(auto_refresh_commands);
+
+            // Step 5: Program the SDRAM's mode register
+            self.send_command(LoadMode(self.mode_register), bank);
+
+            // Step 6: Set the refresh rate counter
+            self.set_refresh_counter(refresh_counter_top);
         }
 
         #[cfg(feature = "trace-register-values")]
@@ -396,56 +1355,391 @@ impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
                 read_reg!(fmc, self.regs.global(), SDRTR)
             );
         }
+    }
 
-        // Memory now initialised. Return base address
-        self.fmc_bank.ptr()
+    /// Release the FMC peripheral and pins
+    ///
+    /// No command is sent to the SDRAM, and the SDRAM controller is not
+    /// changed.
+    pub fn free(self) -> (FMC, PINS) {
+        (self.fmc, self.pins)
     }
+}
 
-    /// Program memory device features and timings
+impl<IC, FMC: FmcPeripheral, PINS, STATE> Sdram<FMC, IC, PINS, STATE> {
+    /// Set the safety margin for the refresh timer, in SD clock cycles
     ///
-    /// # Safety
+    /// The refresh timer count is the refresh period less this margin, so
+    /// that a refresh request that is delayed by an ongoing access is still
+    /// serviced in time. The default is 20 cycles, as recommended by the
+    /// reference manual. A larger margin may be needed if other bus masters
+    /// make long bursts of accesses, and a smaller one reduces the time
+    /// spent refreshing.
+    ///
+    /// Used by `init` and by later changes to the refresh period. The
+    /// refresh timer count must still be at least 41 after the margin is
+    /// subtracted, otherwise initialisation fails with
+    /// [`RefreshCounterUnderflow`](SdramInitError::RefreshCounterUnderflow).
+    pub fn set_refresh_margin(&mut self, cycles: u32) {
+        self.refresh_margin = cycles;
+    }
+
+    /// Return `true` if the SDRAM controller is running with the
+    /// configuration and timing of this instance
     ///
-    /// Some settings are common between both banks. Calling this function
-    /// mutliple times with different banks and different configurations is
-    /// unsafe.
+    /// The SD clock and refresh timer must be running, and the registers for
+    /// the target bank must match the expected values. See
+    /// [`verify_configuration`](Sdram::verify_configuration).
+    pub fn is_configured(&self) -> bool {
+        let refreshing = read_reg!(fmc, self.regs.global(), SDRTR, COUNT) != 0;
+        let matches = match self.verify_configuration() {
+            Ok(()) => true,
+            Err(diff) => diff.sdcr == 0 && diff.sdtr == 0,
+        };
+
+        self.sd_clock_divider().is_some() && refreshing && matches
+    }
+
+    /// Change the typestate
+    fn into_state<S>(self) -> Sdram<FMC, IC, PINS, S> {
+        Sdram {
+            target_bank: self.target_bank,
+            fmc_bank: self.fmc_bank,
+            mode_register: self.mode_register,
+            config: self.config,
+            timing: self.timing,
+            timing_ns: self.timing_ns,
+            refresh_period_ns: self.refresh_period_ns,
+            suspended: self.suspended,
+            refresh_margin: self.refresh_margin,
+            _chip: PhantomData,
+            _state: PhantomData,
+            fmc: self.fmc,
+            pins: self.pins,
+            regs: self.regs,
+        }
+    }
+
+    /// The SD clock divider programmed by `init`, from the FMC source clock.
+    /// This is 2 or 3, or `None` if the SDRAM controller has not been
+    /// initialised
+    pub fn sd_clock_divider(&self) -> Option<u32> {
+        match read_reg!(fmc, self.regs.global(), SDCR1, SDCLK) {
+            0 => None,
+            divide => Some(divide),
+        }
+    }
+
+    /// Frequency of the SD clock programmed by `init` in Hz, or `None` if the
+    /// SDRAM controller has not been initialised
     ///
-    /// For example, see RM0433 rev 7 Section 22.9.3
-    unsafe fn set_features_timings(
-        &mut self,
-        config: SdramConfiguration,
-        timing: SdramTiming,
-        sd_clock_divide: u32,
-    ) {
-        // Features ---- SDCR REGISTER
+    /// This is calculated from the current FMC source clock.
+    pub fn sd_clock_hz(&self) -> Option<u32> {
+        self.sd_clock_divider()
+            .map(|divide| self.fmc.source_clock_hz() / divide)
+    }
 
-        // CAS latency 1 ~ 3 cycles
-        assert!(
-            config.cas_latency >= 1 && config.cas_latency <= 3,
-            "Impossible configuration for FMC Controller"
-        );
+    /// Read back the SDRAM controller registers, and compare them with the
+    /// values expected from the configuration and timing of this instance
+    ///
+    /// This can detect registers that have been overwritten by other code,
+    /// or writes that had no effect, for example because the FMC was not
+    /// clocked. The settings that are common to both banks may have been made
+    /// more conservative for the SDRAM on the other bank, so these are only
+    /// reported if they are not sufficient for this SDRAM.
+    ///
+    /// Returns the fields that differ.
+    pub fn verify_configuration(&self) -> Result<(), SdramRegisterDiff> {
+        use fmc::{SDCR1 as SDCR, SDRTR, SDTR1 as SDTR};
 
-        // Row Bits: 11 ~ 13
-        assert!(
-            config.row_bits >= 11 && config.row_bits <= 13,
-            "Impossible configuration for FMC Controller"
+        let config = self.config;
+        let fmc_source_ck_hz = self.fmc.source_clock_hz();
+        let mut diff = SdramRegisterDiff::default();
+
+        // Common settings in SDCR1
+        let (divide, rpipe, rburst) =
+            read_reg!(fmc, self.regs.global(), SDCR1, SDCLK, RPIPE, RBURST);
+        let divide_wanted =
+            sd_clock_divide(fmc_source_ck_hz, self.timing.max_sd_clock_hz);
+        if divide_wanted.map_or(true, |wanted| divide < wanted) {
+            diff.sdcr |= SDCR::SDCLK::mask;
+        }
+        if rpipe < config.read_pipe_delay_cycles as u32 {
+            diff.sdcr |= SDCR::RPIPE::mask;
+        }
+        if rburst > config.read_burst as u32 {
+            diff.sdcr |= SDCR::RBURST::mask;
+        }
+
+        // Timing at the programmed SD clock
+        let sd_clock_hz = fmc_source_ck_hz / cmp::max(divide, 1);
+        let timing = self.timing_at(sd_clock_hz);
+
+        // Common settings in SDTR1
+        let (trc, trp) = read_reg!(fmc, self.regs.global(), SDTR1, TRC, TRP);
+        if trc + 1 < timing.row_cycle {
+            diff.sdtr |= SDTR::TRC::mask;
+        }
+        if trp + 1 < timing.row_precharge {
+            diff.sdtr |= SDTR::TRP::mask;
+        }
+
+        // Refresh timer
+        if let Ok(top) = refresh_counter(
+            self.refresh_period_ns(),
+            sd_clock_hz,
+            self.refresh_margin,
+        ) {
+            if read_reg!(fmc, self.regs.global(), SDRTR, COUNT) > top {
+                diff.sdrtr |= SDRTR::COUNT::mask;
+            }
+        }
+
+        // Settings for each bank, as (value, offset, mask)
+        let expected_sdcr = [
+            (
+                config.write_protection as u32,
+                SDCR::WP::offset,
+                SDCR::WP::mask,
+            ),
+            (
+                config.cas_latency as u32,
+                SDCR::CAS::offset,
+                SDCR::CAS::mask,
+            ),
+            (
+                (config.internal_banks == 4) as u32,
+                SDCR::NB::offset,
+                SDCR::NB::mask,
+            ),
+            (
+                match config.memory_data_width {
+                    8 => 0,
+                    16 => 1,
+                    _ => 2,
+                },
+                SDCR::MWID::offset,
+                SDCR::MWID::mask,
+            ),
+            (
+                (config.row_bits as u32).wrapping_sub(11),
+                SDCR::NR::offset,
+                SDCR::NR::mask,
+            ),
+            (
+                (config.column_bits as u32).wrapping_sub(8),
+                SDCR::NC::offset,
+                SDCR::NC::mask,
+            ),
+        ];
+        let expected_sdtr = [
+            (timing.row_to_column, SDTR::TRCD::offset, SDTR::TRCD::mask),
+            (write_recovery(&timing), SDTR::TWR::offset, SDTR::TWR::mask),
+            (
+                timing.active_to_precharge,
+                SDTR::TRAS::offset,
+                SDTR::TRAS::mask,
+            ),
+            (
+                timing.exit_self_refresh,
+                SDTR::TXSR::offset,
+                SDTR::TXSR::mask,
+            ),
+            (
+                timing.mode_register_to_active,
+                SDTR::TMRD::offset,
+                SDTR::TMRD::mask,
+            ),
+        ]
+        .map(|(cycles, offset, mask)| (cycles.wrapping_sub(1), offset, mask));
+
+        // Mask of the fields in `actual` that differ from `expected`
+        let differ = |actual: u32, expected: &[(u32, u32, u32)]| {
+            expected
+                .iter()
+                .filter(|&&(value, offset, mask)| {
+                    actual & mask != (value << offset) & mask
+                })
+                .fold(0, |fields, &(_, _, mask)| fields | mask)
+        };
+
+        let regs = self.regs.global();
+        if let SdramTargetBank::Bank1 | SdramTargetBank::Both = self.target_bank
+        {
+            diff.sdcr |= differ(read_reg!(fmc, regs, SDCR1), &expected_sdcr);
+            diff.sdtr |= differ(read_reg!(fmc, regs, SDTR1), &expected_sdtr);
+        }
+        if let SdramTargetBank::Bank2 | SdramTargetBank::Both = self.target_bank
+        {
+            diff.sdcr |= differ(read_reg!(fmc, regs, SDCR2), &expected_sdcr);
+            diff.sdtr |= differ(read_reg!(fmc, regs, SDTR2), &expected_sdtr);
+        }
+
+        if diff.is_empty() {
+            Ok(())
+        } else {
+            Err(diff)
+        }
+    }
+
+    /// Program the refresh timer
+    fn set_refresh_counter(&mut self, refresh_counter_top: u32) {
+        fmc_trace!("SDRTR: count {}", refresh_counter_top);
+
+        modify_reg!(
+            fmc,
+            self.regs.global(),
+            SDRTR,
+            COUNT: refresh_counter_top
         );
+    }
+
+    /// Read back the SD clock, timings and refresh timer programmed by
+    /// `init`, or `None` if the SDRAM controller has not been initialised
+    ///
+    /// These are the values in use, including any changes made to satisfy
+    /// an SDRAM on the other bank, so they can be checked against the
+    /// datasheet. When targeting both banks, the timings for bank 1 are
+    /// reported.
+    pub fn timing_report(&self) -> Option<SdramTimingReport> {
+        let sd_clock_divider = self.sd_clock_divider()?;
+        let regs = self.regs.global();
 
-        // Column bits: 8 ~ 11
+        // Common settings in SDTR1
+        let (trc, trp) = read_reg!(fmc, regs, SDTR1, TRC, TRP);
+        let (trcd, twr, tras, txsr, tmrd) = match self.target_bank {
+            SdramTargetBank::Bank2 => {
+                read_reg!(fmc, regs, SDTR2, TRCD, TWR, TRAS, TXSR, TMRD)
+            }
+            _ => read_reg!(fmc, regs, SDTR1, TRCD, TWR, TRAS, TXSR, TMRD),
+        };
+
+        Some(SdramTimingReport {
+            sd_clock_divider,
+            sd_clock_hz: self.fmc.source_clock_hz() / sd_clock_divider,
+            mode_register_to_active: tmrd + 1,
+            exit_self_refresh: txsr + 1,
+            self_refresh: tras + 1,
+            row_cycle: trc + 1,
+            write_recovery: twr + 1,
+            row_precharge: trp + 1,
+            row_to_column: trcd + 1,
+            refresh_counter: read_reg!(fmc, regs, SDRTR, COUNT),
+        })
+    }
+
+    /// Read the status of the SDRAM controller
+    fn controller_status(&self) -> SdramStatus {
+        let (re, modes1, modes2, busy) =
+            read_reg!(fmc, self.regs.global(), SDSR, RE, MODES1, MODES2, BUSY);
+
+        SdramStatus {
+            bank1: SdramMode::from(modes1),
+            bank2: SdramMode::from(modes2),
+            busy: busy != 0,
+            refresh_error: re != 0,
+        }
+    }
+
+    /// Issue `count` consecutive auto-refresh commands. See
+    /// [`auto_refresh`](Sdram::auto_refresh)
+    fn send_auto_refresh(&mut self, count: u8) {
         assert!(
-            config.column_bits >= 8 && config.column_bits <= 11,
-            "Impossible configuration for FMC Controller"
+            (1..=15).contains(&count),
+            "The FMC can issue between 1 and 15 consecutive auto-refresh commands"
         );
-
-        // Read Pipe Delay Cycles 0 ~ 2
         assert!(
-            config.read_pipe_delay_cycles <= 2,
-            "Impossible configuration for FMC Controller"
+            (1..=16).contains(&self.timing.row_cycle),
+            "Row cycle delay (tRC) too long for FMC Controller"
         );
 
+        unsafe {
+            self.send_command(
+                SdramCommand::Autorefresh(count),
+                self.target_bank,
+            );
+        }
+    }
+
+    /// Refresh period in use, including any change made after
+    /// initialisation
+    fn refresh_period_ns(&self) -> u32 {
+        self.refresh_period_ns
+            .unwrap_or(self.timing.refresh_period_ns)
+    }
+
+    /// Timing parameters at the given SD clock
+    fn timing_at(&self, sd_clock_hz: u32) -> SdramTiming {
+        match self.timing_ns {
+            Some(timing_ns) => timing_ns.cycles(sd_clock_hz),
+            None => self.timing,
+        }
+    }
+
+    /// Wait for the SDRAM controller to finish processing a command, then
+    /// check the mode of the target bank
+    fn check_mode(&mut self, expected: SdramMode) -> Result<(), SdramMode> {
+        let status = loop {
+            let status = self.controller_status();
+            if !status.busy {
+                break status;
+            }
+        };
+
+        let mode = match self.target_bank {
+            SdramTargetBank::Bank1 => status.bank1,
+            SdramTargetBank::Bank2 => status.bank2,
+            // Report bank 2 if bank 1 is already in the expected mode
+            SdramTargetBank::Both => {
+                if status.bank1 == expected {
+                    status.bank2
+                } else {
+                    status.bank1
+                }
+            }
+        };
+
+        fmc_trace!("SDRAM mode: {:?}", mode);
+        if mode == expected {
+            Ok(())
+        } else {
+            Err(mode)
+        }
+    }
+
+    /// Program memory device features and timings
+    ///
+    /// Some settings are common between both banks. If `running` is set, the
+    /// other bank has already been configured, and the common settings are
+    /// combined with the existing ones so that both SDRAMs are satisfied.
+    ///
+    /// For example, see RM0433 rev 7 Section 22.9.3
+    ///
+    /// # Safety
+    ///
+    /// The SDRAM must not be accessed while its configuration is changed
+    unsafe fn set_features_timings(
+        &mut self,
+        config: SdramConfiguration,
+        timing: SdramTiming,
+        sd_clock_divide: u32,
+        running: bool,
+    ) {
+        // Features ---- SDCR REGISTER
+        // (`config` has been checked by `check_configuration`)
+
         // Common settings written to SDCR1 only
+        let mut read_pipe = config.read_pipe_delay_cycles as u32;
+        let mut read_burst = config.read_burst as u32;
+        if running {
+            let (rpipe, rburst) =
+                read_reg!(fmc, self.regs.global(), SDCR1, RPIPE, RBURST);
+            read_pipe = cmp::max(read_pipe, rpipe);
+            read_burst &= rburst;
+        }
         modify_reg!(fmc, self.regs.global(), SDCR1,
-                    RPIPE: config.read_pipe_delay_cycles as u32,
-                    RBURST: config.read_burst as u32,
+                    RPIPE: read_pipe,
+                    RBURST: read_burst,
                     SDCLK: sd_clock_divide);
 
         modify_reg_banked!(fmc, self.regs.global(),
@@ -456,19 +1750,13 @@ impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
                            NB:
                            match config.internal_banks {
                                2 => 0,
-                               4 => 1,
-                               _ => {
-                                   panic!("Impossible configuration for FMC Controller")
-                               }
+                               _ => 1,
                            },
                            MWID:
                            match config.memory_data_width {
                                8 => 0,
                                16 => 1,
-                               32 => 2,
-                               _ => {
-                                   panic!("Impossible configuration for FMC Controller")
-                               }
+                               _ => 2,
                            },
                            NR: config.row_bits as u32 - 11,
                            NC: config.column_bits as u32 - 8);
@@ -478,26 +1766,25 @@ impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
         // Self refresh >= ACTIVE to PRECHARGE
         let minimum_self_refresh = timing.active_to_precharge;
 
-        // Write recovery - Self refresh
-        let write_recovery_self_refresh =
-            minimum_self_refresh - timing.row_to_column;
-        // Write recovery - WRITE command to PRECHARGE command
-        let write_recovery_row_cycle =
-            timing.row_cycle - timing.row_to_column - timing.row_precharge;
-        let write_recovery =
-            cmp::max(write_recovery_self_refresh, write_recovery_row_cycle);
-
         // Common seting written to SDTR1 only
+        let mut trc = timing.row_cycle - 1;
+        let mut trp = timing.row_precharge - 1;
+        if running {
+            let (running_trc, running_trp) =
+                read_reg!(fmc, self.regs.global(), SDTR1, TRC, TRP);
+            trc = cmp::max(trc, running_trc);
+            trp = cmp::max(trp, running_trp);
+        }
         modify_reg!(fmc, self.regs.global(), SDTR1,
-                    TRC: timing.row_cycle - 1,
-                    TRP: timing.row_precharge - 1
+                    TRC: trc,
+                    TRP: trp
         );
 
         modify_reg_banked!(fmc, self.regs.global(),
                            self.target_bank, SDTR1, SDTR2,
                            // fields
                            TRCD: timing.row_to_column - 1,
-                           TWR: write_recovery - 1,
+                           TWR: write_recovery(&timing) - 1,
                            TRAS: minimum_self_refresh - 1,
                            TXSR: timing.exit_self_refresh - 1,
                            TMRD: timing.mode_register_to_active - 1
@@ -536,7 +1823,7 @@ impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
             self.regs.global(),
             SDCMR,
             MRD: mode_reg as u32,
-            NRFS: number_refresh as u32,
+            NRFS: number_refresh as u32 - 1, // 0b0000: 1 auto-refresh
             CTB1: b1,
             CTB2: b2,
             MODE: cmd
@@ -553,3 +1840,753 @@ impl<IC: SdramChip, FMC: FmcPeripheral> Sdram<FMC, IC> {
         );
     }
 }
+
+impl<IC, FMC: FmcPeripheral, PINS> Sdram<FMC, IC, PINS, Initialized> {
+    /// Base address of the memory-mapped SDRAM block
+    ///
+    /// When targeting both banks this is the chip on bank 1, and the chip on
+    /// bank 2 follows at [`FmcBank::Bank6`](crate::FmcBank::Bank6), or at
+    /// [`FmcBank::Bank2`](crate::FmcBank::Bank2) if the banks are remapped
+    /// with [`set_bank_remap`](Sdram::set_bank_remap).
+    pub fn as_ptr(&self) -> *mut u32 {
+        self.fmc_bank.ptr()
+    }
+
+    /// The whole memory as a slice
+    ///
+    /// The length of the slice is calculated from the geometry in the
+    /// configuration. When targeting both banks, the slice only covers the
+    /// chip on bank 1.
+    ///
+    /// # Safety
+    ///
+    /// The slice must be the only reference to the SDRAM. This method must
+    /// not be called again while the slice is in use, and no other pointers
+    /// to the SDRAM may be used.
+    pub unsafe fn as_mut_slice(&mut self) -> &'static mut [u32] {
+        let len = self.config.size_bytes() / size_of::<u32>();
+
+        slice::from_raw_parts_mut(self.as_ptr(), len)
+    }
+
+    /// A handle to the whole memory
+    ///
+    /// The handle carries the length of the memory, calculated from the
+    /// geometry in the configuration, so that accesses through it are bounds
+    /// checked. When targeting both banks, it only covers the chip on bank 1.
+    ///
+    /// # Safety
+    ///
+    /// The handle must be the only way the SDRAM is accessed. This method
+    /// must not be called again while the handle is in use, and no other
+    /// pointers to the SDRAM may be used.
+    pub unsafe fn external_ram(&mut self) -> ExternalRam {
+        ExternalRam::from_raw_parts(self.as_ptr(), self.config.size_bytes())
+    }
+
+    /// Change the period between refresh cycles
+    ///
+    /// The refresh timer is recomputed from the current FMC source clock and
+    /// the SD clock divider programmed by `init`. Call this after changing the
+    /// FMC source clock, or to refresh more often at high temperature. The
+    /// new period is kept by [`resume`](Sdram::resume) and
+    /// [`reinit_timings`](Sdram::reinit_timings).
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the SD clock has been stopped by [`suspend`](Sdram::suspend)
+    ///
+    /// * Panics if the refresh period cannot be programmed at the current SD
+    ///   clock
+    pub fn set_refresh_period_ns(&mut self, refresh_period_ns: u32) {
+        let sd_clock_hz = self.sd_clock_hz().expect("SD clock stopped");
+        match refresh_counter(
+            refresh_period_ns,
+            sd_clock_hz,
+            self.refresh_margin,
+        ) {
+            Ok(refresh_counter_top) => {
+                self.refresh_period_ns = Some(refresh_period_ns);
+                self.set_refresh_counter(refresh_counter_top)
+            }
+            Err(e) => {
+                panic!("Impossible configuration for FMC Controller: {:?}", e)
+            }
+        }
+    }
+
+    /// Adjust the refresh rate for the SDRAM case temperature
+    ///
+    /// Most SDRAMs must be refreshed twice as often above 85°C. The refresh
+    /// period from the timing parameters is halved when `temperature_c` is
+    /// above 85°C, and restored otherwise. Call this whenever a new
+    /// temperature reading is available.
+    ///
+    /// # Panics
+    ///
+    /// * Panics under the same conditions as
+    ///   [`set_refresh_period_ns`](Sdram::set_refresh_period_ns)
+    pub fn set_refresh_temperature(&mut self, temperature_c: i32) {
+        let derating = if temperature_c > 85 { 2 } else { 1 };
+        self.set_refresh_derating(derating);
+    }
+
+    /// Refresh `derating` times as often as required by the timing
+    /// parameters
+    ///
+    /// Use this if the datasheet requires a different derating from
+    /// [`set_refresh_temperature`](Sdram::set_refresh_temperature). A
+    /// `derating` of 1 restores the nominal refresh period.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `derating` is zero
+    ///
+    /// * Panics under the same conditions as
+    ///   [`set_refresh_period_ns`](Sdram::set_refresh_period_ns)
+    pub fn set_refresh_derating(&mut self, derating: u32) {
+        assert!(derating > 0, "Refresh derating must be at least 1");
+
+        fmc_trace!("Refresh derating: {}", derating);
+        self.set_refresh_period_ns(self.timing.refresh_period_ns / derating);
+    }
+
+    /// Enable or disable read bursts after the SDRAM has been initialised
+    ///
+    /// This setting is common to both banks, so it also applies to an SDRAM
+    /// on the other bank. Do not enable read bursts on parts where the
+    /// errata sheet advises against them, see
+    /// [`override_read_burst`](Sdram::override_read_burst). The configuration
+    /// of this instance is updated, and is used if the SDRAM controller is
+    /// programmed again.
+    pub fn set_read_burst(&mut self, read_burst: bool) {
+        self.config.read_burst = read_burst;
+        modify_reg!(fmc, self.regs.global(), SDCR1, RBURST: read_burst as u32);
+    }
+
+    /// Change the read pipe delay after the SDRAM has been initialised
+    ///
+    /// This setting is common to both banks, so it also applies to an SDRAM
+    /// on the other bank. The configuration of this instance is updated, and
+    /// is used if the SDRAM controller is programmed again.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `cycles` is greater than 2
+    pub fn set_read_pipe_delay(&mut self, cycles: u8) {
+        assert!(cycles <= 2, "Read pipe delay must be at most 2 cycles");
+
+        self.config.read_pipe_delay_cycles = cycles;
+        modify_reg!(fmc, self.regs.global(), SDCR1, RPIPE: cycles as u32);
+    }
+
+    /// Put the SDRAM into self-refresh mode
+    ///
+    /// In self-refresh mode the SDRAM retains its contents using its internal
+    /// refresh, without the SD clock. This can be used to park the SDRAM
+    /// before reducing clocks or entering a low-power state. The SDRAM must
+    /// not be accessed until [`exit_self_refresh`](Sdram::exit_self_refresh)
+    /// is called.
+    ///
+    /// Returns an error containing the mode reported by the FMC if the SDRAM
+    /// did not enter self-refresh mode.
+    pub fn enter_self_refresh(&mut self) -> Result<(), SdramMode> {
+        unsafe {
+            self.send_command(SdramCommand::Selfrefresh, self.target_bank);
+        }
+        self.check_mode(SdramMode::SelfRefresh)
+    }
+
+    /// Return the SDRAM from self-refresh to normal mode
+    ///
+    /// Returns an error containing the mode reported by the FMC if the SDRAM
+    /// did not return to normal mode.
+    pub fn exit_self_refresh(&mut self) -> Result<(), SdramMode> {
+        unsafe {
+            self.send_command(SdramCommand::NormalMode, self.target_bank);
+        }
+        self.check_mode(SdramMode::Normal)
+    }
+
+    /// Prepare the SDRAM for a low-power mode of the microcontroller
+    ///
+    /// The SDRAM is put into self-refresh mode, where it retains its
+    /// contents without the SD clock. If `gate_clock` is true the SD clock is
+    /// then stopped, which also stops it for an SDRAM on the other bank.
+    ///
+    /// The SDRAM keeps its contents through STOP and STANDBY modes as long as
+    /// it stays powered and SDCKE is held low, for example by a pull-down
+    /// resistor. Call [`resume`](Sdram::resume) before accessing the SDRAM
+    /// again.
+    ///
+    /// Returns an error containing the mode reported by the FMC if the SDRAM
+    /// did not enter self-refresh mode. In that case the SD clock is not
+    /// stopped.
+    pub fn suspend(&mut self, gate_clock: bool) -> Result<(), SdramMode> {
+        self.enter_self_refresh()?;
+        self.suspended = true;
+
+        if gate_clock {
+            fmc_trace!("SD clock stopped");
+            modify_reg!(fmc, self.regs.global(), SDCR1, SDCLK: 0);
+        }
+        Ok(())
+    }
+
+    /// Return the SDRAM to normal mode after [`suspend`](Sdram::suspend)
+    ///
+    /// If the SD clock was stopped, or the FMC registers were reset in
+    /// STANDBY mode, the SDRAM controller is enabled and programmed again
+    /// from the configuration and timing of this instance. If an SDRAM on
+    /// the other bank has already been resumed, only the registers for this
+    /// bank are restored, and the settings common to both banks are combined
+    /// as in [`init`](Sdram::init). The SDRAM's mode register is retained in
+    /// self-refresh mode, so the initialisation sequence is not repeated. The
+    /// SDRAM is then returned to normal mode, and the refresh timer is
+    /// programmed for the refresh period that was in use before `suspend`,
+    /// including any derating, at the SD clock now running.
+    ///
+    /// Returns an error containing the mode reported by the FMC if the SDRAM
+    /// did not return to normal mode.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the FMC source clock is too fast for the maximum SD clock
+    ///   in the timing parameters
+    ///
+    /// * Panics if the refresh period cannot be programmed at the SD clock
+    pub fn resume(&mut self) -> Result<(), SdramMode> {
+        // The other bank may have been resumed already, in which case only
+        // the registers for this bank are at their reset values
+        let running_divide = read_reg!(fmc, self.regs.global(), SDCR1, SDCLK);
+        let running = running_divide != 0;
+        let sdcr = match self.target_bank {
+            SdramTargetBank::Bank2 => read_reg!(fmc, self.regs.global(), SDCR2),
+            _ => read_reg!(fmc, self.regs.global(), SDCR1),
+        };
+        let bank_reset = sdcr & SDCR_BANK_FIELDS == SDCR_RESET;
+
+        if !running || bank_reset {
+            let fmc_source_ck_hz = self.fmc.source_clock_hz();
+            let divide =
+                sd_clock_divide(fmc_source_ck_hz, self.timing.max_sd_clock_hz)
+                    .unwrap_or_else(|e| {
+                        panic!(
+                            "Impossible configuration for FMC Controller: {:?}",
+                            e
+                        )
+                    });
+            let divide = cmp::max(divide, running_divide);
+            let timing = self.timing_at(fmc_source_ck_hz / divide);
+            fmc_trace!("Restoring SDRAM controller (SD clock /{})", divide);
+
+            unsafe {
+                if !self.regs.in_use() {
+                    self.fmc.enable();
+                }
+                self.set_features_timings(self.config, timing, divide, running);
+                if !running {
+                    self.fmc.memory_controller_enable();
+                }
+            }
+        }
+
+        self.exit_self_refresh()?;
+        if self.suspended {
+            self.suspended = false;
+            self.set_refresh_period_ns(self.refresh_period_ns());
+        }
+        Ok(())
+    }
+
+    /// Change the FMC source clock, and reprogram the SD clock divider,
+    /// timings and refresh timer for the new clock
+    ///
+    /// The SDRAM is put into self-refresh mode, and `change_clock` is called
+    /// to change the FMC source clock. After it returns,
+    /// [`source_clock_hz`](FmcPeripheral::source_clock_hz) must report the
+    /// new clock. The SDRAM controller is then programmed for the new clock,
+    /// keeping any refresh period set by
+    /// [`set_refresh_period_ns`](Sdram::set_refresh_period_ns), and the SDRAM
+    /// returns to normal mode. The power-up sequence is not
+    /// repeated, so the contents of the SDRAM are retained.
+    ///
+    /// The settings that are common to both banks are recalculated for this
+    /// SDRAM only. If an SDRAM on the other bank is in use, it must be
+    /// reprogrammed afterwards.
+    ///
+    /// Returns an error if the timing cannot be achieved at the new clock,
+    /// in which case the SDRAM is left in self-refresh mode, or if the SDRAM
+    /// does not enter or leave self-refresh mode.
+    pub fn reinit_timings<F>(
+        &mut self,
+        change_clock: F,
+    ) -> Result<(), SdramInitError>
+    where
+        F: FnOnce(&mut FMC),
+    {
+        self.enter_self_refresh()
+            .map_err(SdramInitError::SelfRefresh)?;
+
+        change_clock(&mut self.fmc);
+
+        // Check everything at the new clock
+        let fmc_source_ck_hz = self.fmc.source_clock_hz();
+        let divide =
+            sd_clock_divide(fmc_source_ck_hz, self.timing.max_sd_clock_hz)?;
+        let sd_clock_hz = fmc_source_ck_hz / divide;
+        let timing = self.timing_at(sd_clock_hz);
+        check_timing(&timing)?;
+        let refresh_counter_top = refresh_counter(
+            self.refresh_period_ns(),
+            sd_clock_hz,
+            self.refresh_margin,
+        )?;
+        fmc_trace!("FMC clock {:?} (/{})", sd_clock_hz, divide);
+
+        unsafe {
+            self.set_features_timings(self.config, timing, divide, false);
+        }
+
+        self.exit_self_refresh()
+            .map_err(SdramInitError::SelfRefresh)?;
+        self.set_refresh_counter(refresh_counter_top);
+        Ok(())
+    }
+
+    /// Put the SDRAM into power-down mode, deasserting SDCKE
+    ///
+    /// In power-down mode the SDRAM's input buffers are disabled, which
+    /// reduces its standby current while the memory is idle for long
+    /// periods. Unlike self-refresh mode the SD clock and refresh timer keep
+    /// running, and the FMC exits power-down mode to refresh the SDRAM or
+    /// when it is accessed. Call [`exit_power_down`](Sdram::exit_power_down)
+    /// to return to normal mode immediately.
+    ///
+    /// Returns an error containing the mode reported by the FMC if the SDRAM
+    /// did not enter power-down mode.
+    pub fn enter_power_down(&mut self) -> Result<(), SdramMode> {
+        unsafe {
+            self.send_command(SdramCommand::Powerdown, self.target_bank);
+        }
+        self.check_mode(SdramMode::PowerDown)
+    }
+
+    /// Return the SDRAM from power-down to normal mode, asserting SDCKE
+    ///
+    /// Returns an error containing the mode reported by the FMC if the SDRAM
+    /// did not return to normal mode.
+    pub fn exit_power_down(&mut self) -> Result<(), SdramMode> {
+        unsafe {
+            self.send_command(SdramCommand::NormalMode, self.target_bank);
+        }
+        self.check_mode(SdramMode::Normal)
+    }
+
+    /// Read the status of the SDRAM controller
+    ///
+    /// This reports the mode of the SDRAMs on both banks, whichever bank
+    /// this instance targets.
+    pub fn status(&self) -> SdramStatus {
+        self.controller_status()
+    }
+
+    /// Return `true` if the refresh error flag is set
+    ///
+    /// The flag is set when a refresh request is not serviced before the
+    /// next one is due, for example because of a long burst of accesses by
+    /// another bus master. The SDRAM may have lost data. The flag stays set
+    /// until it is cleared by
+    /// [`clear_refresh_error`](Sdram::clear_refresh_error).
+    pub fn refresh_error(&self) -> bool {
+        read_reg!(fmc, self.regs.global(), SDSR, RE == Error)
+    }
+
+    /// Clear the refresh error flag
+    pub fn clear_refresh_error(&mut self) {
+        modify_reg!(fmc, self.regs.global(), SDRTR, CRE: Clear);
+    }
+
+    /// Enable or disable the refresh error interrupt
+    ///
+    /// When enabled, the FMC interrupt is raised while the refresh error
+    /// flag is set. The interrupt handler must clear the flag with
+    /// [`clear_refresh_error`](Sdram::clear_refresh_error).
+    pub fn set_refresh_error_interrupt(&mut self, enable: bool) {
+        modify_reg!(fmc, self.regs.global(), SDRTR, REIE: enable as u32);
+    }
+
+    /// Issue a burst of `count` consecutive auto-refresh commands
+    ///
+    /// The FMC spaces consecutive auto-refresh commands by the row cycle delay
+    /// (tRC) programmed in SDTR1. Since tRC is specified in SD clock cycles it
+    /// is met at any SD clock up to `max_sd_clock_hz` in the timing
+    /// parameters, provided it fits in the register field. This can be used
+    /// to catch up on missed refreshes, for example after a long period with
+    /// the refresh timer suppressed.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `count` is not in the range 1 - 15
+    ///
+    /// * Panics if the row cycle delay in the timing parameters cannot be
+    ///   programmed into the FMC, as back-to-back refreshes would then violate
+    ///   tRC
+    pub fn auto_refresh(&mut self, count: u8) {
+        self.send_auto_refresh(count);
+    }
+
+    /// Send a command to the SDRAM
+    ///
+    /// This allows commands to be issued after initialisation, for example
+    /// to load a new mode register. The command is sent to the bank that
+    /// this SDRAM instance targets, once the SDRAM controller is ready to
+    /// accept it.
+    ///
+    /// The caller is responsible for the effect of the command on the
+    /// memory. For example after `LoadMode` the CAS latency and burst length
+    /// must still match the FMC configuration.
+    ///
+    /// # Panics
+    ///
+    /// * Panics under the same conditions as
+    ///   [`auto_refresh`](Sdram::auto_refresh) for an `Autorefresh` command
+    pub fn send_raw_command(&mut self, command: SdramCommand) {
+        while read_reg!(fmc, self.regs.global(), SDSR, BUSY == Busy) {}
+
+        match command {
+            SdramCommand::Autorefresh(count) => self.auto_refresh(count),
+            _ => unsafe { self.send_command(command, self.target_bank) },
+        }
+    }
+
+    /// Load the extended mode register of a mobile SDRAM
+    ///
+    /// `extended_mode_register` must include the
+    /// [`SELECT`](ExtendedModeRegister::SELECT) bit, as returned by
+    /// [`ExtendedModeRegister::bits`](ExtendedModeRegister::bits). All
+    /// internal banks are precharged first. This can be used after
+    /// initialisation, for example to reduce the refreshed part of the array
+    /// before entering self-refresh mode.
+    ///
+    /// STM32H7 only, as the select bit is driven on BA1 from MRD[13]. On the
+    /// STM32F4 and F7 this would overwrite the mode register instead. See
+    /// [`ExtendedModeRegister`](ExtendedModeRegister).
+    pub fn load_extended_mode_register(&mut self, extended_mode_register: u16) {
+        self.send_raw_command(SdramCommand::Pall);
+        self.send_raw_command(SdramCommand::LoadMode(extended_mode_register));
+    }
+
+    /// Put the SDRAM into self-refresh mode, disable its bank and release
+    /// the FMC peripheral and pins
+    ///
+    /// The FMC can then be used to construct a different memory type, or the
+    /// pins can be returned to GPIO. The SDRAM keeps its contents while it
+    /// stays powered and SDCKE is held low. Any pointers to the SDRAM must
+    /// not be used after this.
+    ///
+    /// The configuration of this bank is returned to its reset value. If an
+    /// SDRAM on the other bank is configured, the SD clock and refresh timer
+    /// are left running so that it is not affected. Otherwise they are
+    /// stopped as well. A new `Sdram` instance for the same bank returns the
+    /// SDRAM to normal mode when it is initialised.
+    ///
+    /// If the SDRAM did not enter self-refresh mode, nothing is disabled and
+    /// an error is returned containing this instance and the mode reported by
+    /// the FMC.
+    pub fn free(mut self) -> Result<(FMC, PINS), (Self, SdramMode)> {
+        unsafe {
+            self.send_command(SdramCommand::Selfrefresh, self.target_bank);
+        }
+        if let Err(mode) = self.check_mode(SdramMode::SelfRefresh) {
+            return Err((self, mode));
+        }
+
+        let regs = self.regs.global();
+        let sdcr1 = read_reg!(fmc, regs, SDCR1);
+        let sdcr2 = read_reg!(fmc, regs, SDCR2);
+        let other_configured = match self.target_bank {
+            SdramTargetBank::Bank1 => sdcr2 & SDCR_BANK_FIELDS != SDCR_RESET,
+            SdramTargetBank::Bank2 => sdcr1 & SDCR_BANK_FIELDS != SDCR_RESET,
+            SdramTargetBank::Both => false,
+        };
+
+        match (other_configured, self.target_bank) {
+            // Keep the settings common to both banks in SDCR1
+            (true, SdramTargetBank::Bank1) => {
+                let sdcr1 = (sdcr1 & !SDCR_BANK_FIELDS) | SDCR_RESET;
+                write_reg!(fmc, regs, SDCR1, sdcr1);
+            }
+            (true, _) => write_reg!(fmc, regs, SDCR2, SDCR_RESET),
+            (false, _) => {
+                fmc_trace!("SD clock stopped");
+                write_reg!(fmc, regs, SDCR1, SDCR_RESET);
+                write_reg!(fmc, regs, SDCR2, SDCR_RESET);
+                write_reg!(fmc, regs, SDRTR, 0);
+            }
+        }
+
+        Ok((self.fmc, self.pins))
+    }
+}
+
+/// Non-blocking SDRAM initialisation
+///
+/// The initialisation sequence is split so that the SDRAM powerup delay does
+/// not block. [`start`](SdramInit::start) programs the SDRAM controller and
+/// starts the SD clock, then [`poll`](SdramInit::poll) is called with the
+/// time that has elapsed, and completes the sequence once the powerup delay
+/// is over. Several SDRAMs can be started together this way.
+///
+/// ```no_run
+/// # use stm32_fmc::{FmcPeripheral, Sdram, SdramChip, SdramInit};
+/// # fn f<FMC: FmcPeripheral, IC: SdramChip>(sdram: Sdram<FMC, IC>) {
+/// let mut init = SdramInit::start(sdram).unwrap();
+/// let sdram = loop {
+///     // Do something else for a while...
+///     match init.poll(100) {
+///         Ok(sdram) => break sdram,
+///         Err(pending) => init = pending,
+///     }
+/// };
+/// # }
+/// ```
+pub struct SdramInit<FMC, IC, PINS> {
+    sdram: Sdram<FMC, IC, PINS>,
+    remaining_us: u32,
+    auto_refresh_commands: u8,
+    refresh_counter_top: u32,
+}
+
+impl<FMC, IC, PINS> fmt::Debug for SdramInit<FMC, IC, PINS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SdramInit")
+            .field("sdram", &self.sdram)
+            .field("remaining_us", &self.remaining_us)
+            .finish()
+    }
+}
+
+impl<IC, FMC: FmcPeripheral, PINS> SdramInit<FMC, IC, PINS> {
+    /// Start initialising `sdram`
+    ///
+    /// Returns an error if the configuration or timing cannot be achieved,
+    /// under the same conditions as [`Sdram::try_init`](Sdram::try_init). In
+    /// that case the FMC is not modified, and `sdram` is returned with the
+    /// error.
+    #[allow(clippy::result_large_err)]
+    pub fn start(
+        mut sdram: Sdram<FMC, IC, PINS>,
+    ) -> Result<Self, (Sdram<FMC, IC, PINS>, SdramInitError)> {
+        let (config, timing) = (sdram.config, sdram.timing);
+        let (startup_delay_us, auto_refresh_commands, refresh_counter_top) =
+            match sdram.init_start(config, timing) {
+                Ok(steps) => steps,
+                Err(e) => return Err((sdram, e)),
+            };
+
+        Ok(SdramInit {
+            sdram,
+            remaining_us: startup_delay_us,
+            auto_refresh_commands,
+            refresh_counter_top,
+        })
+    }
+
+    /// Time remaining until the powerup delay is over, in microseconds
+    pub fn remaining_us(&self) -> u32 {
+        self.remaining_us
+    }
+
+    /// Advance the initialisation by `elapsed_us` microseconds
+    ///
+    /// Returns the initialised instance once the initialisation is
+    /// complete, or this pending initialisation while the powerup delay is
+    /// still in progress. The time must not be underestimated.
+    pub fn poll(
+        mut self,
+        elapsed_us: u32,
+    ) -> Result<Sdram<FMC, IC, PINS, Initialized>, Self> {
+        self.remaining_us = self.remaining_us.saturating_sub(elapsed_us);
+
+        if self.remaining_us > 0 {
+            return Err(self);
+        }
+        self.sdram
+            .init_finish(self.auto_refresh_commands, self.refresh_counter_top);
+        Ok(self.sdram.into_state())
+    }
+}
+
+/// Check that the FMC can be configured for `config`
+const fn check_configuration(
+    config: &SdramConfiguration,
+) -> Result<(), SdramInitError> {
+    use SdramInitError::*;
+
+    if config.cas_latency < 1 || config.cas_latency > 3 {
+        Err(CasLatencyOutOfRange)
+    } else if config.row_bits < 11 || config.row_bits > 13 {
+        Err(RowBitsOutOfRange)
+    } else if config.column_bits < 8 || config.column_bits > 11 {
+        Err(ColumnBitsOutOfRange)
+    } else if config.read_pipe_delay_cycles > 2 {
+        Err(ReadPipeDelayOutOfRange)
+    } else if !matches!(config.internal_banks, 2 | 4) {
+        Err(InternalBanksOutOfRange)
+    } else if !matches!(config.memory_data_width, 8 | 16 | 32) {
+        Err(DataWidthOutOfRange)
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that each delay in `timing` fits in its 4-bit SDTR field
+const fn check_timing(timing: &SdramTiming) -> Result<(), SdramInitError> {
+    use SdramInitError::*;
+
+    let delays = [
+        (timing.row_cycle, RowCycleOutOfRange),
+        (
+            timing.mode_register_to_active,
+            ModeRegisterToActiveOutOfRange,
+        ),
+        (timing.exit_self_refresh, ExitSelfRefreshOutOfRange),
+        (timing.active_to_precharge, ActiveToPrechargeOutOfRange),
+        (timing.row_precharge, RowPrechargeOutOfRange),
+        (timing.row_to_column, RowToColumnOutOfRange),
+    ];
+
+    let mut i = 0;
+    while i < delays.len() {
+        let (cycles, error) = delays[i];
+        if cycles < 1 || cycles > 16 {
+            return Err(error);
+        }
+        i += 1;
+    }
+
+    // The write recovery delay is derived from these
+    if timing.auto_refresh_commands < 1 || timing.auto_refresh_commands > 15 {
+        Err(AutoRefreshCommandsOutOfRange)
+    } else if timing.active_to_precharge < timing.row_to_column {
+        Err(ActiveToPrechargeBelowRowToColumn)
+    } else if timing.row_cycle < timing.row_to_column + timing.row_precharge {
+        Err(RowCycleBelowRowToColumnAndPrecharge)
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that the CAS latency in the mode register matches the configuration
+const fn check_cas_latency(
+    mode_register: u16,
+    config: &SdramConfiguration,
+) -> Result<(), SdramInitError> {
+    if (mode_register >> 4) & 0b111 == config.cas_latency as u16 {
+        Ok(())
+    } else {
+        Err(SdramInitError::CasLatencyMismatch)
+    }
+}
+
+/// Calculate the SD clock divider from the FMC source clock, so that the SD
+/// clock does not exceed `max_sd_clock_hz`
+fn sd_clock_divide(
+    fmc_source_ck_hz: u32,
+    max_sd_clock_hz: u32,
+) -> Result<u32, SdramInitError> {
+    // Divider, round up. At least 2
+    let divide = cmp::max(
+        (fmc_source_ck_hz + max_sd_clock_hz - 1) / max_sd_clock_hz,
+        2,
+    );
+
+    // Max 3
+    if divide > 3 {
+        Err(SdramInitError::ClockTooFast {
+            required_divider: divide,
+            max_source_clock_hz: max_sd_clock_hz.saturating_mul(3),
+        })
+    } else {
+        Ok(divide)
+    }
+}
+
+/// Calculate the write recovery delay (tWR) in cycles
+fn write_recovery(timing: &SdramTiming) -> u32 {
+    // Write recovery - Self refresh
+    let write_recovery_self_refresh = timing
+        .active_to_precharge
+        .saturating_sub(timing.row_to_column);
+    // Write recovery - WRITE command to PRECHARGE command
+    let write_recovery_row_cycle = timing
+        .row_cycle
+        .saturating_sub(timing.row_to_column + timing.row_precharge);
+
+    // At least one cycle. Underflow is reported by `check_timing`
+    cmp::max(
+        cmp::max(write_recovery_self_refresh, write_recovery_row_cycle),
+        1,
+    )
+}
+
+/// Calculate the refresh timer count for a refresh period at the given SD
+/// clock
+const fn refresh_counter(
+    refresh_period_ns: u32,
+    sd_clock_hz: u32,
+    margin: u32,
+) -> Result<u32, SdramInitError> {
+    // period (ns) * frequency (hz) / 10^9 = count
+    let count = (refresh_period_ns as u64 * sd_clock_hz as u64) / 1_000_000_000;
+
+    // Less the safety margin. The result must be at least 41
+    match count.checked_sub(margin as u64) {
+        Some(top) if top >= (1 << 13) => {
+            Err(SdramInitError::RefreshCounterOverflow)
+        }
+        Some(top) if top >= 41 => Ok(top as u32),
+        _ => Err(SdramInitError::RefreshCounterUnderflow),
+    }
+}
+
+/// Description of an initialisation error, for panics in a const context
+const fn init_error_message(error: SdramInitError) -> &'static str {
+    use SdramInitError::*;
+
+    match error {
+        ClockTooFast { .. } => "FMC source clock too fast for the SD clock",
+        CasLatencyOutOfRange => "CAS latency must be 1 - 3 cycles",
+        CasLatencyMismatch => "CAS latency differs from the mode register",
+        RowBitsOutOfRange => "Row address bits must be 11 - 13",
+        ColumnBitsOutOfRange => "Column address bits must be 8 - 11",
+        ReadPipeDelayOutOfRange => "Read pipe delay must be 0 - 2 cycles",
+        InternalBanksOutOfRange => "Internal banks must be 2 or 4",
+        DataWidthOutOfRange => "Data width must be 8, 16 or 32 bits",
+        RowCycleOutOfRange => "tRC must be 1 - 16 cycles",
+        ModeRegisterToActiveOutOfRange => "tMRD must be 1 - 16 cycles",
+        ExitSelfRefreshOutOfRange => "tXSR must be 1 - 16 cycles",
+        ActiveToPrechargeOutOfRange => "tRAS must be 1 - 16 cycles",
+        RowPrechargeOutOfRange => "tRP must be 1 - 16 cycles",
+        RowToColumnOutOfRange => "tRCD must be 1 - 16 cycles",
+        AutoRefreshCommandsOutOfRange => "Auto-refresh commands must be 1 - 15",
+        ActiveToPrechargeBelowRowToColumn => "tRAS must be at least tRCD",
+        RowCycleBelowRowToColumnAndPrecharge => {
+            "tRC must be at least tRCD + tRP"
+        }
+        RefreshCounterOverflow => "Refresh period too long for the SD clock",
+        RefreshCounterUnderflow => "Refresh period too short for the SD clock",
+        SelfRefresh(_) => "SDRAM did not enter or leave self-refresh",
+    }
+}
+
+/// Return the result of a successful initialisation, or panic
+fn expect_init<T, U>(result: Result<T, (U, SdramInitError)>) -> T {
+    match result {
+        Ok(sdram) => sdram,
+        Err((_, e)) => {
+            panic!("Impossible configuration for FMC Controller: {:?}", e)
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Data cache maintenance for an external memory
+//!
+//! On cores with a data cache, such as the Cortex-M7, the cache must be
+//! cleaned before a DMA transfer reads from the memory, and invalidated
+//! after a DMA transfer writes to it.
+
+use cortex_m::peripheral::{CPUID, SCB};
+
+use super::ExternalRam;
+
+impl ExternalRam {
+    /// Write any data in the data cache for this memory back to the memory
+    ///
+    /// Call this before a DMA transfer reads from the memory.
+    pub fn clean_dcache(&self, scb: &mut SCB) {
+        scb.clean_dcache_by_address(self.as_ptr() as usize, self.len());
+    }
+
+    /// Discard any data in the data cache for this memory, so that the
+    /// next reads are from the memory
+    ///
+    /// Call this after a DMA transfer writes to the memory. Cache lines that
+    /// are only partly within this memory are cleaned before they are
+    /// invalidated, so that data outside the memory is not lost.
+    pub fn invalidate_dcache(&mut self, scb: &mut SCB) {
+        let line = (1 << CPUID::cache_dminline()) * 4;
+        let start = self.as_ptr() as usize;
+        let end = start + self.len();
+        let inner_start = (start + line - 1) & !(line - 1);
+        let inner_end = end & !(line - 1);
+
+        if inner_start >= inner_end {
+            scb.clean_invalidate_dcache_by_address(start, self.len());
+            return;
+        }
+        if start < inner_start {
+            scb.clean_invalidate_dcache_by_address(start, inner_start - start);
+        }
+        // Safety: the lines are entirely within this memory, which is only
+        // accessed through this handle
+        unsafe {
+            scb.invalidate_dcache_by_address(
+                inner_start,
+                inner_end - inner_start,
+            );
+        }
+        if inner_end < end {
+            scb.clean_invalidate_dcache_by_address(inner_end, end - inner_end);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Handle to an initialised external memory
+//!
+//! [`ExternalRam`] carries the base address and length of the memory, so
+//! that accesses can be bounds checked. All accesses through
+//! [`read_volatile`](ExternalRam::read_volatile) and
+//! [`write_volatile`](ExternalRam::write_volatile) are volatile.
+
+use core::mem::{align_of, size_of};
+use core::{mem, ptr, slice};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Types that can be read from and written to an [`ExternalRam`]
+///
+/// These are the integer types, which are valid for any contents of the
+/// memory.
+pub trait ExternalRamWord: Copy + sealed::Sealed {}
+
+macro_rules! external_ram_word {
+    ($($t:ty),+) => {
+        $(
+            impl sealed::Sealed for $t {}
+            impl ExternalRamWord for $t {}
+        )+
+    };
+}
+external_ram_word!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// An initialised external memory
+///
+/// Returned by [`Sdram::external_ram`](crate::Sdram::external_ram).
+#[derive(Debug)]
+pub struct ExternalRam {
+    ptr: *mut u32,
+    len: usize,
+}
+
+impl ExternalRam {
+    /// Create a handle for `len` bytes of memory at `ptr`
+    ///
+    /// # Safety
+    ///
+    /// The memory must be initialised and accessible, and must not be
+    /// accessed other than through this handle while it exists.
+    pub unsafe fn from_raw_parts(ptr: *mut u32, len: usize) -> Self {
+        ExternalRam { ptr, len }
+    }
+
+    /// Base address of the memory
+    pub fn as_ptr(&self) -> *mut u32 {
+        self.ptr
+    }
+
+    /// Length of the memory in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the memory has a length of zero
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read a value at `offset` bytes from the base address
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the value is not within the memory, or `offset` is not
+    ///   aligned for `T`
+    pub fn read_volatile<T: ExternalRamWord>(&self, offset: usize) -> T {
+        unsafe { ptr::read_volatile(self.checked::<T>(offset)) }
+    }
+
+    /// Write a value at `offset` bytes from the base address
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the value is not within the memory, or `offset` is not
+    ///   aligned for `T`
+    pub fn write_volatile<T: ExternalRamWord>(
+        &mut self,
+        offset: usize,
+        value: T,
+    ) {
+        unsafe { ptr::write_volatile(self.checked::<T>(offset), value) }
+    }
+
+    /// Split the memory into two handles at `mid` bytes from the base address
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `mid` is greater than the length of the memory, or is not
+    ///   a multiple of 4 bytes
+    pub fn split_at(self, mid: usize) -> (ExternalRam, ExternalRam) {
+        assert!(mid <= self.len, "Split outside of external memory");
+        assert!(mid % 4 == 0, "Split not aligned to a word");
+
+        let upper = unsafe { self.ptr.add(mid / 4) };
+        (
+            ExternalRam {
+                ptr: self.ptr,
+                len: mid,
+            },
+            ExternalRam {
+                ptr: upper,
+                len: self.len - mid,
+            },
+        )
+    }
+
+    /// Partition the memory into non-overlapping regions
+    pub fn partition(self) -> Partition {
+        Partition { rest: self }
+    }
+
+    /// Use the memory as a DMA buffer of `T`
+    ///
+    /// Any bytes at the end of the memory that do not make up a whole `T`
+    /// are not included in the buffer.
+    #[cfg(feature = "embedded-dma")]
+    pub fn into_dma_buffer<T: ExternalRamWord>(self) -> DmaBuffer<T> {
+        DmaBuffer {
+            ptr: self.ptr as *mut T,
+            len: self.len / size_of::<T>(),
+            ram: self,
+        }
+    }
+
+    /// The whole memory as a slice of `T`
+    ///
+    /// Any bytes at the end of the memory that do not make up a whole `T`
+    /// are not included.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the memory must be valid values of `T`. Accesses
+    /// through the slice are not volatile.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `T` is a zero-sized type or needs an alignment larger than
+    ///   the base address
+    pub unsafe fn as_slice<T>(&self) -> &[T] {
+        slice::from_raw_parts(self.base::<T>(), self.len / size_of::<T>())
+    }
+
+    /// The whole memory as a mutable slice of `T`
+    ///
+    /// Any bytes at the end of the memory that do not make up a whole `T`
+    /// are not included.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the memory must be valid values of `T`. Accesses
+    /// through the slice are not volatile.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `T` is a zero-sized type or needs an alignment larger than
+    ///   the base address
+    pub unsafe fn as_mut_slice<T>(&mut self) -> &mut [T] {
+        slice::from_raw_parts_mut(self.base::<T>(), self.len / size_of::<T>())
+    }
+
+    /// Base address as a pointer to `T`
+    fn base<T>(&self) -> *mut T {
+        assert!(size_of::<T>() != 0, "Zero-sized types are not supported");
+        assert!(
+            (self.ptr as usize) % align_of::<T>() == 0,
+            "Base address not aligned"
+        );
+        self.ptr as *mut T
+    }
+
+    /// Pointer to a `T` at `offset` bytes from the base address
+    fn checked<T>(&self, offset: usize) -> *mut T {
+        assert!(
+            offset
+                .checked_add(size_of::<T>())
+                .map_or(false, |end| end <= self.len),
+            "Offset outside of external memory"
+        );
+        assert!(
+            (self.ptr as usize + offset) % align_of::<T>() == 0,
+            "Offset not aligned"
+        );
+        unsafe { (self.ptr as *mut u8).add(offset) as *mut T }
+    }
+}
+
+/// Splits an external memory into non-overlapping regions
+///
+/// Regions are taken from the start of the memory in turn. Each is returned
+/// as its own [`ExternalRam`] handle, so regions cannot overlap.
+///
+/// ```ignore
+/// let mut partition = ram.partition();
+/// let framebuffer = partition.take_aligned(480 * 272 * 2, 64).unwrap();
+/// let network = partition.take(16 * 1024).unwrap();
+/// let heap = partition.into_remainder();
+/// ```
+#[derive(Debug)]
+pub struct Partition {
+    rest: ExternalRam,
+}
+
+impl Partition {
+    /// Take a region of `len` bytes
+    ///
+    /// `len` is rounded up to a multiple of 4 bytes. Returns `None` if there
+    /// is not enough memory remaining.
+    pub fn take(&mut self, len: usize) -> Option<ExternalRam> {
+        self.take_aligned(len, 4)
+    }
+
+    /// Take a region of `len` bytes, starting at a multiple of `align`
+    ///
+    /// `len` is rounded up to a multiple of 4 bytes. Any memory skipped to
+    /// align the region is lost. Returns `None` if there is not enough
+    /// memory remaining.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `align` is not a power of two
+    pub fn take_aligned(
+        &mut self,
+        len: usize,
+        align: usize,
+    ) -> Option<ExternalRam> {
+        assert!(align.is_power_of_two(), "Alignment not a power of two");
+
+        let align = align.max(4);
+        let padding = align.wrapping_sub(self.rest.ptr as usize) & (align - 1);
+        let len = len.checked_add(3)? & !3;
+        if padding.checked_add(len)? > self.rest.len {
+            return None;
+        }
+
+        let empty = ExternalRam {
+            ptr: self.rest.ptr,
+            len: 0,
+        };
+        let (_, rest) = mem::replace(&mut self.rest, empty).split_at(padding);
+        let (region, rest) = rest.split_at(len);
+        self.rest = rest;
+        Some(region)
+    }
+
+    /// Number of bytes remaining
+    pub fn remaining(&self) -> usize {
+        self.rest.len
+    }
+
+    /// The remaining memory, after all regions have been taken
+    pub fn into_remainder(self) -> ExternalRam {
+        self.rest
+    }
+}
+
+/// A region of external memory used as a DMA buffer
+///
+/// Implements the [`embedded_dma`] buffer traits, so that it can be passed
+/// to a HAL DMA transfer. Created by
+/// [`ExternalRam::into_dma_buffer`](ExternalRam::into_dma_buffer), usually
+/// after [`split_at`](ExternalRam::split_at) to select part of the memory.
+#[cfg(feature = "embedded-dma")]
+#[derive(Debug)]
+pub struct DmaBuffer<T> {
+    ptr: *mut T,
+    len: usize,
+    ram: ExternalRam,
+}
+
+#[cfg(feature = "embedded-dma")]
+impl<T> DmaBuffer<T> {
+    /// Number of `T` in the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if the buffer has a length of zero
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Release the memory, for example once a transfer has completed
+    pub fn into_ram(self) -> ExternalRam {
+        self.ram
+    }
+}
+
+#[cfg(feature = "embedded-dma")]
+unsafe impl<T: ExternalRamWord> embedded_dma::ReadBuffer for DmaBuffer<T> {
+    type Word = T;
+
+    unsafe fn read_buffer(&self) -> (*const T, usize) {
+        (self.ptr, self.len)
+    }
+}
+
+#[cfg(feature = "embedded-dma")]
+unsafe impl<T: ExternalRamWord> embedded_dma::WriteBuffer for DmaBuffer<T> {
+    type Word = T;
+
+    unsafe fn write_buffer(&mut self) -> (*mut T, usize) {
+        (self.ptr, self.len)
+    }
+}
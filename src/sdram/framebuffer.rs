@@ -0,0 +1,182 @@
+//! Framebuffers in external memory for `embedded-graphics`
+//!
+//! [`Framebuffer`] implements [`DrawTarget`], storing each pixel in the
+//! raw storage format of its color type, for example 16 bits for
+//! `Rgb565`. This is the format expected by a display controller such as
+//! the LTDC. [`DoubleBuffer`] holds two framebuffers, so that one can be
+//! drawn while the other is displayed.
+
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::{IntoStorage, PixelColor};
+use embedded_graphics_core::Pixel;
+
+use super::{ExternalRam, ExternalRamWord};
+
+/// Alignment of each buffer in a [`DoubleBuffer`], in bytes
+const BUFFER_ALIGN: usize = 64;
+
+/// A framebuffer in external memory
+#[derive(Debug)]
+pub struct Framebuffer<C> {
+    ram: ExternalRam,
+    width: u32,
+    height: u32,
+    _color: PhantomData<C>,
+}
+
+impl<C> Framebuffer<C>
+where
+    C: PixelColor + IntoStorage,
+    C::Storage: ExternalRamWord,
+{
+    /// Number of bytes needed for a framebuffer of `width` x `height`
+    pub fn size_bytes(width: u32, height: u32) -> usize {
+        width as usize * height as usize * size_of::<C::Storage>()
+    }
+
+    /// Create a framebuffer of `width` x `height` pixels in `ram`
+    ///
+    /// The first pixel is at the base address of `ram`, and rows are
+    /// contiguous. The contents are not cleared.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `ram` is shorter than
+    ///   [`size_bytes`](Framebuffer::size_bytes)
+    pub fn new(ram: ExternalRam, width: u32, height: u32) -> Self {
+        assert!(
+            ram.len() >= Self::size_bytes(width, height),
+            "External memory too short for framebuffer"
+        );
+
+        Framebuffer {
+            ram,
+            width,
+            height,
+            _color: PhantomData,
+        }
+    }
+
+    /// Base address of the framebuffer, for the display controller
+    pub fn as_ptr(&self) -> *mut u32 {
+        self.ram.as_ptr()
+    }
+
+    /// Release the memory
+    pub fn into_ram(self) -> ExternalRam {
+        self.ram
+    }
+
+    /// Write `color` to the pixel at `x`, `y`, which must be in bounds
+    fn set(&mut self, x: u32, y: u32, color: C) {
+        let index = y as usize * self.width as usize + x as usize;
+        self.ram.write_volatile(
+            index * size_of::<C::Storage>(),
+            color.into_storage(),
+        );
+    }
+}
+
+impl<C> OriginDimensions for Framebuffer<C> {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl<C> DrawTarget for Framebuffer<C>
+where
+    C: PixelColor + IntoStorage,
+    C::Storage: ExternalRamWord,
+{
+    type Color = C;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let (x, y) = (point.x as u32, point.y as u32);
+            // Negative coordinates wrap to large values, and are discarded
+            if x < self.width && y < self.height {
+                self.set(x, y, color);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.set(x, y, color);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Two framebuffers in external memory
+///
+/// Drawing is done to the back buffer, while the front buffer is shown by
+/// the display controller. [`swap`](DoubleBuffer::swap) exchanges them.
+///
+/// ```ignore
+/// let mut buffers = DoubleBuffer::<Rgb565>::new(ram, 480, 272);
+/// loop {
+///     draw(buffers.back_mut());
+///     buffers.swap();
+///     ltdc.set_framebuffer(buffers.front().as_ptr());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DoubleBuffer<C> {
+    front: Framebuffer<C>,
+    back: Framebuffer<C>,
+}
+
+impl<C> DoubleBuffer<C>
+where
+    C: PixelColor + IntoStorage,
+    C::Storage: ExternalRamWord,
+{
+    /// Create two framebuffers of `width` x `height` pixels in `ram`
+    ///
+    /// Each framebuffer starts on a 64 byte boundary.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `ram` is too short for both framebuffers
+    pub fn new(ram: ExternalRam, width: u32, height: u32) -> Self {
+        let len = Framebuffer::<C>::size_bytes(width, height);
+        let mut partition = ram.partition();
+        let mut take = || {
+            partition
+                .take_aligned(len, BUFFER_ALIGN)
+                .expect("External memory too short for framebuffers")
+        };
+        let front = Framebuffer::new(take(), width, height);
+        let back = Framebuffer::new(take(), width, height);
+
+        DoubleBuffer { front, back }
+    }
+
+    /// The framebuffer being displayed
+    pub fn front(&self) -> &Framebuffer<C> {
+        &self.front
+    }
+
+    /// The framebuffer being drawn
+    pub fn back_mut(&mut self) -> &mut Framebuffer<C> {
+        &mut self.back
+    }
+
+    /// Exchange the front and back framebuffers
+    pub fn swap(&mut self) {
+        core::mem::swap(&mut self.front, &mut self.back);
+    }
+}
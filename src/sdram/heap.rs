@@ -0,0 +1,81 @@
+//! Global allocator over an initialised external memory
+//!
+//! [`ExternalHeap`] is a linked-list allocator that starts empty, so that it
+//! can be registered as the `#[global_allocator]` before the SDRAM has been
+//! initialised. Allocations fail until [`init`](ExternalHeap::init) is
+//! called.
+
+use core::alloc::{GlobalAlloc, Layout};
+
+use linked_list_allocator::LockedHeap;
+
+use super::ExternalRam;
+
+/// A heap in external memory
+///
+/// ```ignore
+/// #[global_allocator]
+/// static HEAP: ExternalHeap = ExternalHeap::empty();
+///
+/// let mut sdram = sdram.init(&mut delay);
+/// let ram = unsafe { sdram.external_ram() };
+/// unsafe { HEAP.init(ram) };
+/// ```
+///
+/// The heap is protected by a spinlock. Allocating from an interrupt handler
+/// that pre-empts an allocation in progress will deadlock.
+pub struct ExternalHeap {
+    heap: LockedHeap,
+}
+
+impl core::fmt::Debug for ExternalHeap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ExternalHeap")
+            .field("size", &self.size())
+            .field("used", &self.used())
+            .finish()
+    }
+}
+
+impl ExternalHeap {
+    /// Create a heap with no memory
+    pub const fn empty() -> Self {
+        ExternalHeap {
+            heap: LockedHeap::empty(),
+        }
+    }
+
+    /// Give the heap the memory in `ram`
+    ///
+    /// # Safety
+    ///
+    /// Must only be called once.
+    pub unsafe fn init(&self, ram: ExternalRam) {
+        self.heap.lock().init(ram.as_ptr() as *mut u8, ram.len());
+    }
+
+    /// Size of the heap in bytes
+    pub fn size(&self) -> usize {
+        self.heap.lock().size()
+    }
+
+    /// Number of bytes currently allocated
+    pub fn used(&self) -> usize {
+        self.heap.lock().used()
+    }
+
+    /// Number of bytes currently free
+    pub fn free(&self) -> usize {
+        self.heap.lock().free()
+    }
+}
+
+unsafe impl GlobalAlloc for ExternalHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.heap.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap.dealloc(ptr, layout)
+    }
+}
@@ -0,0 +1,271 @@
+//! Builder for the value of the SDRAM mode register
+//!
+//! The layout of the mode register is common to JEDEC SDR SDRAMs:
+//!
+//! | Bits  | Field                |
+//! |-------|----------------------|
+//! | 2 - 0 | Burst length         |
+//! | 3     | Burst type           |
+//! | 6 - 4 | CAS latency          |
+//! | 8 - 7 | Operating mode       |
+//! | 9     | Write burst mode     |
+
+use super::SdramConfiguration;
+
+/// CAS latency in SD clock cycles
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cas {
+    /// 1 cycle
+    One = 1,
+    /// 2 cycles
+    Two = 2,
+    /// 3 cycles
+    Three = 3,
+}
+
+/// Value of the SDRAM mode register
+///
+/// Starts from a burst length of 1, sequential bursts, a CAS latency of 3
+/// and programmed burst length for writes. For example:
+///
+/// ```
+/// use stm32_fmc::{Cas, ModeRegister};
+///
+/// const MODE_REGISTER: u16 = ModeRegister::new()
+///     .burst_length(1)
+///     .cas_latency(Cas::Two)
+///     .single_write()
+///     .bits();
+/// assert_eq!(MODE_REGISTER, 0x0220);
+/// ```
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModeRegister {
+    bits: u16,
+    cas: Cas,
+}
+
+impl Default for ModeRegister {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModeRegister {
+    const BURST_LENGTH_MASK: u16 = 0x0007;
+    const BURST_TYPE_INTERLEAVED: u16 = 0x0008;
+    const CAS_LATENCY_MASK: u16 = 0x0070;
+    const WRITEBURST_MODE_SINGLE: u16 = 0x0200;
+
+    /// New mode register value, with a burst length of 1, sequential bursts
+    /// and a CAS latency of 3
+    pub const fn new() -> Self {
+        ModeRegister {
+            bits: (Cas::Three as u16) << 4,
+            cas: Cas::Three,
+        }
+    }
+
+    /// Set the burst length. This is also used for writes, unless
+    /// [`single_write`](ModeRegister::single_write) is selected
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `length` is not 1, 2, 4 or 8
+    pub const fn burst_length(self, length: u8) -> Self {
+        let field = match length {
+            1 => 0,
+            2 => 1,
+            4 => 2,
+            8 => 3,
+            _ => panic!("SDRAM burst length must be 1, 2, 4 or 8"),
+        };
+        ModeRegister {
+            bits: (self.bits & !Self::BURST_LENGTH_MASK) | field,
+            cas: self.cas,
+        }
+    }
+
+    /// Use the interleaved burst order instead of sequential
+    pub const fn interleaved(self) -> Self {
+        ModeRegister {
+            bits: self.bits | Self::BURST_TYPE_INTERLEAVED,
+            cas: self.cas,
+        }
+    }
+
+    /// Set the CAS latency
+    pub const fn cas_latency(self, cas: Cas) -> Self {
+        ModeRegister {
+            bits: (self.bits & !Self::CAS_LATENCY_MASK) | ((cas as u16) << 4),
+            cas,
+        }
+    }
+
+    /// Writes are single location accesses, whatever the burst length
+    pub const fn single_write(self) -> Self {
+        ModeRegister {
+            bits: self.bits | Self::WRITEBURST_MODE_SINGLE,
+            cas: self.cas,
+        }
+    }
+
+    /// Value to load into the mode register
+    pub const fn bits(self) -> u16 {
+        self.bits
+    }
+
+    /// Value to load into the mode register, after checking that the CAS
+    /// latency matches `config`. When used to define a constant, a mismatch
+    /// is a compile-time error
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the CAS latency differs from `config.cas_latency`
+    pub const fn checked(self, config: &SdramConfiguration) -> u16 {
+        assert!(
+            self.cas as u8 == config.cas_latency,
+            "CAS latency of the mode register and configuration differ"
+        );
+        self.bits
+    }
+}
+
+impl From<ModeRegister> for u16 {
+    fn from(mode_register: ModeRegister) -> u16 {
+        mode_register.bits
+    }
+}
+
+/// Partial array self-refresh: the part of the array that keeps its contents
+/// in self-refresh mode
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pasr {
+    /// All banks
+    Full = 0b000,
+    /// Half of the array (banks 0 and 1)
+    Half = 0b001,
+    /// Quarter of the array (bank 0)
+    Quarter = 0b010,
+    /// Eighth of the array (half of bank 0)
+    Eighth = 0b101,
+    /// Sixteenth of the array (quarter of bank 0)
+    Sixteenth = 0b110,
+}
+
+/// Temperature compensated self-refresh: the maximum case temperature in
+/// self-refresh mode
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tcsr {
+    /// 15°C
+    Max15C = 0b10,
+    /// 45°C
+    Max45C = 0b01,
+    /// 70°C
+    Max70C = 0b00,
+    /// 85°C
+    Max85C = 0b11,
+}
+
+/// Output drive strength
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DriveStrength {
+    /// Full strength
+    Full = 0b00,
+    /// Half strength
+    Half = 0b01,
+    /// Quarter strength
+    Quarter = 0b10,
+}
+
+/// Value of the extended mode register of a mobile (low power) SDR SDRAM
+///
+/// | Bits  | Field                                 |
+/// |-------|---------------------------------------|
+/// | 2 - 0 | Partial array self-refresh (PASR)     |
+/// | 4 - 3 | Temperature compensated self-refresh  |
+/// | 6 - 5 | Drive strength                        |
+///
+/// The value returned by [`bits`](ExtendedModeRegister::bits) also sets
+/// MRD[13], which the FMC drives on BA1 during a load mode register command
+/// so that the extended mode register is selected.
+///
+/// Only the STM32H7 has this 14-bit MRD field. On the STM32F4 and F7, MRD is
+/// 13 bits wide and BA1 is low during a load mode register command, so the
+/// extended mode register cannot be loaded and the (normal) mode register
+/// would be overwritten instead.
+///
+/// ```
+/// use stm32_fmc::{DriveStrength, ExtendedModeRegister, Pasr};
+///
+/// const EXTENDED_MODE_REGISTER: u16 = ExtendedModeRegister::new()
+///     .pasr(Pasr::Half)
+///     .drive_strength(DriveStrength::Half)
+///     .bits();
+/// assert_eq!(EXTENDED_MODE_REGISTER, 0x2021);
+/// ```
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExtendedModeRegister {
+    bits: u16,
+}
+
+impl Default for ExtendedModeRegister {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExtendedModeRegister {
+    /// MRD bit driven on BA1, which selects the extended mode register. STM32H7
+    /// only
+    pub const SELECT: u16 = 1 << 13;
+
+    const PASR_MASK: u16 = 0x0007;
+    const TCSR_MASK: u16 = 0x0018;
+    const DRIVE_STRENGTH_MASK: u16 = 0x0060;
+
+    /// New extended mode register value, with full array self-refresh,
+    /// self-refresh up to 70°C and full drive strength
+    pub const fn new() -> Self {
+        ExtendedModeRegister { bits: 0 }
+    }
+
+    /// Set the part of the array that is refreshed in self-refresh mode
+    pub const fn pasr(self, pasr: Pasr) -> Self {
+        ExtendedModeRegister {
+            bits: (self.bits & !Self::PASR_MASK) | pasr as u16,
+        }
+    }
+
+    /// Set the maximum temperature in self-refresh mode
+    pub const fn tcsr(self, tcsr: Tcsr) -> Self {
+        ExtendedModeRegister {
+            bits: (self.bits & !Self::TCSR_MASK) | ((tcsr as u16) << 3),
+        }
+    }
+
+    /// Set the output drive strength
+    pub const fn drive_strength(self, strength: DriveStrength) -> Self {
+        ExtendedModeRegister {
+            bits: (self.bits & !Self::DRIVE_STRENGTH_MASK)
+                | ((strength as u16) << 5),
+        }
+    }
+
+    /// Value to load with a load mode register command, including the
+    /// extended mode register select bit
+    pub const fn bits(self) -> u16 {
+        self.bits | Self::SELECT
+    }
+}
+
+impl From<ExtendedModeRegister> for u16 {
+    fn from(extended_mode_register: ExtendedModeRegister) -> u16 {
+        extended_mode_register.bits()
+    }
+}
@@ -0,0 +1,97 @@
+//! CRC-protected contents of an external memory
+//!
+//! When the SDRAM is kept in self-refresh across a reset, its contents may
+//! or may not have survived. [`ExternalRam::seal`] stamps the memory with a
+//! header containing a CRC of the contents, and
+//! [`ExternalRam::check_seal`] verifies it after the reset.
+//!
+//! The header occupies the first [`SEAL_HEADER_LEN`] bytes of the memory.
+
+use super::ExternalRam;
+
+/// Length in bytes of the header written by [`ExternalRam::seal`]
+pub const SEAL_HEADER_LEN: usize = 12;
+
+/// Reasons that the contents of a memory cannot be trusted
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SealError {
+    /// The memory is too short to hold the header
+    TooShort,
+    /// The header does not contain the expected magic value
+    BadMagic,
+    /// The length in the header does not match the memory
+    LengthMismatch,
+    /// The contents do not match the CRC in the header
+    CrcMismatch,
+}
+
+/// Update a CRC-32 (IEEE 802.3) with `byte`
+fn crc32_update(crc: u32, byte: u8) -> u32 {
+    let mut crc = crc ^ u32::from(byte);
+    for _ in 0..8 {
+        let mask = (crc & 1).wrapping_neg();
+        crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+    }
+    crc
+}
+
+impl ExternalRam {
+    /// CRC-32 of the memory after the header
+    fn contents_crc(&self) -> u32 {
+        let mut crc = !0;
+        let mut offset = SEAL_HEADER_LEN;
+        while offset + 4 <= self.len() {
+            let word: u32 = self.read_volatile(offset);
+            for &byte in word.to_le_bytes().iter() {
+                crc = crc32_update(crc, byte);
+            }
+            offset += 4;
+        }
+        while offset < self.len() {
+            crc = crc32_update(crc, self.read_volatile(offset));
+            offset += 1;
+        }
+        !crc
+    }
+
+    /// Stamp the memory with a header containing `magic` and a CRC of the
+    /// contents
+    ///
+    /// Call this after the last write to the memory, before entering
+    /// self-refresh. The contents start [`SEAL_HEADER_LEN`] bytes from the
+    /// base address.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if the memory is shorter than [`SEAL_HEADER_LEN`]
+    pub fn seal(&mut self, magic: u32) {
+        assert!(
+            self.len() >= SEAL_HEADER_LEN,
+            "External memory too short for header"
+        );
+
+        let crc = self.contents_crc();
+        self.write_volatile(0, magic);
+        self.write_volatile(4, self.len() as u32);
+        self.write_volatile(8, crc);
+    }
+
+    /// Check that the memory was stamped by [`seal`](ExternalRam::seal) with
+    /// `magic`, and that the contents have not changed since
+    pub fn check_seal(&self, magic: u32) -> Result<(), SealError> {
+        if self.len() < SEAL_HEADER_LEN {
+            return Err(SealError::TooShort);
+        }
+        if self.read_volatile::<u32>(0) != magic {
+            return Err(SealError::BadMagic);
+        }
+        if self.read_volatile::<u32>(4) != self.len() as u32 {
+            return Err(SealError::LengthMismatch);
+        }
+        if self.read_volatile::<u32>(8) != self.contents_crc() {
+            return Err(SealError::CrcMismatch);
+        }
+        Ok(())
+    }
+}
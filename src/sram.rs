@@ -0,0 +1,385 @@
+//! HAL for FMC peripheral used to access asynchronous SRAM
+//!
+//! Any asynchronous device with an SRAM-like interface on Bank 1 can be
+//! accessed this way, for example a latch or FPGA register file. The timings
+//! are given directly in FMC kernel clock cycles, so no chip definition is
+//! required.
+
+use core::cmp;
+
+use crate::fmc::{FmcBank, FmcRegisters};
+use crate::FmcPeripheral;
+
+use crate::ral::{fmc, modify_reg, read_reg};
+
+/// FMC SRAM Configuration
+///
+/// Timings are in cycles of the FMC kernel clock, and are used for both reads
+/// and writes (access mode 1).
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SramConfiguration {
+    /// Memory device width in bits: 8, 16 or 32
+    ///
+    /// 32 bits is only available on the FMC (for example STM32F42x/43x, F7
+    /// and H7). The MWID encoding for it is reserved on the FSMC of the
+    /// STM32F1, F2 and F40x/41x, so do not use it there.
+    pub memory_data_width: u8,
+    /// Write operations are enabled. Otherwise writes cause an AHB error
+    pub write_enable: bool,
+    /// ADDSET: Duration of the address setup phase (0 - 15 cycles)
+    pub address_setup_cycles: u8,
+    /// ADDHLD: Duration of the address hold phase (0 - 15 cycles). Only used
+    /// for multiplexed and mode D accesses, which this driver does not
+    /// configure, so it has no effect. A value of 0 is programmed as 1
+    pub address_hold_cycles: u8,
+    /// DATAST: Duration of the data phase (1 - 255 cycles)
+    pub data_setup_cycles: u8,
+    /// BUSTURN: Duration of the bus turnaround phase (0 - 15 cycles)
+    ///
+    /// The sub-banks of Bank 1 share the data bus, so every enabled SRAM or
+    /// LCD sub-bank is programmed with the largest turnaround required by any
+    /// of them. See [`Sram::init`].
+    pub bus_turnaround_cycles: u8,
+}
+
+/// Target sub-bank of FMC Bank 1, selected by the NE1 - NE4 chip selects
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SramTargetBank {
+    /// Sub-bank 1, selected by NE1
+    Bank1,
+    /// Sub-bank 2, selected by NE2
+    Bank2,
+    /// Sub-bank 3, selected by NE3
+    Bank3,
+    /// Sub-bank 4, selected by NE4
+    Bank4,
+}
+impl From<u32> for SramTargetBank {
+    fn from(n: u32) -> Self {
+        match n {
+            1 => SramTargetBank::Bank1,
+            2 => SramTargetBank::Bank2,
+            3 => SramTargetBank::Bank3,
+            4 => SramTargetBank::Bank4,
+            _ => panic!("Invalid SRAM sub-bank {}", n),
+        }
+    }
+}
+impl SramTargetBank {
+    /// Return a pointer to this sub-bank
+    pub fn ptr(self) -> *mut u32 {
+        let offset = match self {
+            SramTargetBank::Bank1 => 0,
+            SramTargetBank::Bank2 => 0x0400_0000,
+            SramTargetBank::Bank3 => 0x0800_0000,
+            SramTargetBank::Bank4 => 0x0C00_0000,
+        };
+        (FmcBank::Bank1.ptr() as usize + offset) as *mut u32
+    }
+}
+
+/// FMC Peripheral specialized as an SRAM Controller. Not yet initialized.
+///
+/// `PINS` is the pin set passed to [`new`](Sram::new), which is returned by
+/// [`free`](Sram::free).
+#[allow(missing_debug_implementations)]
+pub struct Sram<FMC, PINS = ()> {
+    /// SRAM sub-bank
+    target_bank: SramTargetBank,
+    /// Parameters for the SRAM
+    config: SramConfiguration,
+    /// FMC peripheral
+    fmc: FMC,
+    /// Pins
+    pins: PINS,
+    /// Register access
+    regs: FmcRegisters,
+}
+
+/// SRAM target sub-bank
+pub trait SramPinSet {
+    /// Sub-bank of FMC Bank 1
+    const TARGET: SramTargetBank;
+}
+
+macro_rules! sram_bank_markers {
+    ($($Bank:ident, $Target:ident, $NE:expr;)+) => {
+        $(
+            /// Type to mark SRAM on sub-bank selected by
+            #[doc=$NE]
+            #[derive(Clone, Copy, Debug)]
+            #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+            pub struct $Bank;
+            impl SramPinSet for $Bank {
+                const TARGET: SramTargetBank = SramTargetBank::$Target;
+            }
+        )+
+    };
+}
+sram_bank_markers! {
+    SramBank1, Bank1, "NE1";
+    SramBank2, Bank2, "NE2";
+    SramBank3, Bank3, "NE3";
+    SramBank4, Bank4, "NE4";
+}
+
+/// Set of pins for an SRAM on sub-bank `BANK`
+///
+/// Implemented for tuples of the data pins, the byte lane pins (for 16 and
+/// 32-bit memories), the chip select for `BANK` (NE1 - NE4), NOE and NWE in
+/// that order. Address pins are not checked, since many devices use only a
+/// few of them.
+pub trait PinsSram<BANK: SramPinSet> {
+    /// Number of data bus pins
+    const DATA_WIDTH: u8;
+}
+
+/// Modify a register for the target sub-bank
+macro_rules! modify_reg_banked {
+    ( $periph:path, $instance:expr, $bank:expr, $reg1:ident, $reg2:ident, $reg3:ident, $reg4:ident, $( $field:ident : $value:expr ),+ ) => {{
+        use SramTargetBank::*;
+
+        match $bank {
+            Bank1 => modify_reg!( $periph, $instance, $reg1, $( $field : $value ),*),
+            Bank2 => modify_reg!( $periph, $instance, $reg2, $( $field : $value ),*),
+            Bank3 => modify_reg!( $periph, $instance, $reg3, $( $field : $value ),*),
+            Bank4 => modify_reg!( $periph, $instance, $reg4, $( $field : $value ),*),
+        }
+    }};
+}
+
+/// Read a register field for the target sub-bank
+macro_rules! read_reg_banked {
+    ( $periph:path, $instance:expr, $bank:expr, $reg1:ident, $reg2:ident, $reg3:ident, $reg4:ident, $field:ident ) => {{
+        use SramTargetBank::*;
+
+        match $bank {
+            Bank1 => read_reg!($periph, $instance, $reg1, $field),
+            Bank2 => read_reg!($periph, $instance, $reg2, $field),
+            Bank3 => read_reg!($periph, $instance, $reg3, $field),
+            Bank4 => read_reg!($periph, $instance, $reg4, $field),
+        }
+    }};
+}
+
+/// Program the bus turnaround of `target` together with the other enabled
+/// SRAM sub-banks of Bank 1
+///
+/// A memory that is slow to release the data bus after a read can collide
+/// with the next access to any other sub-bank, so all of them use the largest
+/// turnaround required by any of them. Sub-banks are never lowered, as the
+/// requirement of each memory is not recorded in the FMC.
+fn share_bus_turnaround(
+    regs: &FmcRegisters,
+    target: SramTargetBank,
+    cycles: u8,
+) {
+    use SramTargetBank::*;
+    let regs = regs.global();
+
+    // Sub-banks configured as SRAM. Sub-bank 1 is enabled as NOR Flash at
+    // reset, so it is only included once it has been configured
+    let others = [Bank1, Bank2, Bank3, Bank4]
+        .iter()
+        .copied()
+        .filter(|&bank| {
+            bank != target
+                && read_reg_banked!(
+                    fmc, regs, bank, BCR1, BCR2, BCR3, BCR4, MBKEN
+                ) != 0
+                && read_reg_banked!(
+                    fmc, regs, bank, BCR1, BCR2, BCR3, BCR4, MTYP
+                ) == fmc::BCR1::MTYP::RW::SRAM
+        });
+
+    let cycles = others.clone().fold(cycles as u32, |cycles, bank| {
+        let busturn =
+            read_reg_banked!(fmc, regs, bank, BTR1, BTR2, BTR3, BTR4, BUSTURN);
+        cmp::max(cycles, busturn)
+    });
+    fmc_trace!("Bus turnaround: {} cycles", cycles);
+
+    for bank in others.chain(Some(target)) {
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, regs, bank, BTR1, BTR2, BTR3, BTR4,
+                           BUSTURN: cycles);
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, regs, bank, BWTR1, BWTR2, BWTR3, BWTR4,
+                           BUSTURN: cycles);
+    }
+}
+
+// Declared after the banked register macros so that they are in scope
+mod lcd;
+pub use lcd::{Lcd, LcdChip, LcdConfiguration, LcdDevice, LcdTiming};
+
+impl<FMC: FmcPeripheral, PINS> Sram<FMC, PINS> {
+    /// New SRAM instance
+    ///
+    /// `pins` must be a set of pins connecting to an SRAM on the FMC
+    /// controller. The sub-bank is selected by the chip select pin in `pins`.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if there is a mismatch between the data lines in `PINS` and the
+    ///   width in `config`
+    pub fn new<BANK>(fmc: FMC, pins: PINS, config: SramConfiguration) -> Self
+    where
+        PINS: PinsSram<BANK>,
+        BANK: SramPinSet,
+    {
+        assert!(
+            PINS::DATA_WIDTH == config.memory_data_width,
+            "SRAM Data Bus Width mismatch between configuration and controller"
+        );
+
+        fmc_trace!("Sub-bank selected via pins: {:?}.", BANK::TARGET);
+
+        Sram {
+            target_bank: BANK::TARGET,
+            config,
+            fmc,
+            pins,
+            regs: FmcRegisters::new::<FMC>(),
+        }
+    }
+}
+
+impl<FMC: FmcPeripheral> Sram<FMC> {
+    /// New SRAM instance
+    ///
+    /// `bank` denotes which sub-bank of Bank 1 to target, 1 - 4.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if `bank` is given as an integer outside 1 - 4
+    ///
+    /// # Safety
+    ///
+    /// The pins are not checked against the requirements for the SRAM. So
+    /// you may be able to initialise a SRAM without a chip select or enough
+    /// data pins to access it.
+    pub fn new_unchecked(
+        fmc: FMC,
+        bank: impl Into<SramTargetBank>,
+        config: SramConfiguration,
+    ) -> Self {
+        Sram {
+            target_bank: bank.into(),
+            config,
+            fmc,
+            pins: (),
+            regs: FmcRegisters::new::<FMC>(),
+        }
+    }
+}
+
+impl<FMC: FmcPeripheral, PINS> Sram<FMC, PINS> {
+    /// Initialise SRAM instance
+    ///
+    /// Returns a raw pointer to the memory-mapped SRAM sub-bank
+    ///
+    /// The bus turnaround of this sub-bank and of any other SRAM or LCD
+    /// sub-bank already initialised is raised to the largest required by any
+    /// of them, as they share the data bus.
+    ///
+    /// # Panics
+    ///
+    /// * Panics if any setting in the configuration cannot be achieved
+    pub fn init(&mut self) -> *mut u32 {
+        fmc_trace!("SRAM on sub-bank {:?}", self.target_bank);
+
+        unsafe {
+            // Enable memory controller AHB register access. Skipped if
+            // another memory controller is running, as this may reset the FMC
+            if !self.regs.in_use() {
+                self.fmc.enable();
+            }
+
+            // Program device features and timing
+            self.set_features_timings(self.config);
+
+            // Enable memory controller
+            self.fmc.memory_controller_enable();
+        }
+
+        self.target_bank.ptr()
+    }
+
+    /// Disable the SRAM sub-bank, and release the FMC peripheral and pins
+    ///
+    /// The FMC can then be used to construct a different memory type. Any
+    /// pointers to the sub-bank must not be used after this, as accesses to a
+    /// disabled sub-bank cause a bus fault.
+    pub fn free(self) -> (FMC, PINS) {
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, self.regs.global(),
+                           self.target_bank, BCR1, BCR2, BCR3, BCR4,
+                           MBKEN: 0);
+
+        (self.fmc, self.pins)
+    }
+
+    /// Program memory device features and timings
+    ///
+    /// # Safety
+    ///
+    /// Some settings are common between all sub-banks. Calling this function
+    /// multiple times with different banks and different configurations is
+    /// unsafe.
+    unsafe fn set_features_timings(&mut self, config: SramConfiguration) {
+        assert!(
+            config.address_setup_cycles <= 15,
+            "Impossible configuration for FMC Controller"
+        );
+        assert!(
+            config.address_hold_cycles <= 15,
+            "Impossible configuration for FMC Controller"
+        );
+        assert!(
+            config.data_setup_cycles >= 1,
+            "Impossible configuration for FMC Controller"
+        );
+        assert!(
+            config.bus_turnaround_cycles <= 15,
+            "Impossible configuration for FMC Controller"
+        );
+
+        let data_width = match config.memory_data_width {
+            8 => 0,
+            16 => 1,
+            32 => 2,
+            _ => panic!("not possible"),
+        };
+
+        // BCRx
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, self.regs.global(),
+                           self.target_bank, BCR1, BCR2, BCR3, BCR4,
+                           WREN: config.write_enable as u32,
+                           EXTMOD: 0,  // access mode 1 for reads and writes
+                           WAITEN: 0,
+                           BURSTEN: 0,
+                           MWID: data_width,
+                           MTYP: 0,    // 0b00: SRAM
+                           MUXEN: 0,
+                           MBKEN: 1);
+
+        // BTRx
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, self.regs.global(),
+                           self.target_bank, BTR1, BTR2, BTR3, BTR4,
+                           ACCMOD: 0,
+                           DATAST: config.data_setup_cycles as u32,
+                           ADDHLD: cmp::max(config.address_hold_cycles, 1) as u32,
+                           ADDSET: config.address_setup_cycles as u32);
+
+        share_bus_turnaround(
+            &self.regs,
+            self.target_bank,
+            config.bus_turnaround_cycles,
+        );
+    }
+}
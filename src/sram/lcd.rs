@@ -0,0 +1,259 @@
+//! Parallel LCD controllers (Intel 8080 interface) on FMC Bank 1
+//!
+//! The display controller is accessed as an SRAM in extended mode, so that
+//! the read and write strobes can be timed separately. One address line is
+//! connected to the D/CX (register select) input of the display controller,
+//! and selects between the command and data registers.
+
+use core::cmp;
+use core::marker::PhantomData;
+use core::ptr;
+
+use super::{share_bus_turnaround, SramTargetBank};
+use crate::fmc::FmcRegisters;
+use crate::FmcPeripheral;
+
+use crate::ral::{fmc, modify_reg};
+
+/// FMC LCD Configuration
+///
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LcdConfiguration {
+    /// Data bus width in bits, 8 or 16
+    pub data_width: u8,
+    /// FMC address line connected to D/CX. This line is low for commands and
+    /// high for data. At most A25 for an 8-bit bus, or A24 for a 16-bit bus
+    pub dcx_address_line: u8,
+}
+
+/// FMC LCD Timing parameters
+///
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LcdTiming {
+    /// Write cycle time tWC
+    pub write_cycle_ns: i32,
+    /// Write strobe (nWE) low pulse width tWRL
+    pub write_strobe_ns: i32,
+    /// Read cycle time tRC
+    pub read_cycle_ns: i32,
+    /// Read strobe (nOE) low pulse width tRDL
+    pub read_strobe_ns: i32,
+    /// Time for the controller to release the data bus after a read, tRODH
+    pub bus_turnaround_ns: i32,
+}
+
+/// Respresents a model of display controller
+pub trait LcdChip {
+    /// LCD controller configuration
+    const CONFIG: LcdConfiguration;
+    /// Timing parameters
+    const TIMING: LcdTiming;
+}
+
+/// FMC Peripheral specialized as an LCD Controller. Not yet initialized.
+#[allow(missing_debug_implementations)]
+pub struct Lcd<FMC, IC> {
+    /// SRAM sub-bank
+    target_bank: SramTargetBank,
+    /// Parameters for the display controller
+    _chip: PhantomData<IC>,
+    /// FMC peripheral
+    fmc: FMC,
+    /// Register access
+    regs: FmcRegisters,
+}
+
+/// Command and data registers of an initialized display controller
+#[derive(Clone, Debug, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct LcdDevice {
+    command: *mut u8,
+    data: *mut u8,
+    data_width: u8,
+}
+
+impl<IC: LcdChip, FMC: FmcPeripheral> Lcd<FMC, IC> {
+    /// New LCD instance
+    ///
+    /// `bank` denotes which sub-bank of Bank 1 to target, 1 - 4.
+    ///
+    /// # Safety
+    ///
+    /// The pins are not checked against the requirements for the display
+    /// controller.
+    pub fn new_unchecked(
+        fmc: FMC,
+        bank: impl Into<SramTargetBank>,
+        _chip: IC,
+    ) -> Self {
+        Lcd {
+            target_bank: bank.into(),
+            _chip: PhantomData,
+            fmc,
+            regs: FmcRegisters::new::<FMC>(),
+        }
+    }
+
+    /// Initialise LCD instance
+    ///
+    /// Returns a [`LcdDevice`](LcdDevice) instance.
+    ///
+    /// The bus turnaround is shared with any other SRAM or LCD sub-bank, as
+    /// for [`Sram::init`](super::Sram::init).
+    ///
+    /// # Panics
+    ///
+    /// * Panics if any setting in `IC::CONFIG` cannot be achieved
+    /// * Panics if the FMC Kernel Clock is too fast to achieve the timing
+    ///   required by the display controller
+    pub fn init(&mut self) -> LcdDevice {
+        // calculate clock period, round down
+        let fmc_source_ck_hz = self.fmc.source_clock_hz();
+        let ker_clk_period_ns = 1_000_000_000u32 / fmc_source_ck_hz;
+
+        fmc_trace!("LCD on sub-bank {:?}", self.target_bank);
+
+        // enable memory controller AHB register access. Skipped if another
+        // memory controller is running, as this may reset the FMC
+        if !self.regs.in_use() {
+            self.fmc.enable();
+        }
+
+        // device features and timing
+        self.set_features_timings(IC::CONFIG, IC::TIMING, ker_clk_period_ns);
+
+        // enable memory controller
+        self.fmc.memory_controller_enable();
+
+        // HADDR is a byte address, so for a 16-bit bus address line n is
+        // driven by HADDR[n + 1]
+        let dcx_bit = match IC::CONFIG.data_width {
+            16 => IC::CONFIG.dcx_address_line + 1,
+            _ => IC::CONFIG.dcx_address_line,
+        };
+        let command = self.target_bank.ptr() as *mut u8;
+        LcdDevice {
+            command,
+            data: command.wrapping_add(1 << dcx_bit),
+            data_width: IC::CONFIG.data_width,
+        }
+    }
+
+    /// Program memory device features and timings
+    fn set_features_timings(
+        &mut self,
+        config: LcdConfiguration,
+        timing: LcdTiming,
+        period_ns: u32,
+    ) {
+        let period_ns = period_ns as i32;
+        let n_clock_periods = |time_ns: i32| {
+            (time_ns + period_ns - 1) / period_ns // round up
+        };
+        // (ADDSET, DATAST) for one direction
+        let phases = |cycle_ns: i32, strobe_ns: i32| {
+            let datast = cmp::max(n_clock_periods(strobe_ns), 1);
+            assert!(datast <= 255, "FMC ker clock too fast");
+
+            // the remainder of the cycle is spent in the address phase
+            let addset = cmp::max(n_clock_periods(cycle_ns) - datast, 0);
+            assert!(addset <= 15, "FMC ker clock too fast");
+
+            (addset as u32, datast as u32)
+        };
+
+        let data_width = match config.data_width {
+            8 => 0,
+            16 => 1,
+            _ => panic!("not possible"),
+        };
+        // the sub-bank spans HADDR[25:0], and a 16-bit bus drops HADDR[0]
+        assert!(
+            config.dcx_address_line as u32 <= 25 - data_width,
+            "Impossible configuration for FMC Controller"
+        );
+
+        // BCRx
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, self.regs.global(),
+                           self.target_bank, BCR1, BCR2, BCR3, BCR4,
+                           WREN: 1,
+                           EXTMOD: 1,  // separate read and write timings
+                           WAITEN: 0,
+                           BURSTEN: 0,
+                           MWID: data_width,
+                           MTYP: 0,    // 0b00: SRAM
+                           MUXEN: 0,
+                           MBKEN: 1);
+
+        // BTRx: Read timings
+        let (addset, datast) =
+            phases(timing.read_cycle_ns, timing.read_strobe_ns);
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, self.regs.global(),
+                           self.target_bank, BTR1, BTR2, BTR3, BTR4,
+                           ACCMOD: 0,  // mode A
+                           DATAST: datast,
+                           ADDSET: addset);
+
+        // BWTRx: Write timings
+        let (addset, datast) =
+            phases(timing.write_cycle_ns, timing.write_strobe_ns);
+        #[rustfmt::skip]
+        modify_reg_banked!(fmc, self.regs.global(),
+                           self.target_bank, BWTR1, BWTR2, BWTR3, BWTR4,
+                           ACCMOD: 0,  // mode A
+                           DATAST: datast,
+                           ADDSET: addset);
+
+        let busturn = cmp::max(n_clock_periods(timing.bus_turnaround_ns), 0);
+        assert!(busturn <= 15, "FMC ker clock too fast");
+        share_bus_turnaround(&self.regs, self.target_bank, busturn as u8);
+    }
+}
+
+/// Methods to allow users to access the display controller registers using
+/// `unsafe`.
+///
+impl LcdDevice {
+    /// Return a Raw Pointer to the command register (D/CX low)
+    pub fn command_ptr(&mut self) -> *mut u8 {
+        self.command
+    }
+    /// Return a Raw Pointer to the data register (D/CX high)
+    pub fn data_ptr(&mut self) -> *mut u8 {
+        self.data
+    }
+}
+
+impl LcdDevice {
+    /// Write a value to the register at `reg`, using an access as wide as the
+    /// data bus
+    fn write(&mut self, reg: *mut u8, value: u16) {
+        unsafe {
+            match self.data_width {
+                8 => ptr::write_volatile(reg, value as u8),
+                _ => ptr::write_volatile(reg as *mut u16, value),
+            }
+        }
+    }
+    /// Write a command
+    pub fn write_command(&mut self, command: u16) {
+        self.write(self.command, command)
+    }
+    /// Write a parameter or pixel data
+    pub fn write_data(&mut self, data: u16) {
+        self.write(self.data, data)
+    }
+    /// Read a parameter or pixel data
+    pub fn read_data(&mut self) -> u16 {
+        unsafe {
+            match self.data_width {
+                8 => ptr::read_volatile(self.data) as u16,
+                _ => ptr::read_volatile(self.data as *mut u16),
+            }
+        }
+    }
+}
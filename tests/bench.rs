@@ -0,0 +1,57 @@
+//! Tests the throughput benchmarks against RAM on the host
+#![cfg(feature = "bench")]
+
+use stm32_fmc::bench;
+
+#[test]
+/// Every benchmark transfers the whole memory
+fn bench_run_all() {
+    let mut memory = [0xA5A5_A5A5u32; 256];
+    let mut cycles = 0u32;
+    let counter = || {
+        cycles = cycles.wrapping_add(1000);
+        cycles
+    };
+
+    let report = bench::run_all(&mut memory, counter);
+
+    for throughput in [
+        report.sequential_read,
+        report.sequential_write,
+        report.random_read,
+        report.random_write,
+    ]
+    .iter()
+    {
+        assert_eq!(throughput.bytes, 1024);
+    }
+    assert_eq!(report.sequential_read.cycles, 1000);
+    assert_eq!(report.sequential_write.cycles, 1000);
+    // The same as the time to generate the addresses
+    assert_eq!(report.random_read.cycles, 0);
+    assert_eq!(report.random_write.cycles, 0);
+    assert!(memory.iter().all(|&word| word == 0));
+}
+
+#[test]
+/// The time to generate the addresses is subtracted from the random
+/// benchmarks
+fn bench_random_overhead() {
+    let memory = [0u32; 256];
+    let mut counts = [0, 300, 1000, 2000].iter().copied();
+    let mut counter = || counts.next().unwrap();
+
+    let throughput = bench::random_read(&memory, &mut counter);
+    assert_eq!(throughput.cycles, 700);
+}
+
+#[test]
+/// Throughput is scaled by the counter frequency
+fn bench_bytes_per_second() {
+    let throughput = bench::Throughput {
+        bytes: 4096,
+        cycles: 2048,
+    };
+
+    assert_eq!(throughput.bytes_per_second(100_000_000), 200_000_000);
+}
@@ -0,0 +1,112 @@
+#![allow(unused, clippy::new_without_default)]
+
+//! A fake FMC register block in host memory, so that register values written
+//! by the driver can be checked
+
+use core::cell::UnsafeCell;
+use core::ptr;
+
+use embedded_hal::delay::DelayNs;
+
+pub const BCR1: usize = 0x00;
+pub const BTR1: usize = 0x04;
+pub const BCR2: usize = 0x08;
+pub const BTR2: usize = 0x0C;
+pub const BCR3: usize = 0x10;
+pub const BTR3: usize = 0x14;
+pub const BCR4: usize = 0x18;
+pub const BTR4: usize = 0x1C;
+
+pub const PCR: usize = 0x80;
+pub const PMEM: usize = 0x88;
+pub const PATT: usize = 0x8C;
+
+pub const PCR4: usize = 0xA0;
+pub const PMEM4: usize = 0xA8;
+pub const PATT4: usize = 0xAC;
+pub const PIO4: usize = 0xB0;
+
+pub const BWTR1: usize = 0x104;
+pub const BWTR2: usize = 0x10C;
+pub const BWTR3: usize = 0x114;
+pub const BWTR4: usize = 0x11C;
+
+pub const SDCR1: usize = 0x140;
+pub const SDCR2: usize = 0x144;
+pub const SDTR1: usize = 0x148;
+pub const SDTR2: usize = 0x14C;
+pub const SDCMR: usize = 0x150;
+pub const SDRTR: usize = 0x154;
+pub const SDSR: usize = 0x158;
+
+/// Backing memory for the FMC register block
+pub struct RegisterFile(UnsafeCell<[u32; 88]>);
+unsafe impl Sync for RegisterFile {}
+impl RegisterFile {
+    pub const fn new() -> Self {
+        RegisterFile(UnsafeCell::new([0; 88]))
+    }
+}
+
+/// Read a register from the fake register block at `base`
+pub fn read(base: *const (), offset: usize) -> u32 {
+    unsafe { ptr::read_volatile((base as *const u8).add(offset) as *const u32) }
+}
+
+/// Write a register in the fake register block at `base`
+pub fn write(base: *const (), offset: usize, value: u32) {
+    unsafe {
+        ptr::write_volatile((base as *mut u8).add(offset) as *mut u32, value)
+    }
+}
+
+/// Define a FmcPeripheral implementation backed by its own fake register
+/// block, with the given source clock
+#[macro_export]
+macro_rules! fake_fmc {
+    ($Fmc:ident, $source_clock_hz:expr) => {
+        struct $Fmc;
+        unsafe impl stm32_fmc::FmcPeripheral for $Fmc {
+            const REGISTERS: *const () = {
+                static REGS: fake_fmc::RegisterFile =
+                    fake_fmc::RegisterFile::new();
+                &REGS as *const fake_fmc::RegisterFile as *const ()
+            };
+            fn enable(&mut self) {
+                $Fmc::enables()
+                    .fetch_add(1, core::sync::atomic::Ordering::SeqCst);
+            }
+            fn source_clock_hz(&self) -> u32 {
+                $source_clock_hz
+            }
+        }
+        #[allow(unused)]
+        impl $Fmc {
+            /// Number of calls to `enable`
+            fn enables() -> &'static core::sync::atomic::AtomicU32 {
+                static ENABLES: core::sync::atomic::AtomicU32 =
+                    core::sync::atomic::AtomicU32::new(0);
+                &ENABLES
+            }
+            fn reg(offset: usize) -> u32 {
+                fake_fmc::read(
+                    <$Fmc as stm32_fmc::FmcPeripheral>::REGISTERS,
+                    offset,
+                )
+            }
+            fn set_reg(offset: usize, value: u32) {
+                fake_fmc::write(
+                    <$Fmc as stm32_fmc::FmcPeripheral>::REGISTERS,
+                    offset,
+                    value,
+                )
+            }
+        }
+    };
+}
+
+/// Delay that returns immediately
+pub struct NoDelay;
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
@@ -0,0 +1,63 @@
+//! Tests the framebuffers against RAM on the host
+#![cfg(all(feature = "sdram", feature = "graphics"))]
+
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::Point;
+use embedded_graphics_core::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics_core::Pixel;
+use stm32_fmc::{DoubleBuffer, ExternalRam, Framebuffer};
+
+#[test]
+/// Pixels are stored row by row in the storage format of the color
+fn framebuffer_draw() {
+    let mut memory = [0u32; 8];
+    let ram = unsafe { ExternalRam::from_raw_parts(memory.as_mut_ptr(), 32) };
+    let mut framebuffer = Framebuffer::<Rgb565>::new(ram, 4, 4);
+
+    framebuffer
+        .draw_iter(
+            [
+                Pixel(Point::new(1, 0), Rgb565::RED),
+                Pixel(Point::new(0, 2), Rgb565::BLUE),
+                Pixel(Point::new(-1, 0), Rgb565::WHITE),
+                Pixel(Point::new(4, 0), Rgb565::WHITE),
+            ]
+            .iter()
+            .copied(),
+        )
+        .unwrap();
+    let _ = framebuffer.into_ram();
+
+    assert_eq!(memory[0], 0xF800 << 16);
+    assert_eq!(memory[4], 0x001F);
+    assert!(memory.iter().filter(|&&word| word != 0).count() == 2);
+}
+
+#[test]
+#[should_panic(expected = "External memory too short for framebuffer")]
+/// The memory must hold every pixel
+fn framebuffer_too_short() {
+    let mut memory = [0u32; 7];
+    let ram = unsafe { ExternalRam::from_raw_parts(memory.as_mut_ptr(), 28) };
+
+    let _ = Framebuffer::<Rgb565>::new(ram, 4, 4);
+}
+
+#[test]
+/// Drawing goes to the back buffer until the buffers are swapped
+fn framebuffer_double_buffer() {
+    let mut memory = [0u32; 64];
+    let ram = unsafe { ExternalRam::from_raw_parts(memory.as_mut_ptr(), 256) };
+    let mut buffers = DoubleBuffer::<Rgb565>::new(ram, 4, 4);
+
+    let front = buffers.front().as_ptr();
+    let back = buffers.back_mut().as_ptr();
+    assert_ne!(front, back);
+    assert_eq!(back as usize % 64, 0);
+
+    buffers.back_mut().clear(Rgb565::GREEN).unwrap();
+    buffers.swap();
+    assert_eq!(buffers.front().as_ptr(), back);
+    assert_eq!(unsafe { back.read() }, 0x07E0_07E0);
+    assert_eq!(unsafe { front.read() }, 0);
+}
@@ -0,0 +1,251 @@
+//! Tests the flash translation layer against a NAND Flash in host memory
+#![cfg(feature = "ftl")]
+
+use stm32_fmc::nand_ftl::{Ftl, FtlError, FtlFlash, METADATA_BYTES};
+use stm32_fmc::BadBlockTable;
+
+const PAGE_BYTES: usize = 16;
+const PAGES_PER_BLOCK: usize = 4;
+const BLOCKS: usize = 8;
+
+/// NAND Flash in host memory, where programming can only clear bits
+struct FakeFlash {
+    data: Vec<[u8; PAGE_BYTES]>,
+    metadata: Vec<[u8; METADATA_BYTES]>,
+    erases: [u32; BLOCKS],
+    /// Block that fails to erase
+    failing: Option<usize>,
+    /// Number of pages that can be programmed before a reset
+    program_budget: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Failed;
+
+impl FakeFlash {
+    fn new() -> Self {
+        FakeFlash {
+            data: vec![[0xFF; PAGE_BYTES]; BLOCKS * PAGES_PER_BLOCK],
+            metadata: vec![[0xFF; METADATA_BYTES]; BLOCKS * PAGES_PER_BLOCK],
+            erases: [0; BLOCKS],
+            failing: None,
+            program_budget: None,
+        }
+    }
+}
+
+impl FtlFlash for FakeFlash {
+    type Error = Failed;
+
+    fn read_page(
+        &mut self,
+        page: usize,
+        data: &mut [u8],
+    ) -> Result<(), Failed> {
+        data.copy_from_slice(&self.data[page]);
+        Ok(())
+    }
+
+    fn read_metadata(
+        &mut self,
+        page: usize,
+        metadata: &mut [u8],
+    ) -> Result<(), Failed> {
+        metadata.copy_from_slice(&self.metadata[page]);
+        Ok(())
+    }
+
+    fn program_page(
+        &mut self,
+        page: usize,
+        data: &[u8],
+        metadata: &[u8],
+    ) -> Result<(), Failed> {
+        if let Some(budget) = self.program_budget.as_mut() {
+            if *budget == 0 {
+                return Err(Failed);
+            }
+            *budget -= 1;
+        }
+        for (cell, x) in self.data[page].iter_mut().zip(data) {
+            *cell &= x;
+        }
+        for (cell, x) in self.metadata[page].iter_mut().zip(metadata) {
+            *cell &= x;
+        }
+        Ok(())
+    }
+
+    fn erase_block(&mut self, first_page: usize) -> Result<(), Failed> {
+        let block = first_page / PAGES_PER_BLOCK;
+        if self.failing == Some(block) {
+            return Err(Failed);
+        }
+        self.erases[block] += 1;
+        for page in first_page..first_page + PAGES_PER_BLOCK {
+            self.data[page] = [0xFF; PAGE_BYTES];
+            self.metadata[page] = [0xFF; METADATA_BYTES];
+        }
+        Ok(())
+    }
+}
+
+/// Storage for an FTL with 6 logical blocks
+struct Storage {
+    table: [u32; 1],
+    map: [u16; 6],
+    state: [u32; BLOCKS],
+    buffer: [u8; PAGE_BYTES],
+}
+
+impl Storage {
+    fn new() -> Self {
+        Storage {
+            table: [0; 1],
+            map: [0; 6],
+            state: [0; BLOCKS],
+            buffer: [0; PAGE_BYTES],
+        }
+    }
+
+    fn mount(&mut self, flash: FakeFlash) -> Ftl<'_, FakeFlash> {
+        let table =
+            BadBlockTable::new(&mut self.table, BLOCKS, PAGES_PER_BLOCK);
+        Ftl::mount(
+            flash,
+            table,
+            &mut self.map,
+            &mut self.state,
+            &mut self.buffer,
+        )
+        .unwrap()
+    }
+}
+
+fn page(value: u8) -> [u8; PAGE_BYTES] {
+    [value; PAGE_BYTES]
+}
+
+#[test]
+/// Pages can be rewritten in any order, and read back after remounting
+fn ftl_read_write() {
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(FakeFlash::new());
+    assert_eq!(ftl.pages(), 24);
+    assert_eq!(ftl.page_bytes(), PAGE_BYTES);
+
+    let mut data = [0; PAGE_BYTES];
+    ftl.read(5, &mut data).unwrap();
+    assert_eq!(data, page(0xFF));
+
+    ftl.write(5, &page(1)).unwrap();
+    ftl.write(4, &page(2)).unwrap();
+    ftl.write(5, &page(3)).unwrap();
+    ftl.write(23, &page(4)).unwrap();
+    ftl.read(5, &mut data).unwrap();
+    assert_eq!(data, page(3));
+    ftl.read(4, &mut data).unwrap();
+    assert_eq!(data, page(2));
+    ftl.read(6, &mut data).unwrap();
+    assert_eq!(data, page(0xFF));
+
+    assert_eq!(ftl.write(24, &page(0)), Err(FtlError::OutOfRange));
+    assert_eq!(ftl.read(24, &mut data), Err(FtlError::OutOfRange));
+
+    let flash = ftl.free();
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(flash);
+    for &(lba, value) in [(4, 2), (5, 3), (23, 4), (0, 0xFF)].iter() {
+        ftl.read(lba, &mut data).unwrap();
+        assert_eq!(data, page(value), "page {}", lba);
+    }
+}
+
+#[test]
+/// Writes are spread over the free blocks
+fn ftl_wear_leveling() {
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(FakeFlash::new());
+    for i in 0..80 {
+        ftl.write(0, &page(i as u8)).unwrap();
+    }
+    let flash = ftl.free();
+    assert!(flash.erases.iter().all(|&n| n == 10), "{:?}", flash.erases);
+}
+
+#[test]
+/// A block that fails to erase is marked bad and another is used
+fn ftl_bad_block() {
+    let mut flash = FakeFlash::new();
+    flash.failing = Some(0);
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(flash);
+
+    ftl.write(0, &page(7)).unwrap();
+    assert!(ftl.bad_blocks().is_bad(0));
+    let mut data = [0; PAGE_BYTES];
+    ftl.read(0, &mut data).unwrap();
+    assert_eq!(data, page(7));
+
+    // One good block left after the 6 logical blocks are used
+    for lba in (4..24).step_by(PAGES_PER_BLOCK) {
+        ftl.write(lba, &page(1)).unwrap();
+    }
+    ftl.write(0, &page(8)).unwrap();
+}
+
+#[test]
+/// A copy interrupted before the last page leaves the previous contents
+fn ftl_interrupted_write() {
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(FakeFlash::new());
+    ftl.write(1, &page(1)).unwrap();
+
+    let mut flash = ftl.free();
+    flash.program_budget = Some(PAGES_PER_BLOCK - 1);
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(flash);
+    // Every block fails to program, until there are none left
+    assert_eq!(ftl.write(1, &page(2)), Err(FtlError::NoFreeBlocks));
+
+    let mut flash = ftl.free();
+    flash.program_budget = None;
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(flash);
+    let mut data = [0; PAGE_BYTES];
+    ftl.read(1, &mut data).unwrap();
+    assert_eq!(data, page(1));
+    ftl.write(1, &page(3)).unwrap();
+    ftl.read(1, &mut data).unwrap();
+    assert_eq!(data, page(3));
+}
+
+#[test]
+/// A header with a corrupted copy is still found, and a block with both
+/// copies corrupted is ignored
+fn ftl_corrupted_header() {
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(FakeFlash::new());
+    ftl.write(1, &page(1)).unwrap();
+
+    let mut flash = ftl.free();
+    let last = flash
+        .metadata
+        .iter()
+        .position(|m| m.iter().any(|&x| x != 0xFF))
+        .unwrap();
+    flash.metadata[last][4] ^= 0x01; // version, first copy
+
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(flash);
+    let mut data = [0; PAGE_BYTES];
+    ftl.read(1, &mut data).unwrap();
+    assert_eq!(data, page(1));
+
+    let mut flash = ftl.free();
+    flash.metadata[last][METADATA_BYTES / 2 + 2] ^= 0x01; // logical, copy 2
+    let mut storage = Storage::new();
+    let mut ftl = storage.mount(flash);
+    ftl.read(1, &mut data).unwrap();
+    assert_eq!(data, page(0xFF));
+}
@@ -0,0 +1,106 @@
+//! Tests LCD register programming against a fake register block
+#![cfg(feature = "sram")]
+
+mod fake_fmc;
+use fake_fmc::*;
+
+use stm32_fmc::*;
+
+/// ILI9341-like controller on a 16-bit bus, D/CX on A16
+struct Display;
+impl LcdChip for Display {
+    const CONFIG: LcdConfiguration = LcdConfiguration {
+        data_width: 16,
+        dcx_address_line: 16,
+    };
+    const TIMING: LcdTiming = LcdTiming {
+        write_cycle_ns: 66,
+        write_strobe_ns: 15,
+        read_cycle_ns: 160,
+        read_strobe_ns: 45,
+        bus_turnaround_ns: 0,
+    };
+}
+
+#[test]
+/// Read and write timings are converted to cycles of the 100MHz kernel clock
+fn lcd_init() {
+    fake_fmc!(Fmc, 100_000_000);
+    let mut lcd = Lcd::new_unchecked(Fmc, 3, Display);
+
+    let mut device = lcd.init();
+    assert_eq!(device.command_ptr() as usize, 0x6800_0000);
+    assert_eq!(device.data_ptr() as usize, 0x6802_0000);
+
+    // WREN, EXTMOD, MWID = 16 bits, MTYP = SRAM, MBKEN
+    assert_eq!(Fmc::reg(BCR3), (1 << 14) | (1 << 12) | (1 << 4) | 1);
+    // DATAST, ADDSET
+    assert_eq!(Fmc::reg(BTR3), 0x0000_050B);
+    assert_eq!(Fmc::reg(BWTR3), 0x0000_0205);
+}
+
+#[test]
+/// The FMC is not enabled again while another memory controller is running
+fn lcd_init_fmc_running() {
+    use core::sync::atomic::Ordering;
+    fake_fmc!(Fmc, 100_000_000);
+
+    // SDRAM controller running, SDCLK = 2
+    Fmc::set_reg(SDCR1, 2 << 10);
+    let mut lcd = Lcd::new_unchecked(Fmc, 3, Display);
+    let _ = lcd.init();
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 0);
+}
+
+#[test]
+/// The LCD shares the largest bus turnaround with SRAM on Bank 1
+fn lcd_share_bus_turnaround() {
+    struct SlowDisplay;
+    impl LcdChip for SlowDisplay {
+        const CONFIG: LcdConfiguration = Display::CONFIG;
+        const TIMING: LcdTiming = LcdTiming {
+            bus_turnaround_ns: 45,
+            ..Display::TIMING
+        };
+    }
+    fake_fmc!(Fmc, 100_000_000);
+
+    let mut lcd = Lcd::new_unchecked(Fmc, 3, SlowDisplay);
+    let _ = lcd.init();
+    assert_eq!((Fmc::reg(BTR3) >> 16) & 0xF, 5); // BUSTURN
+    assert_eq!((Fmc::reg(BWTR3) >> 16) & 0xF, 5);
+
+    let mut sram = Sram::new_unchecked(
+        Fmc,
+        2,
+        SramConfiguration {
+            memory_data_width: 16,
+            address_setup_cycles: 1,
+            address_hold_cycles: 1,
+            data_setup_cycles: 2,
+            bus_turnaround_cycles: 2,
+            write_enable: true,
+        },
+    );
+    let _ = sram.init();
+    assert_eq!((Fmc::reg(BTR2) >> 16) & 0xF, 5);
+    assert_eq!((Fmc::reg(BTR3) >> 16) & 0xF, 5);
+}
+
+#[test]
+#[should_panic(expected = "Impossible configuration for FMC Controller")]
+/// A25 is outside the sub-bank when driven by HADDR[26] on a 16-bit bus
+fn lcd_dcx_out_of_range() {
+    struct WideDisplay;
+    impl LcdChip for WideDisplay {
+        const CONFIG: LcdConfiguration = LcdConfiguration {
+            data_width: 16,
+            dcx_address_line: 25,
+        };
+        const TIMING: LcdTiming = Display::TIMING;
+    }
+    fake_fmc!(Fmc, 100_000_000);
+
+    let mut lcd = Lcd::new_unchecked(Fmc, 3, WideDisplay);
+    let _ = lcd.init();
+}
@@ -0,0 +1,26 @@
+//! Tests the memory tests against RAM on the host
+#![cfg(feature = "memtest")]
+
+use stm32_fmc::memtest;
+
+#[test]
+/// Working memory passes every test
+fn memtest_pass() {
+    let mut memory = [0xA5A5_A5A5u32; 256];
+
+    assert_eq!(memtest::run_all(&mut memory), Ok(()));
+    assert!(memory.iter().all(|&word| word == 0));
+}
+
+#[test]
+/// The failing address and bits are reported
+fn memtest_error() {
+    let memory = [0u32; 4];
+    let error = memtest::MemtestError {
+        address: &memory[2] as *const u32 as usize,
+        expected: 0xFFFF_FFFF,
+        actual: 0xFFFF_FFF7,
+    };
+
+    assert_eq!(error.failing_bits(), 1 << 3);
+}
@@ -0,0 +1,204 @@
+//! Tests NAND error correction, bad block management and timing
+#![cfg(feature = "nand")]
+
+mod fake_fmc;
+use fake_fmc::*;
+
+use stm32_fmc::devices::s34ml08g3_4kb::S34ml08g3;
+use stm32_fmc::nand_ecc::{correct_hamming, EccError};
+use stm32_fmc::{BadBlockTable, Nand, NandTiming};
+
+/// Syndrome of a single bit error at `position` bits into a sector with
+/// `address_bits` bits of bit offset
+fn single_bit_syndrome(position: usize, address_bits: u32) -> u32 {
+    (0..address_bits)
+        .map(|i| if position >> i & 1 == 1 { 0b10 } else { 0b01 } << (2 * i))
+        .sum()
+}
+
+#[test]
+/// A single bit error in the data is corrected
+fn nand_hamming_single_bit() {
+    let original = [0x5Au8; 512];
+    for &position in [0, 7, 8, 1234, 4095].iter() {
+        let mut sector = original;
+        sector[position / 8] ^= 1 << (position % 8);
+
+        let computed = 0x00AB_CDEF;
+        let stored = computed ^ single_bit_syndrome(position, 12);
+        assert_eq!(correct_hamming(&mut sector, stored, computed), Ok(1));
+        assert_eq!(sector[..], original[..]);
+    }
+
+    // 8192 byte sectors use all 32 bits of the code
+    let mut sector = [0u8; 8192];
+    sector[8191] = 0x80;
+    let stored = single_bit_syndrome(8192 * 8 - 1, 16);
+    assert_eq!(correct_hamming(&mut sector, stored, 0), Ok(1));
+    assert!(sector.iter().all(|&b| b == 0));
+}
+
+#[test]
+/// Matching codes and errors in the stored code leave the data unchanged
+fn nand_hamming_no_data_error() {
+    let mut sector = [0x5Au8; 512];
+    assert_eq!(correct_hamming(&mut sector, 0x12_3456, 0x12_3456), Ok(0));
+    assert_eq!(correct_hamming(&mut sector, 0x12_3456, 0x12_3416), Ok(0));
+    // Bits above the code for this sector size are ignored
+    assert_eq!(correct_hamming(&mut sector, 0xFF12_3456, 0x12_3456), Ok(0));
+    assert!(sector.iter().all(|&b| b == 0x5A));
+}
+
+#[test]
+/// Multiple bit errors are detected but not corrected
+fn nand_hamming_uncorrectable() {
+    let mut sector = [0x5Au8; 256];
+    let syndrome = single_bit_syndrome(3, 11) ^ single_bit_syndrome(100, 11);
+    assert_eq!(
+        correct_hamming(&mut sector, syndrome, 0),
+        Err(EccError::Uncorrectable)
+    );
+    assert!(sector.iter().all(|&b| b == 0x5A));
+}
+
+#[test]
+/// Blocks marked bad are recorded in a bitmap
+fn nand_bad_block_table() {
+    assert_eq!(BadBlockTable::storage_words(4096), 128);
+    assert_eq!(BadBlockTable::storage_words(33), 2);
+
+    let mut storage = [0xFFFF_FFFFu32; 3];
+    let mut table = BadBlockTable::new(&mut storage, 40, 64);
+    assert_eq!(table.blocks(), 40);
+    assert_eq!(table.pages_per_block(), 64);
+    assert_eq!(table.bad_count(), 0);
+    assert_eq!(table.as_words(), &[0, 0]);
+
+    table.mark_bad(0);
+    table.mark_bad(33);
+    table.mark_bad(33);
+    assert!(table.is_bad(0) && table.is_bad(33));
+    assert!(!table.is_bad(1) && !table.is_bad(39));
+    assert_eq!(table.bad_count(), 2);
+    assert_eq!(table.as_words(), &[1, 2]);
+    assert_eq!(table.good_blocks().take(2).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(table.good_blocks().count(), 38);
+}
+
+#[test]
+#[should_panic(expected = "Block out of range")]
+/// Blocks beyond the device are rejected
+fn nand_bad_block_out_of_range() {
+    let mut storage = [0u32; 2];
+    let table = BadBlockTable::new(&mut storage, 40, 64);
+    let _ = table.is_bad(40);
+}
+
+#[test]
+/// The FMC timing can be changed after a faster timing mode is selected
+fn nand_set_timing() {
+    fake_fmc!(Fmc, 200_000_000);
+    let mut nand = unsafe { Nand::new_unchecked(Fmc, S34ml08g3 {}) };
+
+    // ONFI timing mode 0
+    nand.set_timing(NandTiming {
+        nce_setup_time: 70,
+        data_setup_time: 40,
+        ale_hold_time: 20,
+        cle_hold_time: 20,
+        ale_to_nre_delay: 25,
+        cle_to_nre_delay: 20,
+        nre_pulse_width_ns: 50,
+        nwe_pulse_width_ns: 50,
+        read_cycle_time_ns: 100,
+        write_cycle_time_ns: 100,
+        nwe_high_to_busy_ns: 200,
+    });
+
+    assert_eq!(Fmc::reg(PMEM), 0x10_06_09_03); // HIZ, HOLD, WAIT, SET
+    assert_eq!(Fmc::reg(PATT), 0x10_27_09_03);
+    assert_eq!(Fmc::reg(PCR) & 0b1110, 0b1110); // PTYP, PBKEN, PWAITEN
+}
+
+#[cfg(feature = "bch")]
+mod bch {
+    use stm32_fmc::nand_ecc::{Bch, EccError};
+
+    /// A sector of pseudo-random data
+    fn sector(len: usize) -> Vec<u8> {
+        let mut x = 0x1234_5678u32;
+        (0..len)
+            .map(|_| {
+                x = x.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (x >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    /// The code length is 13 bits for each correctable bit
+    fn nand_bch_ecc_bytes() {
+        assert_eq!(Bch::new(512, 4).ecc_bytes(), 7);
+        assert_eq!(Bch::new(512, 8).ecc_bytes(), 13);
+        assert_eq!(Bch::new(1000, 1).ecc_bytes(), 2);
+    }
+
+    #[test]
+    /// Up to t bit errors, in the data or the code, are corrected
+    fn nand_bch_correct() {
+        for &t in [1, 4, 8].iter() {
+            let bch = Bch::new(512, t);
+            let original = sector(512);
+            let mut ecc = vec![0; bch.ecc_bytes()];
+            bch.encode(&original, &mut ecc);
+
+            let mut data = original.clone();
+            assert_eq!(bch.correct(&mut data, &ecc), Ok(0));
+
+            for errors in 1..=t {
+                let mut data = original.clone();
+                for e in 0..errors {
+                    let bit = (e * 1237 + 5) % 4096;
+                    data[bit / 8] ^= 0x80 >> (bit % 8);
+                }
+                assert_eq!(bch.correct(&mut data, &ecc), Ok(errors));
+                assert_eq!(data, original);
+            }
+
+            // One error in the code and t - 1 in the data
+            let mut data = original.clone();
+            let mut bad_ecc = ecc.clone();
+            bad_ecc[0] ^= 0x40;
+            for e in 0..t - 1 {
+                data[e * 31] ^= 0x01;
+            }
+            assert_eq!(bch.correct(&mut data, &bad_ecc), Ok(t - 1));
+            assert_eq!(data, original);
+        }
+    }
+
+    #[test]
+    /// More than t bit errors are reported and the data is left unchanged
+    fn nand_bch_uncorrectable() {
+        let bch = Bch::new(512, 4);
+        let original = sector(512);
+        let mut ecc = vec![0; bch.ecc_bytes()];
+        bch.encode(&original, &mut ecc);
+
+        let mut data = original.clone();
+        for byte in [3, 90, 200, 301, 511].iter() {
+            data[*byte] ^= 0x10;
+        }
+        let corrupted = data.clone();
+        assert_eq!(bch.correct(&mut data, &ecc), Err(EccError::Uncorrectable));
+        assert_eq!(data, corrupted);
+    }
+
+    #[test]
+    /// An erased sector is not an error
+    fn nand_bch_erased() {
+        let bch = Bch::new(512, 8);
+        let mut data = vec![0xFF; 512];
+        assert_eq!(bch.correct(&mut data, &[0xFF; 13]), Ok(0));
+    }
+}
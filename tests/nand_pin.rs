@@ -0,0 +1,76 @@
+//! Tests NAND pin constraints apply correctly
+#![cfg(feature = "nand")]
+
+mod dummy_pins;
+use dummy_pins::*;
+
+use stm32_fmc::*;
+
+/// Dummy FmcPeripheral implementation for testing
+struct DummyFMC;
+unsafe impl FmcPeripheral for DummyFMC {
+    const REGISTERS: *const () = core::ptr::null();
+    fn enable(&mut self) {}
+    fn source_clock_hz(&self) -> u32 {
+        100_000_000
+    }
+}
+
+macro_rules! fmc_pin_set {
+    ($($p:ident),*) => {
+        paste::item! {
+            (
+                $(
+                    [< PinThats $p:upper>] {}
+                ),*
+            )
+        }
+    }
+}
+
+const TIMING: NandTiming = NandTiming {
+    nce_setup_time: 15,
+    data_setup_time: 7,
+    ale_hold_time: 5,
+    cle_hold_time: 5,
+    ale_to_nre_delay: 10,
+    cle_to_nre_delay: 10,
+    nre_pulse_width_ns: 10,
+    nwe_pulse_width_ns: 10,
+    read_cycle_time_ns: 20,
+    write_cycle_time_ns: 20,
+    nwe_high_to_busy_ns: 100,
+};
+
+/// 16-bit NAND with 2048 byte pages
+struct Nand16;
+impl NandChip for Nand16 {
+    const CONFIG: NandConfiguration = NandConfiguration {
+        data_width: 16,
+        column_bits: 11,
+        ecc_sector_bytes: 512,
+    };
+    const TIMING: NandTiming = TIMING;
+}
+
+#[test]
+/// NAND with 16 bit data
+fn nand_pins_16bit() {
+    let pins = fmc_pin_set!(
+        A17, A16, D0, D1, D2, D3, D4, D5, D6, D7, D8, D9, D10, D11, D12, D13,
+        D14, D15, NCE, NOE, NWE, NWAIT
+    );
+
+    let _nand = Nand::new(DummyFMC, pins, Nand16);
+}
+
+#[test]
+#[should_panic]
+/// NAND configured for 16 bit data, but only 8 data pins
+fn nand_pins_data_width_mismatch() {
+    let pins = fmc_pin_set!(
+        A17, A16, D0, D1, D2, D3, D4, D5, D6, D7, NCE, NOE, NWE, NWAIT
+    );
+
+    let _nand = Nand::new(DummyFMC, pins, Nand16);
+}
@@ -0,0 +1,101 @@
+//! Tests the range and alignment checks of the `embedded-storage` traits for
+//! NAND Flash
+#![cfg(feature = "embedded-storage")]
+
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash,
+    NorFlashErrorKind, ReadNorFlash,
+};
+use stm32_fmc::NandStorage;
+
+type Storage = NandStorage<2048, 64>;
+const BLOCKS: usize = 4;
+
+/// Storage with the sizes of `Storage`, without a device
+struct Sizes;
+
+impl ErrorType for Sizes {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for Sizes {
+    const READ_SIZE: usize = <Storage as ReadNorFlash>::READ_SIZE;
+
+    fn read(&mut self, _: u32, _: &mut [u8]) -> Result<(), Self::Error> {
+        unreachable!()
+    }
+
+    fn capacity(&self) -> usize {
+        Storage::capacity_for(BLOCKS).unwrap()
+    }
+}
+
+impl NorFlash for Sizes {
+    const WRITE_SIZE: usize = <Storage as NorFlash>::WRITE_SIZE;
+    const ERASE_SIZE: usize = <Storage as NorFlash>::ERASE_SIZE;
+
+    fn erase(&mut self, _: u32, _: u32) -> Result<(), Self::Error> {
+        unreachable!()
+    }
+
+    fn write(&mut self, _: u32, _: &[u8]) -> Result<(), Self::Error> {
+        unreachable!()
+    }
+}
+
+#[test]
+/// The capacity is checked against the u32 offsets without overflowing
+fn nand_storage_capacity() {
+    assert_eq!(Storage::capacity_for(BLOCKS), Some(4 * 64 * 2048));
+    assert_eq!(
+        NandStorage::<4096, 64>::capacity_for(16383),
+        Some(16383 * 64 * 4096)
+    );
+    assert_eq!(NandStorage::<4096, 64>::capacity_for(16384), None);
+    assert_eq!(Storage::capacity_for(usize::MAX), None);
+}
+
+#[test]
+/// Reads may start and end at any byte within the capacity
+fn nand_storage_check_read() {
+    let capacity = Sizes.capacity() as u32;
+    assert_eq!(check_read(&Sizes, 1, 3), Ok(()));
+    assert_eq!(check_read(&Sizes, capacity - 1, 1), Ok(()));
+    assert_eq!(
+        check_read(&Sizes, capacity - 1, 2),
+        Err(NorFlashErrorKind::OutOfBounds)
+    );
+}
+
+#[test]
+/// Writes are whole pages
+fn nand_storage_check_write() {
+    assert_eq!(check_write(&Sizes, 2048, 4096), Ok(()));
+    assert_eq!(
+        check_write(&Sizes, 1, 2048),
+        Err(NorFlashErrorKind::NotAligned)
+    );
+    assert_eq!(
+        check_write(&Sizes, 0, 2047),
+        Err(NorFlashErrorKind::NotAligned)
+    );
+    assert_eq!(
+        check_write(&Sizes, Sizes.capacity() as u32, 2048),
+        Err(NorFlashErrorKind::OutOfBounds)
+    );
+}
+
+#[test]
+/// Erases are whole blocks
+fn nand_storage_check_erase() {
+    let block = 2048 * 64;
+    assert_eq!(check_erase(&Sizes, block, 3 * block), Ok(()));
+    assert_eq!(
+        check_erase(&Sizes, 2048, block),
+        Err(NorFlashErrorKind::NotAligned)
+    );
+    assert_eq!(
+        check_erase(&Sizes, 0, 5 * block),
+        Err(NorFlashErrorKind::OutOfBounds)
+    );
+}
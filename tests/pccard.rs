@@ -0,0 +1,67 @@
+//! Tests PC Card register programming against a fake register block
+#![cfg(feature = "pccard")]
+
+mod fake_fmc;
+use fake_fmc::*;
+
+use stm32_fmc::*;
+
+struct CompactFlash;
+impl PccardChip for CompactFlash {
+    const CONFIG: PccardConfiguration = PccardConfiguration {
+        data_width: 16,
+        wait_feature: true,
+    };
+    const TIMING: PccardTiming = PccardTiming {
+        common: PccardSpaceTiming {
+            setup_ns: 30,
+            command_ns: 150,
+            hold_ns: 20,
+            hiz_ns: 0,
+        },
+        attribute: PccardSpaceTiming {
+            setup_ns: 30,
+            command_ns: 300,
+            hold_ns: 20,
+            hiz_ns: 0,
+        },
+        io: PccardSpaceTiming {
+            setup_ns: 70,
+            command_ns: 165,
+            hold_ns: 20,
+            hiz_ns: 30,
+        },
+    };
+}
+
+#[test]
+/// Timings are converted to cycles of the 100MHz kernel clock
+fn pccard_init() {
+    fake_fmc!(Fmc, 100_000_000);
+    let mut pccard = unsafe { Pccard::new_unchecked(Fmc, CompactFlash) };
+
+    let mut device = pccard.init(&mut NoDelay);
+    assert_eq!(device.common_memory() as usize, 0x9000_0000);
+    assert_eq!(device.attribute_memory() as usize, 0x9800_0000);
+    assert_eq!(device.io_space() as usize, 0x9C00_0000);
+
+    // PBKEN, PWID = 16 bits, PTYP = PC Card, PWAITEN
+    assert_eq!(Fmc::reg(PCR4), 0b01_0110);
+    // HIZ, HOLD, WAIT, SET
+    assert_eq!(Fmc::reg(PMEM4), 0x00_02_0E_02);
+    assert_eq!(Fmc::reg(PATT4), 0x00_02_1D_02);
+    assert_eq!(Fmc::reg(PIO4), 0x03_02_10_06);
+}
+
+#[test]
+/// The FMC is not enabled again while another memory controller is running
+fn pccard_init_fmc_running() {
+    use core::sync::atomic::Ordering;
+    fake_fmc!(Fmc, 100_000_000);
+
+    // SRAM on sub-bank 2
+    Fmc::set_reg(BCR2, 1);
+    let mut pccard = unsafe { Pccard::new_unchecked(Fmc, CompactFlash) };
+    let _ = pccard.init(&mut NoDelay);
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,1195 @@
+//! Tests SDRAM register programming against a fake register block
+#![cfg(feature = "sdram")]
+
+mod fake_fmc;
+use fake_fmc::*;
+
+use stm32_fmc::*;
+
+type Chip = devices::is42s32800g_6::Is42s32800g;
+
+/// The error from a failed initialisation, if any
+fn init_error<T, U>(
+    result: Result<T, (U, SdramInitError)>,
+) -> Option<SdramInitError> {
+    result.err().map(|(_, e)| e)
+}
+
+#[test]
+/// Auto-refresh bursts are encoded as NRFS = count - 1
+fn sdram_auto_refresh() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    sdram.auto_refresh(4);
+
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!(sdcmr & 0b111, 0b011); // MODE: Auto-refresh
+    assert_eq!((sdcmr >> 4) & 1, 1); // CTB1
+    assert_eq!((sdcmr >> 3) & 1, 0); // CTB2
+    assert_eq!((sdcmr >> 5) & 0xF, 3); // NRFS
+}
+
+#[test]
+#[should_panic]
+/// The FMC cannot issue more than 15 auto-refresh commands at once
+fn sdram_auto_refresh_too_many() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    sdram.auto_refresh(16);
+}
+
+#[test]
+/// SDRAM initialisation with the nominal timing of the chip
+fn sdram_init() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+
+    let sdram = sdram.init(&mut NoDelay);
+    assert_eq!(sdram.as_ptr() as usize, 0xC000_0000);
+
+    let sdcr1 = Fmc::reg(SDCR1);
+    assert_eq!((sdcr1 >> 10) & 0b11, 2); // SDCLK: 100MHz
+    assert_eq!((sdcr1 >> 13) & 0b11, 0); // RPIPE
+}
+
+#[test]
+/// The bundled SDRAM devices pass the checks in `try_init`
+fn sdram_devices() {
+    macro_rules! check {
+        ($($chip:expr),+ $(,)?) => {$({
+            fake_fmc!(Fmc, 200_000_000);
+            let sdram = Sdram::new_unchecked(Fmc, 1, $chip);
+            let ptr = sdram.try_init(&mut NoDelay).map(|s| s.as_ptr() as usize);
+            assert_eq!(ptr.ok(), Some(0xC000_0000));
+        })+};
+    }
+
+    check!(
+        devices::w9825g6kh_6::W9825g6kh {},
+        devices::mt48lc16m16a2_6a::Mt48lc16m16a2 {},
+        devices::mt48lc16m16a2_75::Mt48lc16m16a2 {},
+        devices::is42s16320f_6::Is42s16320f {},
+        devices::w9864g6jt_6::W9864g6jt {},
+        devices::as4c8m16sa_6::As4c8m16sa {},
+        devices::mt48lc8m16a2_6a::Mt48lc8m16a2 {},
+        devices::mt48lc8m16a2_75::Mt48lc8m16a2 {},
+        devices::is42s16160j_6::Is42s16160j {},
+        devices::is42s16160j_7::Is42s16160j {},
+    );
+}
+
+#[test]
+/// The smallest geometry supported by the FMC, 12 row and 8 column bits
+fn sdram_small_geometry() {
+    use devices::w9864g6jt_6::W9864g6jt;
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, W9864g6jt {});
+    let _ = sdram.init(&mut NoDelay);
+
+    let sdcr1 = Fmc::reg(SDCR1);
+    assert_eq!(sdcr1 & 0b11, 0); // NC: 8 bits
+    assert_eq!((sdcr1 >> 2) & 0b11, 1); // NR: 12 bits
+    assert_eq!((sdcr1 >> 4) & 0b11, 1); // MWID: 16 bits
+    assert_eq!(W9864g6jt::SIZE_BYTES, 8 * 1024 * 1024);
+}
+
+#[test]
+#[should_panic(expected = "tRC must be 1 - 16 cycles")]
+/// Chip definitions are checked by a const fn
+fn sdram_check_chip() {
+    struct SlowChip;
+    impl SdramChip for SlowChip {
+        const MODE_REGISTER: u16 = Chip::MODE_REGISTER;
+        const CONFIG: SdramConfiguration = Chip::CONFIG;
+        const TIMING: SdramTiming = SdramTiming {
+            row_cycle: 17,
+            ..Chip::TIMING
+        };
+    }
+
+    check_sdram_chip::<Chip>();
+    check_sdram_chip::<SlowChip>();
+}
+
+#[test]
+/// Non-blocking initialisation completes after the powerup delay
+fn sdram_init_poll() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+
+    let init = SdramInit::start(sdram).unwrap();
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b001); // MODE: Clock enable
+    assert_eq!(init.remaining_us(), 100);
+
+    let init = init.poll(60).unwrap_err();
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b001); // Still waiting
+    assert_eq!(init.remaining_us(), 40);
+
+    let sdram = init.poll(60).unwrap();
+    assert_eq!(sdram.as_ptr() as usize, 0xC000_0000);
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b100); // MODE: Load mode register
+    assert_ne!(Fmc::reg(SDRTR), 0);
+}
+
+#[cfg(feature = "async")]
+#[test]
+/// Asynchronous initialisation awaits the powerup delay
+fn sdram_init_async() {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    struct AsyncDelay(u32);
+    impl embedded_hal_async::delay::DelayNs for AsyncDelay {
+        async fn delay_ns(&mut self, ns: u32) {
+            self.0 += ns;
+        }
+    }
+
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut delay = AsyncDelay(0);
+
+    let mut init = Box::pin(sdram.init_async(&mut delay));
+    let mut cx = Context::from_waker(Waker::noop());
+    match init.as_mut().poll(&mut cx) {
+        Poll::Ready(sdram) => assert_eq!(sdram.as_ptr() as usize, 0xC000_0000),
+        Poll::Pending => panic!("Delay is always ready"),
+    }
+    drop(init);
+
+    assert_eq!(delay.0, 100_000);
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b100); // MODE: Load mode register
+}
+
+#[test]
+/// An initialised SDRAM provides a handle covering the whole memory
+fn sdram_external_ram() {
+    fake_fmc!(Fmc, 200_000_000);
+    let mut sdram = Sdram::new_unchecked(Fmc, 1, Chip {}).init(&mut NoDelay);
+
+    let ram = unsafe { sdram.external_ram() };
+    assert_eq!(ram.as_ptr() as usize, 0xC000_0000);
+    assert_eq!(ram.len(), Chip::SIZE_BYTES);
+}
+
+#[test]
+/// Accesses through an external memory handle are bounds checked
+fn external_ram_access() {
+    let mut memory = [0u32; 4];
+    let mut ram =
+        unsafe { ExternalRam::from_raw_parts(memory.as_mut_ptr(), 16) };
+
+    ram.write_volatile(0, 0x1234_5678u32);
+    ram.write_volatile(6, 0xABCDu16);
+    ram.write_volatile(15, 0xEFu8);
+    assert_eq!(ram.read_volatile::<u8>(0), 0x78);
+    assert_eq!(ram.read_volatile::<u16>(6), 0xABCD);
+    assert_eq!(ram.read_volatile::<u64>(8) >> 56, 0xEF);
+
+    let words = unsafe { ram.as_mut_slice::<u32>() };
+    assert_eq!(words.len(), 4);
+    assert_eq!(words[0], 0x1234_5678);
+    words[2] = 0;
+
+    assert!(std::panic::catch_unwind(|| ram.read_volatile::<u32>(14)).is_err());
+    assert!(std::panic::catch_unwind(|| ram.read_volatile::<u32>(2)).is_err());
+    assert_eq!(memory[2], 0);
+}
+
+#[test]
+#[cfg(feature = "embedded-dma")]
+/// Split external memory used as DMA buffers
+fn external_ram_dma_buffer() {
+    use embedded_dma::{ReadBuffer, WriteBuffer};
+
+    let mut memory = [0u32; 4];
+    let ram = unsafe { ExternalRam::from_raw_parts(memory.as_mut_ptr(), 16) };
+    let (lower, upper) = ram.split_at(4);
+    assert_eq!(lower.len(), 4);
+    assert_eq!(upper.len(), 12);
+
+    let mut buffer = upper.into_dma_buffer::<u16>();
+    assert_eq!(buffer.len(), 6);
+    let (ptr, len) = unsafe { buffer.write_buffer() };
+    assert_eq!(len, 6);
+    unsafe { ptr.write(0xABCD) };
+
+    let buffer = lower.into_dma_buffer::<u32>();
+    let (ptr, len) = unsafe { buffer.read_buffer() };
+    assert_eq!(len, 1);
+    assert_eq!(ptr, memory.as_ptr());
+    assert_eq!(buffer.into_ram().len(), 4);
+    assert_eq!(memory[1] & 0xFFFF, 0xABCD);
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+/// Allocations from a heap in external memory
+fn external_heap() {
+    use core::alloc::{GlobalAlloc, Layout};
+
+    let heap = ExternalHeap::empty();
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    assert!(unsafe { heap.alloc(layout) }.is_null());
+
+    let mut memory = [0u32; 64];
+    let range = memory.as_ptr_range();
+    unsafe { heap.init(ExternalRam::from_raw_parts(memory.as_mut_ptr(), 256)) };
+    assert_eq!(heap.size(), 256);
+
+    let block = unsafe { heap.alloc(layout) };
+    assert!(range.contains(&(block as *const u32)));
+    assert_eq!(heap.used(), 64);
+
+    unsafe { heap.dealloc(block, layout) };
+    assert_eq!(heap.free(), 256);
+}
+
+#[test]
+/// Regions taken from a partition do not overlap
+fn external_ram_partition() {
+    let mut memory = [0u32; 64];
+    let base = memory.as_mut_ptr();
+    let ram = unsafe { ExternalRam::from_raw_parts(base, 256) };
+    let mut partition = ram.partition();
+
+    let a = partition.take(6).unwrap();
+    assert_eq!((a.as_ptr(), a.len()), (base, 8));
+
+    let b = partition.take_aligned(16, 64).unwrap();
+    assert_eq!((b.as_ptr() as usize) % 64, 0);
+    assert!(b.as_ptr() as usize >= base as usize + 8);
+    assert_eq!(b.len(), 16);
+
+    assert!(partition.take(1024).is_none());
+    let rest = partition.into_remainder();
+    assert_eq!(rest.as_ptr() as usize, b.as_ptr() as usize + 16);
+    assert_eq!(rest.as_ptr() as usize + rest.len(), base as usize + 256);
+}
+
+#[test]
+/// Retained memory is only trusted if it is unchanged since it was sealed
+fn external_ram_seal() {
+    let mut memory = [0u32; 16];
+    let mut ram =
+        unsafe { ExternalRam::from_raw_parts(memory.as_mut_ptr(), 64) };
+    assert_eq!(ram.check_seal(0x5EA1), Err(SealError::BadMagic));
+
+    ram.write_volatile(SEAL_HEADER_LEN, 0x1234_5678u32);
+    ram.seal(0x5EA1);
+    assert_eq!(ram.check_seal(0x5EA1), Ok(()));
+    assert_eq!(ram.check_seal(0xBEEF), Err(SealError::BadMagic));
+
+    ram.write_volatile(63, 0xFFu8);
+    assert_eq!(ram.check_seal(0x5EA1), Err(SealError::CrcMismatch));
+
+    let (ram, _) = ram.split_at(32);
+    assert_eq!(ram.check_seal(0x5EA1), Err(SealError::LengthMismatch));
+    let (ram, _) = ram.split_at(8);
+    assert_eq!(ram.check_seal(0x5EA1), Err(SealError::TooShort));
+}
+
+struct BoardCalibration;
+impl CalibrationSource for BoardCalibration {
+    fn calibration(&mut self) -> Option<SdramCalibration> {
+        Some(SdramCalibration {
+            extra_read_pipe_delay_cycles: 1,
+            max_sd_clock_hz: Some(80_000_000),
+        })
+    }
+}
+
+#[test]
+/// SDRAM initialisation with per-board calibration applied
+fn sdram_init_calibrated() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+
+    let sdram = sdram.init_calibrated(&mut NoDelay, &mut BoardCalibration);
+
+    let sdcr1 = Fmc::reg(SDCR1);
+    assert_eq!((sdcr1 >> 10) & 0b11, 3); // SDCLK: 66.7MHz
+    assert_eq!((sdcr1 >> 13) & 0b11, 1); // RPIPE
+
+    // The calibrated values are kept
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+}
+
+struct OverflowCalibration;
+impl CalibrationSource for OverflowCalibration {
+    fn calibration(&mut self) -> Option<SdramCalibration> {
+        Some(SdramCalibration {
+            extra_read_pipe_delay_cycles: 255,
+            max_sd_clock_hz: None,
+        })
+    }
+}
+
+#[test]
+/// A calibration that overflows the read pipe delay is an error
+fn sdram_init_calibrated_overflow() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_with_config(
+        Fmc,
+        1,
+        Chip::MODE_REGISTER,
+        SdramConfiguration {
+            read_pipe_delay_cycles: 1,
+            ..Chip::CONFIG
+        },
+        Chip::TIMING,
+    );
+
+    let result =
+        sdram.try_init_calibrated(&mut NoDelay, &mut OverflowCalibration);
+    assert_eq!(
+        init_error(result),
+        Some(SdramInitError::ReadPipeDelayOutOfRange)
+    );
+    assert_eq!(Fmc::reg(SDCR1), 0);
+}
+
+#[test]
+/// Self-refresh is entered on bank 2 and checked against SDSR
+fn sdram_self_refresh() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 2, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    // The fake FMC does not update SDSR by itself
+    Fmc::set_reg(SDSR, 0b01 << 3); // MODES2: Self-refresh
+    assert_eq!(sdram.enter_self_refresh(), Ok(()));
+
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!(sdcmr & 0b111, 0b101); // MODE: Self-refresh
+    assert_eq!((sdcmr >> 3) & 1, 1); // CTB2
+
+    // Still in self-refresh, so exiting fails
+    assert_eq!(sdram.exit_self_refresh(), Err(SdramMode::SelfRefresh));
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b000); // MODE: Normal
+
+    Fmc::set_reg(SDSR, 0);
+    assert_eq!(sdram.exit_self_refresh(), Ok(()));
+}
+
+#[test]
+/// Power-down is entered on bank 1, deasserting SDCKE
+fn sdram_power_down() {
+    fake_fmc!(Fmc, 200_000_000);
+    let mut sdram = Sdram::new_unchecked(Fmc, 1, Chip {}).init(&mut NoDelay);
+
+    Fmc::set_reg(SDSR, 0b10 << 1); // MODES1: Power-down
+    assert_eq!(sdram.enter_power_down(), Ok(()));
+
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!(sdcmr & 0b111, 0b110); // MODE: Power-down
+    assert_eq!((sdcmr >> 4) & 1, 1); // CTB1
+
+    assert_eq!(sdram.exit_power_down(), Err(SdramMode::PowerDown));
+    Fmc::set_reg(SDSR, 0);
+    assert_eq!(sdram.exit_power_down(), Ok(()));
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b000); // MODE: Normal
+}
+
+#[test]
+/// A new mode register can be loaded after initialisation
+fn sdram_send_raw_command() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    sdram.send_raw_command(SdramCommand::LoadMode(0x0231));
+
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!(sdcmr & 0b111, 0b100); // MODE: Load mode register
+    assert_eq!(sdcmr >> 9, 0x0231); // MRD
+}
+
+#[test]
+/// The refresh timer is reprogrammed for a shorter refresh period
+fn sdram_set_refresh_period() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    // 7.8µs at 100MHz SD clock = 781 cycles, less 20 cycles margin
+    sdram.set_refresh_period_ns(7_812);
+    assert_eq!((Fmc::reg(SDRTR) >> 1) & 0x1FFF, 761); // COUNT
+}
+
+#[test]
+/// The refresh period is halved above 85°C
+fn sdram_set_refresh_temperature() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    // 15.625µs / 2 at 100MHz SD clock = 781 cycles, less 20 cycles margin
+    sdram.set_refresh_temperature(95);
+    assert_eq!((Fmc::reg(SDRTR) >> 1) & 0x1FFF, 761); // COUNT
+
+    sdram.set_refresh_temperature(25);
+    assert_eq!((Fmc::reg(SDRTR) >> 1) & 0x1FFF, 1542); // COUNT
+}
+
+#[test]
+/// SDRAM constructed from runtime parameters instead of a chip definition
+fn sdram_new_with_config() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_with_config(
+        Fmc,
+        2,
+        Chip::MODE_REGISTER,
+        Chip::CONFIG,
+        Chip::TIMING,
+    );
+
+    let sdram = sdram.init(&mut NoDelay);
+    assert_eq!(sdram.as_ptr() as usize, 0xD000_0000);
+
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!(sdcmr & 0b111, 0b100); // MODE: Load mode register
+    assert_eq!((sdcmr >> 3) & 1, 1); // CTB2
+    assert_eq!((sdcmr >> 9) as u16, Chip::MODE_REGISTER); // MRD
+}
+
+#[test]
+/// An impossible SD clock is reported before the FMC is modified
+fn sdram_try_init_clock_too_fast() {
+    fake_fmc!(Fmc, 480_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+
+    assert_eq!(
+        init_error(sdram.try_init(&mut NoDelay)),
+        Some(SdramInitError::ClockTooFast {
+            required_divider: 5,
+            max_source_clock_hz: 300_000_000,
+        })
+    );
+    assert_eq!(Fmc::reg(SDCR1), 0);
+    assert_eq!(Fmc::reg(SDCMR), 0);
+}
+
+#[test]
+/// Configuration errors are reported in detail
+fn sdram_try_init_configuration() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_with_config(
+        Fmc,
+        1,
+        Chip::MODE_REGISTER,
+        SdramConfiguration {
+            cas_latency: 4,
+            ..Chip::CONFIG
+        },
+        Chip::TIMING,
+    );
+
+    assert_eq!(
+        init_error(sdram.try_init(&mut NoDelay)),
+        Some(SdramInitError::CasLatencyOutOfRange)
+    );
+}
+
+#[test]
+/// The FMC must use the CAS latency loaded into the mode register
+fn sdram_try_init_cas_mismatch() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_with_config(
+        Fmc,
+        1,
+        Chip::MODE_REGISTER,
+        SdramConfiguration {
+            cas_latency: 2,
+            ..Chip::CONFIG
+        },
+        Chip::TIMING,
+    );
+
+    assert_eq!(
+        init_error(sdram.try_init(&mut NoDelay)),
+        Some(SdramInitError::CasLatencyMismatch)
+    );
+}
+
+#[test]
+/// A delay that does not fit in its SDTR field is named in the error
+fn sdram_try_init_timing_field() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_with_config(
+        Fmc,
+        1,
+        Chip::MODE_REGISTER,
+        Chip::CONFIG,
+        SdramTiming {
+            active_to_precharge: 17,
+            ..Chip::TIMING
+        },
+    );
+
+    assert_eq!(
+        init_error(sdram.try_init(&mut NoDelay)),
+        Some(SdramInitError::ActiveToPrechargeOutOfRange)
+    );
+    assert_eq!(Fmc::reg(SDTR1), 0);
+}
+
+#[test]
+/// Delays that would underflow the write recovery delay are named in the
+/// error
+fn sdram_try_init_write_recovery() {
+    fake_fmc!(Fmc, 200_000_000);
+    for (timing, error) in [
+        (
+            SdramTiming {
+                active_to_precharge: 1,
+                row_to_column: 2,
+                ..Chip::TIMING
+            },
+            SdramInitError::ActiveToPrechargeBelowRowToColumn,
+        ),
+        (
+            SdramTiming {
+                row_cycle: 3,
+                row_to_column: 2,
+                row_precharge: 2,
+                ..Chip::TIMING
+            },
+            SdramInitError::RowCycleBelowRowToColumnAndPrecharge,
+        ),
+    ] {
+        let sdram = Sdram::new_with_config(
+            Fmc,
+            1,
+            Chip::MODE_REGISTER,
+            Chip::CONFIG,
+            timing,
+        );
+        assert_eq!(init_error(sdram.try_init(&mut NoDelay)), Some(error));
+    }
+    assert_eq!(Fmc::reg(SDTR1), 0);
+}
+
+#[test]
+/// The number of auto-refresh commands at initialisation is checked
+fn sdram_try_init_auto_refresh_commands() {
+    fake_fmc!(Fmc, 200_000_000);
+    for (auto_refresh_commands, result) in [
+        (0, Err(SdramInitError::AutoRefreshCommandsOutOfRange)),
+        (2, Ok(0xC000_0000)),
+        (15, Ok(0xC000_0000)),
+        (16, Err(SdramInitError::AutoRefreshCommandsOutOfRange)),
+    ] {
+        let timing = SdramTiming {
+            auto_refresh_commands,
+            ..Chip::TIMING
+        };
+        let sdram = Sdram::new_with_config(
+            Fmc,
+            1,
+            Chip::MODE_REGISTER,
+            Chip::CONFIG,
+            timing,
+        );
+        let ptr = sdram
+            .try_init(&mut NoDelay)
+            .map(|sdram| sdram.as_ptr() as usize)
+            .map_err(|(_, e)| e);
+        assert_eq!(ptr, result);
+    }
+}
+
+#[test]
+/// A refresh period that is too short for the refresh timer
+fn sdram_try_init_refresh_period() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_with_config(
+        Fmc,
+        1,
+        Chip::MODE_REGISTER,
+        Chip::CONFIG,
+        SdramTiming {
+            refresh_period_ns: 500,
+            ..Chip::TIMING
+        },
+    );
+
+    assert_eq!(
+        init_error(sdram.try_init(&mut NoDelay)),
+        Some(SdramInitError::RefreshCounterUnderflow)
+    );
+}
+
+#[test]
+/// Capacity is derived from the geometry of the chip
+fn sdram_size_bytes() {
+    // 4096 rows x 512 columns x 4 banks x 32 bits
+    assert_eq!(Chip::SIZE_BYTES, 32 * 1024 * 1024);
+    assert_eq!(
+        devices::is42s16400j_7::Is42s16400j::SIZE_BYTES,
+        8 * 1024 * 1024
+    );
+}
+
+#[test]
+/// Identical chips on both banks are configured together
+fn sdram_init_both_banks() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, SdramTargetBank::Both, Chip {});
+
+    let sdram = sdram.init(&mut NoDelay);
+    assert_eq!(sdram.as_ptr() as usize, 0xC000_0000);
+
+    // Per-bank fields are the same for both banks
+    let bank_fields = 0b1_1111_1111;
+    assert_ne!(Fmc::reg(SDCR1) & bank_fields, 0);
+    assert_eq!(Fmc::reg(SDCR1) & bank_fields, Fmc::reg(SDCR2) & bank_fields);
+    let timing_fields = 0x0F0F_0FFF; // TRCD, TWR, TRAS, TXSR, TMRD
+    assert_eq!(
+        Fmc::reg(SDTR1) & timing_fields,
+        Fmc::reg(SDTR2) & timing_fields
+    );
+
+    // Commands are sent to both banks
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!((sdcmr >> 3) & 0b11, 0b11); // CTB1, CTB2
+}
+
+#[test]
+/// A different SDRAM on each bank, with the common settings satisfying both
+fn sdram_two_instances() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram1 = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let sdram2 = Sdram::new_with_config(
+        Fmc,
+        2,
+        Chip::MODE_REGISTER,
+        SdramConfiguration {
+            read_burst: false,
+            read_pipe_delay_cycles: 1,
+            ..Chip::CONFIG
+        },
+        SdramTiming {
+            max_sd_clock_hz: 80_000_000,
+            refresh_period_ns: 7_812,
+            row_cycle: 9,
+            row_precharge: 3,
+            ..Chip::TIMING
+        },
+    );
+
+    let _ = sdram1.init(&mut NoDelay);
+    let _ = sdram2.init(&mut NoDelay);
+
+    let sdcr1 = Fmc::reg(SDCR1);
+    assert_eq!((sdcr1 >> 10) & 0b11, 3); // SDCLK: slowest of both
+    assert_eq!((sdcr1 >> 12) & 1, 0); // RBURST: only if both allow it
+    assert_eq!((sdcr1 >> 13) & 0b11, 1); // RPIPE: longest of both
+
+    let sdtr1 = Fmc::reg(SDTR1);
+    assert_eq!((sdtr1 >> 12) & 0xF, 8); // TRC: longest of both
+    assert_eq!((sdtr1 >> 20) & 0xF, 2); // TRP: longest of both
+
+    // 7.8µs at 66.7MHz SD clock, less 20 cycles margin
+    assert_eq!((Fmc::reg(SDRTR) >> 1) & 0x1FFF, 500); // COUNT
+}
+
+#[test]
+/// Remapped SDRAM is accessed in the NOR/PSRAM region
+fn sdram_bank_remap() {
+    fake_fmc!(Fmc, 200_000_000);
+    let mut sdram = Sdram::new_unchecked(Fmc, 2, Chip {});
+    sdram.set_bank_remap(true);
+
+    let sdram = sdram.init(&mut NoDelay);
+    assert_eq!(sdram.as_ptr() as usize, 0x7000_0000);
+    assert_eq!((Fmc::reg(BCR1) >> 24) & 0b11, 0b01); // BMAP: Swapped
+}
+
+const TIMING_NS: SdramTimingNs = SdramTimingNs {
+    startup_delay_ns: 100_000,
+    max_sd_clock_hz: 100_000_000,
+    refresh_period_ns: 15_625,
+    mode_register_to_active_ns: 20,
+    exit_self_refresh_ns: 70,
+    active_to_precharge_ns: 42,
+    row_cycle_ns: 60,
+    row_precharge_ns: 18,
+    row_to_column_ns: 18,
+    auto_refresh_commands: 8,
+};
+
+#[test]
+/// Nanosecond timings are rounded up to whole SD clock cycles
+fn sdram_timing_ns_cycles() {
+    let timing = TIMING_NS.cycles(100_000_000);
+    assert_eq!(timing.active_to_precharge, 5);
+    assert_eq!(timing.row_cycle, 6);
+    assert_eq!(timing.row_to_column, 2);
+}
+
+#[test]
+/// Nanosecond timings are converted at the SD clock selected by init
+fn sdram_set_timing_ns() {
+    fake_fmc!(Fmc, 100_000_000); // SD clock 50MHz
+    let mut sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    sdram.set_timing_ns(TIMING_NS);
+
+    let _sdram = sdram.init(&mut NoDelay);
+
+    let sdtr1 = Fmc::reg(SDTR1);
+    assert_eq!((sdtr1 >> 8) & 0xF, 2); // TRAS: 3 cycles
+    assert_eq!((sdtr1 >> 12) & 0xF, 2); // TRC: 3 cycles
+    assert_eq!((sdtr1 >> 24) & 0xF, 0); // TRCD: 1 cycle
+}
+
+#[test]
+/// The mode register builder matches the hand-written device constants
+fn sdram_mode_register() {
+    let mode_register = ModeRegister::new()
+        .burst_length(1)
+        .cas_latency(Cas::Three)
+        .single_write()
+        .checked(&Chip::CONFIG);
+    assert_eq!(mode_register, Chip::MODE_REGISTER);
+
+    let mode_register = ModeRegister::new().burst_length(8).interleaved();
+    assert_eq!(mode_register.bits(), 0x003B);
+}
+
+#[test]
+#[should_panic]
+/// The CAS latency must agree with the controller configuration
+fn sdram_mode_register_cas_mismatch() {
+    ModeRegister::new()
+        .cas_latency(Cas::Two)
+        .checked(&Chip::CONFIG);
+}
+
+#[test]
+/// Releasing the SDRAM leaves it in self-refresh mode, and stops the SDRAM
+/// controller
+fn sdram_free() {
+    fake_fmc!(Fmc, 200_000_000);
+    Fmc::set_reg(SDCR2, 0x2D0); // Reset value
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let sdram = sdram.init(&mut NoDelay);
+
+    Fmc::set_reg(SDSR, 0b01 << 1); // MODES1: Self-refresh
+    let (_fmc, ()) = sdram.free().ok().unwrap();
+
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!(sdcmr & 0b111, 0b101); // MODE: Self-refresh
+    assert_eq!((sdcmr >> 4) & 1, 1); // CTB1
+    assert_eq!(Fmc::reg(SDCR1), 0x2D0); // SDCLK stopped
+    assert_eq!(Fmc::reg(SDRTR), 0);
+}
+
+#[test]
+/// An uninitialised SDRAM is released without sending any command
+fn sdram_free_uninitialized() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let (_fmc, ()) = sdram.free();
+
+    assert_eq!(Fmc::reg(SDCMR), 0);
+    assert_eq!(Fmc::reg(SDCR1), 0);
+}
+
+#[test]
+/// An SDRAM on the other bank keeps the SD clock and refresh timer running
+fn sdram_free_other_bank_running() {
+    fake_fmc!(Fmc, 200_000_000);
+    Fmc::set_reg(SDCR1, 0x2D0); // Reset value
+    let sdram2 = Sdram::new_unchecked(Fmc, 2, Chip {});
+    let _sdram2 = sdram2.init(&mut NoDelay);
+    let sdram1 = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let sdram1 = sdram1.init(&mut NoDelay);
+    let sdrtr = Fmc::reg(SDRTR);
+
+    Fmc::set_reg(SDSR, 0b01 << 1); // MODES1: Self-refresh
+    let (_fmc, ()) = sdram1.free().ok().unwrap();
+
+    assert_ne!(Fmc::reg(SDCR1) & (0b11 << 10), 0); // SDCLK still running
+    assert_eq!(Fmc::reg(SDCR1) & 0x3FF, 0x2D0); // Bank 1 reset
+    assert_ne!(Fmc::reg(SDCR2), 0x2D0);
+    assert_eq!(Fmc::reg(SDRTR), sdrtr);
+}
+
+#[test]
+/// The SDRAM is not released if it does not enter self-refresh mode
+fn sdram_free_error() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let sdram = sdram.init(&mut NoDelay);
+    let sdcr1 = Fmc::reg(SDCR1);
+
+    let (_sdram, mode) = sdram.free().err().unwrap();
+    assert_eq!(mode, SdramMode::Normal);
+    assert_eq!(Fmc::reg(SDCR1), sdcr1);
+}
+
+#[test]
+/// The SD clock is reported once the SDRAM controller is initialised
+fn sdram_sd_clock_hz() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    assert_eq!(sdram.sd_clock_hz(), None);
+
+    let sdram = sdram.init(&mut NoDelay);
+    assert_eq!(sdram.sd_clock_divider(), Some(2));
+    assert_eq!(sdram.sd_clock_hz(), Some(100_000_000));
+}
+
+struct MobileChip;
+impl SdramChip for MobileChip {
+    const MODE_REGISTER: u16 = Chip::MODE_REGISTER;
+    const CONFIG: SdramConfiguration = Chip::CONFIG;
+    const TIMING: SdramTiming = Chip::TIMING;
+}
+impl MobileSdramChip for MobileChip {
+    const EXTENDED_MODE_REGISTER: u16 = ExtendedModeRegister::new()
+        .pasr(Pasr::Quarter)
+        .tcsr(Tcsr::Max45C)
+        .bits();
+}
+
+#[test]
+/// The extended mode register of a mobile SDRAM is loaded after init
+fn sdram_init_mobile() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, MobileChip);
+
+    let _ = sdram.init_mobile(&mut NoDelay);
+
+    let sdcmr = Fmc::reg(SDCMR);
+    assert_eq!(sdcmr & 0b111, 0b100); // MODE: Load mode register
+    assert_eq!((sdcmr >> 9) as u16, 0x200A); // MRD: BA1, TCSR, PASR
+}
+
+#[test]
+/// Registers that were changed after init are reported
+fn sdram_verify_configuration() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    assert!(sdram.verify_configuration().is_err());
+
+    let sdram = sdram.init(&mut NoDelay);
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+
+    // A slower refresh timer and different tRCD
+    Fmc::set_reg(SDRTR, Fmc::reg(SDRTR) + (1 << 1));
+    Fmc::set_reg(SDTR1, Fmc::reg(SDTR1) ^ (1 << 24));
+    assert_eq!(
+        sdram.verify_configuration(),
+        Err(SdramRegisterDiff {
+            sdcr: 0,
+            sdtr: 0xF << 24,    // TRCD
+            sdrtr: 0x1FFF << 1, // COUNT
+        })
+    );
+}
+
+#[test]
+/// The SDRAM controller is restored after STANDBY, without repeating the
+/// initialisation sequence
+fn sdram_suspend_resume() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+    sdram.set_refresh_temperature(95);
+    let sdrtr = Fmc::reg(SDRTR);
+
+    Fmc::set_reg(SDSR, 0b01 << 1); // MODES1: Self-refresh
+    assert_eq!(sdram.suspend(true), Ok(()));
+    assert_eq!(Fmc::reg(SDCR1) & (0b11 << 10), 0); // SDCLK: Stopped
+
+    // Registers are reset in STANDBY
+    for reg in [SDCR1, SDTR1, SDRTR] {
+        Fmc::set_reg(reg, 0);
+    }
+    Fmc::set_reg(SDSR, 0);
+    assert_eq!(sdram.resume(), Ok(()));
+
+    assert_eq!((Fmc::reg(SDCR1) >> 10) & 0b11, 2); // SDCLK: 100MHz
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b000); // MODE: Normal
+    assert_eq!(Fmc::reg(SDRTR), sdrtr);
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+}
+
+#[test]
+/// Different SDRAMs on each bank are both restored after STANDBY mode
+fn sdram_suspend_resume_two_instances() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram1 = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let sdram2 = Sdram::new_with_config(
+        Fmc,
+        2,
+        Chip::MODE_REGISTER,
+        Chip::CONFIG,
+        SdramTiming {
+            row_cycle: 9,
+            row_to_column: 3,
+            ..Chip::TIMING
+        },
+    );
+    let mut sdram1 = sdram1.init(&mut NoDelay);
+    let mut sdram2 = sdram2.init(&mut NoDelay);
+
+    Fmc::set_reg(SDSR, 0b01 << 1 | 0b01 << 3); // MODES1, MODES2: Self-refresh
+    assert_eq!(sdram1.suspend(false), Ok(()));
+    assert_eq!(sdram2.suspend(true), Ok(()));
+
+    // Registers are reset in STANDBY
+    for reg in [SDCR1, SDCR2] {
+        Fmc::set_reg(reg, 0x0000_02D0);
+    }
+    for reg in [SDTR1, SDTR2] {
+        Fmc::set_reg(reg, 0x0FFF_FFFF);
+    }
+    Fmc::set_reg(SDRTR, 0);
+    Fmc::set_reg(SDSR, 0);
+    assert_eq!(sdram1.resume(), Ok(()));
+    assert_eq!(sdram2.resume(), Ok(()));
+
+    assert_eq!(sdram1.verify_configuration(), Ok(()));
+    assert_eq!(sdram2.verify_configuration(), Ok(()));
+    assert_eq!((Fmc::reg(SDTR1) >> 12) & 0xF, 8); // TRC: longest of both
+    assert_eq!((Fmc::reg(SDTR1) >> 24) & 0xF, 1); // TRCD: bank 1
+    assert_eq!((Fmc::reg(SDTR2) >> 24) & 0xF, 2); // TRCD: bank 2
+}
+
+#[test]
+/// The read path can be tuned after initialisation
+fn sdram_read_path_tuning() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    sdram.set_read_burst(false);
+    sdram.set_read_pipe_delay(2);
+    let sdcr1 = Fmc::reg(SDCR1);
+    assert_eq!((sdcr1 >> 12) & 1, 0); // RBURST
+    assert_eq!((sdcr1 >> 13) & 0b11, 2); // RPIPE
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+
+    sdram.set_read_burst(true);
+    sdram.set_read_pipe_delay(0);
+    let sdcr1 = Fmc::reg(SDCR1);
+    assert_eq!((sdcr1 >> 12) & 1, 1); // RBURST
+    assert_eq!((sdcr1 >> 13) & 0b11, 0); // RPIPE
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+}
+
+#[test]
+/// Read bursts can be disabled for a chip definition that enables them
+fn sdram_override_read_burst() {
+    fake_fmc!(Fmc, 200_000_000);
+    let mut sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    sdram.override_read_burst(false);
+    let sdram = sdram.init(&mut NoDelay);
+
+    assert_eq!((Fmc::reg(SDCR1) >> 12) & 1, 0); // RBURST
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+}
+
+#[test]
+/// The programmed timings are read back after initialisation
+fn sdram_timing_report() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 2, Chip {});
+    assert_eq!(sdram.timing_report(), None);
+    let sdram = sdram.init(&mut NoDelay);
+
+    let report = sdram.timing_report().unwrap();
+    assert_eq!(
+        report,
+        SdramTimingReport {
+            sd_clock_divider: 2,
+            sd_clock_hz: 100_000_000,
+            mode_register_to_active: 2,
+            exit_self_refresh: 7,
+            self_refresh: 4,
+            row_cycle: 7,
+            write_recovery: 3,
+            row_precharge: 2,
+            row_to_column: 2,
+            refresh_counter: 1542,
+        }
+    );
+}
+
+#[test]
+/// A running SDRAM controller is not initialised again after a soft reset
+fn sdram_try_init_warm() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    assert!(!sdram.is_configured());
+    let _ = sdram.try_init_warm(&mut NoDelay).unwrap();
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b100); // MODE: Load mode register
+
+    // Soft reset
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    assert!(sdram.is_configured());
+    Fmc::set_reg(SDCMR, 0);
+    Fmc::set_reg(SDRTR, 0x1FFF << 1); // Refresh timer too slow
+
+    let sdram = sdram.try_init_warm(&mut NoDelay).unwrap();
+    assert_eq!(sdram.as_ptr() as usize, 0xC000_0000);
+    assert_eq!(Fmc::reg(SDCMR), 0); // No commands
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+}
+
+#[test]
+/// The SDRAM controller is reprogrammed for a new FMC source clock
+fn sdram_reinit_timings() {
+    use std::sync::atomic::{AtomicU32, Ordering::Relaxed};
+    static CLOCK_HZ: AtomicU32 = AtomicU32::new(200_000_000);
+    fake_fmc!(Fmc, CLOCK_HZ.load(Relaxed));
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+
+    Fmc::set_reg(SDSR, 0b01 << 1); // MODES1: Self-refresh
+    let result = sdram.reinit_timings(|_| {
+        CLOCK_HZ.store(100_000_000, Relaxed);
+        Fmc::set_reg(SDSR, 0);
+    });
+    assert_eq!(result, Ok(()));
+
+    assert_eq!(sdram.sd_clock_hz(), Some(50_000_000));
+    assert_eq!(Fmc::reg(SDCMR) & 0b111, 0b000); // MODE: Normal
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+}
+
+#[test]
+/// A derated refresh period is kept when the SDRAM controller is
+/// reprogrammed for a new FMC source clock
+fn sdram_reinit_timings_derated() {
+    use std::sync::atomic::{AtomicU32, Ordering::Relaxed};
+    static CLOCK_HZ: AtomicU32 = AtomicU32::new(200_000_000);
+    fake_fmc!(Fmc, CLOCK_HZ.load(Relaxed));
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+    sdram.set_refresh_temperature(95);
+
+    Fmc::set_reg(SDSR, 0b01 << 1); // MODES1: Self-refresh
+    let result = sdram.reinit_timings(|_| {
+        CLOCK_HZ.store(100_000_000, Relaxed);
+        Fmc::set_reg(SDSR, 0);
+    });
+    assert_eq!(result, Ok(()));
+
+    // 15.625µs / 2 at 50MHz SD clock = 390 cycles, less 20 cycles margin
+    assert_eq!((Fmc::reg(SDRTR) >> 1) & 0x1FFF, 370); // COUNT
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+}
+
+#[test]
+/// The refresh timer is programmed for the SD clock running after resume
+fn sdram_resume_derated_slower_clock() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+    sdram.set_refresh_temperature(95);
+
+    Fmc::set_reg(SDSR, 0b01 << 1); // MODES1: Self-refresh
+    assert_eq!(sdram.suspend(false), Ok(()));
+
+    // An SDRAM on bank 2 is resumed first, with a slower SD clock
+    Fmc::set_reg(SDCR1, 0x2D0 | (3 << 10));
+    Fmc::set_reg(SDSR, 0);
+    assert_eq!(sdram.resume(), Ok(()));
+
+    // 15.625µs / 2 at 66MHz SD clock = 520 cycles, less 20 cycles margin
+    assert_eq!((Fmc::reg(SDRTR) >> 1) & 0x1FFF, 500); // COUNT
+}
+
+#[test]
+/// The refresh error flag is read from SDSR, and cleared through SDRTR
+fn sdram_refresh_error() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let mut sdram = sdram.init(&mut NoDelay);
+    assert!(!sdram.refresh_error());
+
+    Fmc::set_reg(SDSR, 1); // RE
+    assert!(sdram.refresh_error());
+
+    sdram.set_refresh_error_interrupt(true);
+    sdram.clear_refresh_error();
+    assert_eq!(Fmc::reg(SDRTR) & ((1 << 14) | 1), (1 << 14) | 1); // REIE, CRE
+
+    sdram.set_refresh_error_interrupt(false);
+    assert_eq!(Fmc::reg(SDRTR) & (1 << 14), 0);
+}
+
+#[test]
+/// SDSR is decoded for both banks
+fn sdram_status() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    let sdram = sdram.init(&mut NoDelay);
+
+    Fmc::set_reg(SDSR, (1 << 5) | (0b10 << 3) | (0b01 << 1));
+    assert_eq!(
+        sdram.status(),
+        SdramStatus {
+            bank1: SdramMode::SelfRefresh,
+            bank2: SdramMode::PowerDown,
+            busy: true,
+            refresh_error: false,
+        }
+    );
+}
+
+#[test]
+/// The refresh timer margin can be changed from the default 20 cycles
+fn sdram_refresh_margin() {
+    fake_fmc!(Fmc, 200_000_000);
+    let mut sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    sdram.set_refresh_margin(100);
+    let sdram = sdram.init(&mut NoDelay);
+
+    // 15.625µs at 100MHz SD clock = 1562 cycles, less 100 cycles margin
+    assert_eq!((Fmc::reg(SDRTR) >> 1) & 0x1FFF, 1462); // COUNT
+    assert_eq!(sdram.verify_configuration(), Ok(()));
+
+    let mut sdram = Sdram::new_unchecked(Fmc, 1, Chip {});
+    sdram.set_refresh_margin(1600);
+    assert_eq!(
+        init_error(sdram.try_init(&mut NoDelay)),
+        Some(SdramInitError::RefreshCounterUnderflow)
+    );
+}
+
+/// Delay that records the total time waited
+struct RecordingDelay(u64);
+impl embedded_hal::delay::DelayNs for RecordingDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0 += ns as u64;
+    }
+}
+
+#[test]
+/// Startup delays of more than 255µs are waited in full
+fn sdram_long_startup_delay() {
+    fake_fmc!(Fmc, 200_000_000);
+    let sdram = Sdram::new_with_config(
+        Fmc,
+        1,
+        Chip::MODE_REGISTER,
+        Chip::CONFIG,
+        SdramTiming {
+            startup_delay_ns: 500_000,
+            ..Chip::TIMING
+        },
+    );
+
+    let mut delay = RecordingDelay(0);
+    sdram.init(&mut delay);
+    assert_eq!(delay.0, 500_000);
+}
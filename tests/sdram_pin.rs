@@ -1,4 +1,5 @@
 //! Tests SDRAM pin constraints apply correctly
+#![cfg(feature = "sdram")]
 
 mod dummy_pins;
 use dummy_pins::*;
@@ -8,7 +9,7 @@ use stm32_fmc::*;
 /// Dummy FmcPeripheral implementation for testing
 struct DummyFMC;
 unsafe impl FmcPeripheral for DummyFMC {
-    const REGISTERS: *const () = 0 as *const ();
+    const REGISTERS: *const () = core::ptr::null();
     fn enable(&mut self) {}
     fn source_clock_hz(&self) -> u32 {
         100_000_000
@@ -101,6 +102,29 @@ fn sdram_pins_12a_4b_not_enough_bank_pins() {
     Sdram::new(fmc, pins, chip);
 }
 
+#[test]
+#[should_panic]
+/// 32-bit SDRAM with only NBL0 and NBL1
+fn sdram_pins_12a_4b_not_enough_byte_lanes() {
+    let fmc = DummyFMC {};
+    let pins = fmc_pin_set!(
+        // 12 address bits
+        A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11,
+        // 4 internal banks --------------------------------------
+        BA0, BA1,
+        // 16 bit data (!) ---------------------------------------
+        D0, D1, D2, D3, D4, D5, D6, D7, D8, D9, D10, D11, D12, D13, D14, D15,
+        // NBL0-1 (!) --------------------------------------------
+        NBL0, NBL1,
+        // SDRAM Bank 0 ------------------------------------------
+        SDCKE0, SDCLK, SDNCAS, SDNE0, SDNRAS, SDNWE
+    );
+    let chip = devices::is42s32800g_6::Is42s32800g {};
+
+    // Check we can create a SDRAM
+    Sdram::new(fmc, pins, chip);
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct DummyChip {}
 
@@ -138,6 +162,7 @@ impl SdramChip for DummyChip {
         row_cycle: 7,                 // tRC = 70ns
         row_precharge: 2,             // tRP = 18ns
         row_to_column: 2,             // tRCD = 18ns
+        auto_refresh_commands: 8,     // 8 auto-refresh commands
     };
 }
 
@@ -164,3 +189,160 @@ fn sdram_chip_impl() {
     // Check we can create a SDRAM
     Sdram::new(fmc, pins, chip);
 }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dummy8BitChip {}
+
+impl SdramChip for Dummy8BitChip {
+    const MODE_REGISTER: u16 = DummyChip::MODE_REGISTER;
+
+    const CONFIG: stm32_fmc::SdramConfiguration = SdramConfiguration {
+        memory_data_width: 8, // 8-bit
+        ..DummyChip::CONFIG
+    };
+
+    const TIMING: stm32_fmc::SdramTiming = DummyChip::TIMING;
+}
+
+#[test]
+/// SDRAM with 12 address pins, 4 banks and an 8-bit data bus
+fn sdram_pins_8bit() {
+    let fmc = DummyFMC {};
+    let pins = fmc_pin_set!(
+        // 12 address bits
+        A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11,
+        // 4 internal banks --------------------------------------
+        BA0, BA1,
+        // 8 bit data --------------------------------------------
+        D0, D1, D2, D3, D4, D5, D6, D7,
+        // NBL0 --------------------------------------------------
+        NBL0,
+        // SDRAM Bank 1 ------------------------------------------
+        SDCKE1, SDCLK, SDNCAS, SDNE1, SDNRAS, SDNWE
+    );
+    let chip = Dummy8BitChip {};
+
+    // Check we can create a SDRAM
+    Sdram::new(fmc, pins, chip);
+}
+
+stm32_fmc::define_sdram_pinset! {
+    /// 16-bit SDRAM on bank 2 with 12 address pins and 4 banks
+    struct BoardSdramPins: SdramBank2, AddressPins12 {
+        a0: PinThatsA0, a1: PinThatsA1, a2: PinThatsA2, a3: PinThatsA3,
+        a4: PinThatsA4, a5: PinThatsA5, a6: PinThatsA6, a7: PinThatsA7,
+        a8: PinThatsA8, a9: PinThatsA9, a10: PinThatsA10, a11: PinThatsA11,
+        ba0: PinThatsBA0, ba1: PinThatsBA1,
+        d0: PinThatsD0, d1: PinThatsD1, d2: PinThatsD2, d3: PinThatsD3,
+        d4: PinThatsD4, d5: PinThatsD5, d6: PinThatsD6, d7: PinThatsD7,
+        d8: PinThatsD8, d9: PinThatsD9, d10: PinThatsD10, d11: PinThatsD11,
+        d12: PinThatsD12, d13: PinThatsD13, d14: PinThatsD14,
+        d15: PinThatsD15,
+        nbl0: PinThatsNBL0, nbl1: PinThatsNBL1,
+        sdcke1: PinThatsSDCKE1, sdclk: PinThatsSDCLK, sdncas: PinThatsSDNCAS,
+        sdne1: PinThatsSDNE1, sdnras: PinThatsSDNRAS, sdnwe: PinThatsSDNWE,
+    }
+}
+
+#[test]
+/// Test that a pin set struct can be used in place of a tuple
+fn sdram_pins_struct() {
+    let fmc = DummyFMC {};
+    let pins = BoardSdramPins {
+        a0: PinThatsA0,
+        a1: PinThatsA1,
+        a2: PinThatsA2,
+        a3: PinThatsA3,
+        a4: PinThatsA4,
+        a5: PinThatsA5,
+        a6: PinThatsA6,
+        a7: PinThatsA7,
+        a8: PinThatsA8,
+        a9: PinThatsA9,
+        a10: PinThatsA10,
+        a11: PinThatsA11,
+        ba0: PinThatsBA0,
+        ba1: PinThatsBA1,
+        d0: PinThatsD0,
+        d1: PinThatsD1,
+        d2: PinThatsD2,
+        d3: PinThatsD3,
+        d4: PinThatsD4,
+        d5: PinThatsD5,
+        d6: PinThatsD6,
+        d7: PinThatsD7,
+        d8: PinThatsD8,
+        d9: PinThatsD9,
+        d10: PinThatsD10,
+        d11: PinThatsD11,
+        d12: PinThatsD12,
+        d13: PinThatsD13,
+        d14: PinThatsD14,
+        d15: PinThatsD15,
+        nbl0: PinThatsNBL0,
+        nbl1: PinThatsNBL1,
+        sdcke1: PinThatsSDCKE1,
+        sdclk: PinThatsSDCLK,
+        sdncas: PinThatsSDNCAS,
+        sdne1: PinThatsSDNE1,
+        sdnras: PinThatsSDNRAS,
+        sdnwe: PinThatsSDNWE,
+    };
+    let chip = devices::is42s16400j_7::Is42s16400j {};
+
+    // Check we can create a SDRAM
+    Sdram::new(fmc, pins, chip);
+}
+
+#[test]
+/// SDRAM with 13 address pins, 4 banks and a 16-bit data bus
+fn sdram_pins_13a_4b() {
+    let fmc = DummyFMC {};
+    let pins = fmc_pin_set!(
+        // 13 address bits
+        A0, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12,
+        // 4 internal banks --------------------------------------
+        BA0, BA1,
+        // 16 bit data -------------------------------------------
+        D0, D1, D2, D3, D4, D5, D6, D7, D8, D9, D10, D11, D12, D13, D14, D15,
+        // NBL0-1 ------------------------------------------------
+        NBL0, NBL1,
+        // SDRAM Bank 0 ------------------------------------------
+        SDCKE0, SDCLK, SDNCAS, SDNE0, SDNRAS, SDNWE
+    );
+    let chip = devices::is42s16320f_6::Is42s16320f {};
+
+    // Check we can create a SDRAM
+    Sdram::new(fmc, pins, chip);
+}
+
+#[test]
+/// SDRAM with pins described at runtime
+fn sdram_pins_dyn() {
+    let fmc = DummyFMC {};
+    let pins = DynPinsSdram {
+        address_lines: 12,
+        banks: 4,
+        width: 32,
+    };
+    let chip = devices::is42s32800g_6::Is42s32800g {};
+
+    // Check we can create a SDRAM
+    Sdram::new_dyn(fmc, 1, pins, chip);
+}
+
+#[test]
+#[should_panic]
+/// SDRAM with pins described at runtime, but not enough data pins
+fn sdram_pins_dyn_not_enough_data_pins() {
+    let fmc = DummyFMC {};
+    let pins = DynPinsSdram {
+        address_lines: 12,
+        banks: 4,
+        width: 16,
+    };
+    let chip = devices::is42s32800g_6::Is42s32800g {};
+
+    // Check we can create a SDRAM
+    Sdram::new_dyn(fmc, 1, pins, chip);
+}
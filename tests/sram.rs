@@ -0,0 +1,242 @@
+//! Tests SRAM register programming against a fake register block
+#![cfg(feature = "sram")]
+
+mod fake_fmc;
+use fake_fmc::*;
+
+use stm32_fmc::*;
+
+const CONFIG: SramConfiguration = SramConfiguration {
+    memory_data_width: 16,
+    write_enable: true,
+    address_setup_cycles: 2,
+    address_hold_cycles: 1,
+    data_setup_cycles: 5,
+    bus_turnaround_cycles: 3,
+};
+
+#[test]
+/// The registers for the selected sub-bank are programmed
+fn sram_init() {
+    fake_fmc!(Fmc, 100_000_000);
+    let mut sram = Sram::new_unchecked(Fmc, 2, CONFIG);
+
+    let ptr = sram.init();
+    assert_eq!(ptr as usize, 0x6400_0000);
+    assert_eq!(Fmc::enables().load(core::sync::atomic::Ordering::SeqCst), 1);
+
+    // WREN, MWID = 16 bits, MTYP = SRAM, MBKEN
+    assert_eq!(Fmc::reg(BCR2), (1 << 12) | (1 << 4) | 1);
+    // BUSTURN, DATAST, ADDHLD, ADDSET
+    assert_eq!(Fmc::reg(BTR2), 0x0003_0512);
+    // Other sub-banks are untouched
+    assert_eq!(Fmc::reg(BCR1), 0);
+    assert_eq!(Fmc::reg(BTR1), 0);
+}
+
+#[test]
+/// The FMC is not enabled again while another memory controller is running
+fn sram_init_fmc_running() {
+    use core::sync::atomic::Ordering;
+    fake_fmc!(Fmc, 100_000_000);
+
+    // SDRAM controller running, SDCLK = 2
+    Fmc::set_reg(SDCR1, 2 << 10);
+    let mut sram = Sram::new_unchecked(Fmc, 2, CONFIG);
+    let _ = sram.init();
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 0);
+    assert_eq!(Fmc::reg(BCR2) & 1, 1); // MBKEN
+
+    // Another SRAM sub-bank running
+    Fmc::set_reg(SDCR1, 0);
+    let mut sram = Sram::new_unchecked(Fmc, 3, CONFIG);
+    let _ = sram.init();
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 0);
+}
+
+#[test]
+/// A 32-bit SRAM is programmed with MWID = 32 bits
+fn sram_init_32bit() {
+    fake_fmc!(Fmc, 100_000_000);
+    let mut sram = Sram::new_unchecked(
+        Fmc,
+        1,
+        SramConfiguration {
+            memory_data_width: 32,
+            ..CONFIG
+        },
+    );
+
+    let _ = sram.init();
+    // WREN, MWID = 32 bits, MTYP = SRAM, MBKEN
+    assert_eq!(Fmc::reg(BCR1) & 0x103F, (1 << 12) | (2 << 4) | 1);
+}
+
+#[test]
+/// ADDHLD is unused in mode 1, so zero is accepted
+fn sram_zero_address_hold() {
+    fake_fmc!(Fmc, 100_000_000);
+    let mut sram = Sram::new_unchecked(
+        Fmc,
+        1,
+        SramConfiguration {
+            address_hold_cycles: 0,
+            ..CONFIG
+        },
+    );
+
+    let _ = sram.init();
+    assert_eq!((Fmc::reg(BTR1) >> 4) & 0xF, 1); // ADDHLD
+}
+
+#[test]
+#[should_panic(expected = "Invalid SRAM sub-bank 5")]
+/// There are four sub-banks
+fn sram_invalid_sub_bank() {
+    fake_fmc!(Fmc, 100_000_000);
+    let _ = Sram::new_unchecked(Fmc, 5, CONFIG);
+}
+
+#[test]
+#[should_panic]
+/// DATAST must be at least one cycle
+fn sram_zero_data_phase() {
+    fake_fmc!(Fmc, 100_000_000);
+    let mut sram = Sram::new_unchecked(
+        Fmc,
+        1,
+        SramConfiguration {
+            data_setup_cycles: 0,
+            ..CONFIG
+        },
+    );
+
+    let _ = sram.init();
+}
+
+#[test]
+/// Sub-banks sharing the bus all use the largest turnaround
+fn sram_share_bus_turnaround() {
+    fake_fmc!(Fmc, 100_000_000);
+    let busturn = |btr| (Fmc::reg(btr) >> 16) & 0xF;
+
+    // Sub-bank 1 is enabled as NOR Flash at reset and is left alone
+    Fmc::set_reg(BCR1, 0x30DB);
+    Fmc::set_reg(BTR1, 0x0FFF_FFFF);
+
+    let mut slow = Sram::new_unchecked(
+        Fmc,
+        2,
+        SramConfiguration {
+            bus_turnaround_cycles: 6,
+            ..CONFIG
+        },
+    );
+    let _ = slow.init();
+    assert_eq!(busturn(BTR2), 6);
+
+    let mut fast = Sram::new_unchecked(
+        Fmc,
+        3,
+        SramConfiguration {
+            bus_turnaround_cycles: 0,
+            ..CONFIG
+        },
+    );
+    let _ = fast.init();
+    assert_eq!(busturn(BTR3), 6);
+    assert_eq!(busturn(BTR2), 6);
+
+    let mut slower = Sram::new_unchecked(
+        Fmc,
+        4,
+        SramConfiguration {
+            bus_turnaround_cycles: 9,
+            ..CONFIG
+        },
+    );
+    let _ = slower.init();
+    assert_eq!(busturn(BTR2), 9);
+    assert_eq!(busturn(BTR3), 9);
+    assert_eq!(busturn(BTR4), 9);
+    assert_eq!(Fmc::reg(BTR1), 0x0FFF_FFFF);
+}
+
+#[test]
+/// Freeing the SRAM disables the sub-bank
+fn sram_free() {
+    fake_fmc!(Fmc, 100_000_000);
+    let mut sram = Sram::new_unchecked(Fmc, 4, CONFIG);
+
+    let _ = sram.init();
+    assert_eq!(Fmc::reg(BCR4) & 1, 1);
+
+    let (_fmc, ()) = sram.free();
+    assert_eq!(Fmc::reg(BCR4), (1 << 12) | (1 << 4));
+}
+
+#[cfg(feature = "sdram")]
+#[test]
+/// An SDRAM initialised after the SRAM does not enable the FMC again
+fn sram_then_sdram_init() {
+    use core::sync::atomic::Ordering;
+    fake_fmc!(Fmc, 200_000_000);
+
+    let mut sram = Sram::new_unchecked(Fmc, 2, CONFIG);
+    let _ = sram.init();
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 1);
+
+    let chip = devices::is42s32800g_6::Is42s32800g {};
+    let _sdram = Sdram::new_unchecked(Fmc, 1, chip).init(&mut NoDelay);
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "nand")]
+#[test]
+/// A NAND initialised after the SRAM does not enable the FMC again
+fn sram_then_nand_init() {
+    use core::sync::atomic::Ordering;
+    use embedded_hal::delay::DelayNs;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    fake_fmc!(Fmc, 200_000_000);
+
+    /// Stops `Nand::init` once the controller is enabled, before the device
+    /// itself is accessed
+    struct StopDelay;
+    impl DelayNs for StopDelay {
+        fn delay_ns(&mut self, _ns: u32) {
+            panic!("controller enabled");
+        }
+    }
+
+    let mut sram = Sram::new_unchecked(Fmc, 2, CONFIG);
+    let _ = sram.init();
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 1);
+
+    let chip = devices::s34ml08g3_4kb::S34ml08g3 {};
+    let mut nand = unsafe { Nand::new_unchecked(Fmc, chip) };
+    let stopped = catch_unwind(AssertUnwindSafe(|| nand.init(&mut StopDelay)));
+    assert!(stopped.is_err());
+    assert_eq!(Fmc::enables().load(Ordering::SeqCst), 1);
+    assert_eq!(Fmc::reg(PCR) & 0b100, 0b100); // PBKEN
+}
+
+#[test]
+/// The bundled SRAM configurations can be programmed
+fn sram_devices() {
+    fake_fmc!(Fmc, 200_000_000);
+    let configs = [
+        devices::cy7c1041_10::CONFIG,
+        devices::cy7c1041_12::CONFIG,
+        devices::mt45w8mw16_70::CONFIG,
+    ];
+
+    for (config, bank) in configs.iter().zip(1..) {
+        let mut sram = Sram::new_unchecked(Fmc, bank, *config);
+        let _ = sram.init();
+    }
+    // BUSTURN is shared, DATAST, ADDHLD, ADDSET
+    assert_eq!(Fmc::reg(BTR1), 0x0002_0311);
+    assert_eq!(Fmc::reg(BTR2), 0x0002_0411);
+    assert_eq!(Fmc::reg(BTR3), 0x0002_0E11);
+}
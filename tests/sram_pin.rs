@@ -0,0 +1,96 @@
+//! Tests SRAM pin constraints apply correctly
+#![cfg(feature = "sram")]
+
+mod dummy_pins;
+use dummy_pins::*;
+
+use stm32_fmc::*;
+
+/// Dummy FmcPeripheral implementation for testing
+struct DummyFMC;
+unsafe impl FmcPeripheral for DummyFMC {
+    const REGISTERS: *const () = core::ptr::null();
+    fn enable(&mut self) {}
+    fn source_clock_hz(&self) -> u32 {
+        100_000_000
+    }
+}
+
+macro_rules! fmc_pin_set {
+    ($($p:ident),*) => {
+        paste::item! {
+            (
+                $(
+                    [< PinThats $p:upper>] {}
+                ),*
+            )
+        }
+    }
+}
+
+const CONFIG: SramConfiguration = SramConfiguration {
+    memory_data_width: 16,
+    write_enable: true,
+    address_setup_cycles: 2,
+    address_hold_cycles: 1,
+    data_setup_cycles: 5,
+    bus_turnaround_cycles: 1,
+};
+
+#[test]
+/// SRAM with 16 bit data
+fn sram_pins_16bit() {
+    let fmc = DummyFMC {};
+    let pins = fmc_pin_set!(
+        D0, D1, D2, D3, D4, D5, D6, D7, D8, D9, D10, D11, D12, D13, D14, D15,
+        NBL0, NBL1, NE1, NOE, NWE
+    );
+
+    let _sram = Sram::new(fmc, pins, CONFIG);
+}
+
+#[test]
+#[should_panic]
+/// SRAM configured for 16 bit data, but only 8 data pins
+fn sram_pins_data_width_mismatch() {
+    let fmc = DummyFMC {};
+    let pins = fmc_pin_set!(D0, D1, D2, D3, D4, D5, D6, D7, NE1, NOE, NWE);
+
+    let _sram = Sram::new(fmc, pins, CONFIG);
+}
+
+#[test]
+/// SRAM with 8 bit data on sub-bank 2
+fn sram_pins_ne2() {
+    let fmc = DummyFMC {};
+    let pins = fmc_pin_set!(D0, D1, D2, D3, D4, D5, D6, D7, NE2, NOE, NWE);
+
+    let _sram = Sram::new(
+        fmc,
+        pins,
+        SramConfiguration {
+            memory_data_width: 8,
+            ..CONFIG
+        },
+    );
+}
+
+#[test]
+/// SRAM with 32 bit data on sub-bank 3
+fn sram_pins_32bit() {
+    let fmc = DummyFMC {};
+    let pins = fmc_pin_set!(
+        D0, D1, D2, D3, D4, D5, D6, D7, D8, D9, D10, D11, D12, D13, D14, D15,
+        D16, D17, D18, D19, D20, D21, D22, D23, D24, D25, D26, D27, D28, D29,
+        D30, D31, NBL0, NBL1, NBL2, NBL3, NE3, NOE, NWE
+    );
+
+    let _sram = Sram::new(
+        fmc,
+        pins,
+        SramConfiguration {
+            memory_data_width: 32,
+            ..CONFIG
+        },
+    );
+}